@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::stdweb::unstable::TryInto;
+use crate::gl_state::Gl;
+use crate::models::Mesh;
+use crate::webgl_rendering_context::{GLuint, WebGLProgram, WebGLRenderingContext};
+
+/// Maps attribute names used by shaders onto the names used in vertex layouts,
+/// so shaders written against other conventions still pick up mesh data.
+pub struct AttributeAliases {
+    aliases: HashMap<String, String>,
+}
+
+impl AttributeAliases {
+    pub fn new() -> Self {
+        let mut aliases = Self { aliases: HashMap::new() };
+        aliases.insert("aPosition", "aVertexPosition");
+        aliases.insert("position", "aVertexPosition");
+        aliases.insert("aNormal", "aVertexNormal");
+        aliases.insert("normal", "aVertexNormal");
+        aliases.insert("aUV", "aTextureCoord");
+        aliases.insert("aTexCoord", "aTextureCoord");
+        aliases.insert("uv", "aTextureCoord");
+        aliases.insert("aColor", "aVertexColor");
+        aliases.insert("color", "aVertexColor");
+        aliases
+    }
+
+    /// Registers `shader_name` as another name for the layout attribute `layout_name`.
+    pub fn insert(&mut self, shader_name: &str, layout_name: &str) {
+        self.aliases.insert(shader_name.to_string(), layout_name.to_string());
+    }
+
+    pub fn resolve<'a>(&'a self, shader_name: &'a str) -> &'a str {
+        self.aliases.get(shader_name).map(String::as_str).unwrap_or(shader_name)
+    }
+}
+
+impl Default for AttributeAliases {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An active program attribute matched to an entry of a mesh's vertex layout.
+#[derive(Debug, Clone)]
+pub struct AttributeBinding {
+    pub name: String,
+    pub layout_name: String,
+    pub location: GLuint,
+}
+
+/// The result of matching a program's active attributes against a mesh.
+#[derive(Debug, Clone, Default)]
+pub struct AttributeBindings {
+    pub bound: Vec<AttributeBinding>,
+    /// Attributes the program reads that the mesh has no data for, along with
+    /// their locations so they can be disabled.
+    pub missing: Vec<(String, GLuint)>,
+}
+
+impl AttributeBindings {
+    /// Enumerates the active attributes of a linked program and matches each
+    /// one to the mesh layout by name, falling back to the given aliases.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `program` - Linked shader program.
+    /// * `mesh` - Mesh whose vertex layout provides the data.
+    /// * `aliases` - Alternate attribute names to accept.
+    ///
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/getActiveAttrib
+    pub fn new(gl: &Gl, program: &WebGLProgram, mesh: &Mesh, aliases: &AttributeAliases) -> Self {
+        let mut bindings = Self::default();
+
+        let count: u32 = gl
+            .get_program_parameter(program, WebGLRenderingContext::ACTIVE_ATTRIBUTES)
+            .try_into()
+            .unwrap_or(0);
+
+        for index in 0..count {
+            let info = match gl.get_active_attrib(program, index) {
+                Some(info) => info,
+                None => continue,
+            };
+
+            let name = info.name();
+            if name.starts_with("gl_") {
+                continue;
+            }
+
+            let location = gl.get_attrib_location(program, &name);
+            if location < 0 {
+                continue;
+            }
+
+            let layout_name = aliases.resolve(&name).to_string();
+            if mesh.layout.get(&layout_name).is_some() {
+                bindings.bound.push(AttributeBinding { name, layout_name, location: location as GLuint });
+            } else {
+                bindings.missing.push((name, location as GLuint));
+            }
+        }
+
+        bindings
+    }
+
+    /// Points every bound attribute at the mesh's buffers. Missing attributes
+    /// are disabled so they read the constant default value instead of stale data.
+    pub fn apply(&self, gl: &Gl, mesh: &Mesh) {
+        for binding in &self.bound {
+            mesh.bind_attribute(gl, &binding.layout_name, binding.location);
+        }
+
+        for (_, location) in &self.missing {
+            gl.disable_vertex_attrib_array(*location);
+        }
+    }
+
+    /// Human readable warnings for attributes the mesh cannot provide.
+    pub fn warnings(&self) -> Vec<String> {
+        self.missing
+            .iter()
+            .map(|(name, _)| format!("The shader expects attribute `{}` but the mesh does not provide it", name))
+            .collect()
+    }
+}
@@ -0,0 +1,265 @@
+//! What shaders get to see of the audio: a spectrum, a waveform, the overall
+//! level and the level of a few frequency bands.
+
+use super::fft;
+
+/// Width of the audio texture, bins of the spectrum and samples of the
+/// waveform alike.
+pub const BINS: usize = 512;
+
+/// Samples analyzed every frame, the spectrum of `SAMPLES` real samples has
+/// `BINS` useful bins.
+pub const SAMPLES: usize = BINS * 2;
+
+/// Bass, mids and highs in Hz, the bands of `iAudioBands`.
+pub const BANDS: [(f32, f32); 3] = [(20.0, 250.0), (250.0, 4000.0), (4000.0, 20000.0)];
+
+/// The range of magnitudes the spectrum maps onto 0 to 1, in decibels. The
+/// same defaults as Web Audio's `AnalyserNode`, so spectra look familiar.
+const MIN_DECIBELS: f32 = -100.0;
+const MAX_DECIBELS: f32 = -30.0;
+
+/// How much of the previous frame's spectrum is kept, smoothing out flicker.
+const SMOOTHING: f32 = 0.8;
+
+/// The latest analysis of the audio channel.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// Magnitudes of each bin, smoothed over time.
+    magnitudes: Vec<f32>,
+    /// The magnitudes on a decibel scale, from 0 to 1.
+    spectrum: Vec<f32>,
+    /// The most recent samples, from -1 to 1.
+    waveform: Vec<f32>,
+    level: f32,
+    bands: [f32; 3],
+}
+
+impl Analysis {
+    /// An analysis of silence.
+    pub fn new() -> Self {
+        Self {
+            magnitudes: vec![0.0; BINS],
+            spectrum: vec![0.0; BINS],
+            waveform: vec![0.0; BINS],
+            level: 0.0,
+            bands: [0.0; 3],
+        }
+    }
+
+    /// Analyzes the latest samples.
+    ///
+    /// ## Arguments
+    ///
+    /// * `samples` - The last `SAMPLES` samples played, oldest first.
+    /// * `sample_rate` - Samples per second.
+    pub fn update(&mut self, samples: &[f32], sample_rate: f32) {
+        if samples.len() != SAMPLES {
+            return;
+        }
+
+        for (smoothed, magnitude) in self.magnitudes.iter_mut().zip(fft::magnitudes(samples)) {
+            *smoothed = SMOOTHING * *smoothed + (1.0 - SMOOTHING) * magnitude;
+        }
+        for (value, &magnitude) in self.spectrum.iter_mut().zip(&self.magnitudes) {
+            *value = normalize(magnitude);
+        }
+
+        self.waveform.copy_from_slice(&samples[SAMPLES - BINS..]);
+        self.level = level(samples);
+
+        let bin_width = sample_rate / SAMPLES as f32;
+        let bands = split_bands(&self.spectrum, bin_width, &BANDS);
+        self.bands.copy_from_slice(&bands);
+    }
+
+    /// Back to silence, when the audio stops.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Root mean square of the samples, 1 for a full scale square wave.
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Average of the spectrum over each of `BANDS`.
+    pub fn bands(&self) -> [f32; 3] {
+        self.bands
+    }
+
+    /// The audio texture, `BINS` by 2 RGBA pixels: the spectrum in the row at
+    /// y = 0 and the waveform, mapped from -1 to 1 onto 0 to 1, at y = 1.
+    /// Every channel holds the value, so any can be read.
+    pub fn pixels(&self) -> Vec<u8> {
+        let waveform = self.waveform.iter().map(|sample| sample * 0.5 + 0.5);
+        self.spectrum
+            .iter()
+            .cloned()
+            .chain(waveform)
+            .flat_map(|value| vec![(value.clamp(0.0, 1.0) * 255.0).round() as u8; 4])
+            .collect()
+    }
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Maps a magnitude onto 0 to 1 between `MIN_DECIBELS` and `MAX_DECIBELS`.
+fn normalize(magnitude: f32) -> f32 {
+    if magnitude <= 0.0 {
+        return 0.0;
+    }
+    let decibels = 20.0 * magnitude.log10();
+    ((decibels - MIN_DECIBELS) / (MAX_DECIBELS - MIN_DECIBELS)).clamp(0.0, 1.0)
+}
+
+/// Root mean square of the samples.
+pub fn level(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Averages a spectrum over frequency bands.
+///
+/// A bin belongs to the band its center frequency falls in, including the
+/// lower edge but not the upper one. Bands without a bin, e.g. above the
+/// highest frequency the sample rate can represent, are 0.
+///
+/// ## Arguments
+///
+/// * `spectrum` - A value per bin, bin `k` centered on `k * bin_width`.
+/// * `bin_width` - Frequency between two bins in Hz.
+/// * `bands` - Lowest and highest frequency of each band in Hz.
+pub fn split_bands(spectrum: &[f32], bin_width: f32, bands: &[(f32, f32)]) -> Vec<f32> {
+    bands
+        .iter()
+        .map(|&(low, high)| {
+            let (sum, count) = spectrum
+                .iter()
+                .enumerate()
+                .filter(|&(bin, _)| {
+                    let frequency = bin as f32 * bin_width;
+                    frequency >= low && frequency < high
+                })
+                .fold((0.0, 0), |(sum, count), (_, value)| (sum + value, count + 1));
+
+            if count == 0 {
+                0.0
+            } else {
+                sum / count as f32
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn sine(frequency: f32, sample_rate: f32) -> Vec<f32> {
+        (0..SAMPLES).map(|i| (2.0 * PI * frequency * i as f32 / sample_rate).sin()).collect()
+    }
+
+    #[test]
+    fn bands_average_their_bins() {
+        let spectrum = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+
+        let bands = split_bands(&spectrum, 10.0, &[(0.0, 20.0), (20.0, 50.0), (50.0, 60.0)]);
+
+        assert_eq!(bands, vec![1.5, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn bands_without_bins_are_silent() {
+        let bands = split_bands(&[1.0; 4], 10.0, &[(15.0, 19.0), (100.0, 200.0)]);
+
+        assert_eq!(bands, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn bass_lands_in_the_first_band() {
+        let sample_rate = 48000.0;
+        let mut analysis = Analysis::new();
+
+        // Long enough for the smoothing to settle.
+        for _ in 0..50 {
+            analysis.update(&sine(93.75, sample_rate), sample_rate);
+        }
+
+        let [bass, mids, highs] = analysis.bands();
+        assert!(bass > mids && bass > highs, "{:?}", analysis.bands());
+        assert!(highs < 0.05, "{:?}", analysis.bands());
+    }
+
+    #[test]
+    fn highs_land_in_the_last_band() {
+        let sample_rate = 44100.0;
+        let mut analysis = Analysis::new();
+
+        for _ in 0..50 {
+            analysis.update(&sine(8000.0, sample_rate), sample_rate);
+        }
+
+        let [bass, mids, highs] = analysis.bands();
+        assert!(highs > mids && highs > bass, "{:?}", analysis.bands());
+    }
+
+    #[test]
+    fn level_is_the_root_mean_square() {
+        assert_eq!(level(&[0.0; 8]), 0.0);
+        assert_eq!(level(&[1.0, -1.0, 1.0, -1.0]), 1.0);
+        assert!((level(&sine(1000.0, 48000.0)) - 0.5f32.sqrt()).abs() < 0.01);
+        assert_eq!(level(&[]), 0.0);
+    }
+
+    #[test]
+    fn normalizes_decibels() {
+        assert_eq!(normalize(0.0), 0.0);
+        assert_eq!(normalize(1.0), 1.0);
+        assert!((normalize(10f32.powf(-65.0 / 20.0)) - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ignores_the_wrong_number_of_samples() {
+        let mut analysis = Analysis::new();
+
+        analysis.update(&[1.0; 100], 48000.0);
+
+        assert_eq!(analysis.level(), 0.0);
+    }
+
+    #[test]
+    fn texture_holds_spectrum_then_waveform() {
+        let mut analysis = Analysis::new();
+        let mut samples = vec![0.0; SAMPLES];
+        samples[SAMPLES - BINS] = -1.0;
+        samples[SAMPLES - 1] = 1.0;
+
+        analysis.update(&samples, 48000.0);
+        let pixels = analysis.pixels();
+
+        assert_eq!(pixels.len(), BINS * 2 * 4);
+        let waveform = &pixels[BINS * 4..];
+        assert_eq!(&waveform[..4], &[0, 0, 0, 0]);
+        assert_eq!(&waveform[4..8], &[128, 128, 128, 128]);
+        assert_eq!(&waveform[waveform.len() - 4..], &[255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn clear_goes_back_to_silence() {
+        let mut analysis = Analysis::new();
+        analysis.update(&sine(440.0, 48000.0), 48000.0);
+
+        analysis.clear();
+
+        assert_eq!(analysis.level(), 0.0);
+        assert!(analysis.spectrum.iter().all(|&value| value == 0.0));
+    }
+}
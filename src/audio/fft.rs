@@ -0,0 +1,185 @@
+//! Fast Fourier transform of the audio channel's samples.
+
+use std::f32::consts::PI;
+
+/// Transforms a signal into its spectrum in place, with the iterative radix-2
+/// Cooley-Tukey algorithm.
+///
+/// ## Arguments
+///
+/// * `real` - Real parts, the samples before and the spectrum after.
+/// * `imag` - Imaginary parts, zeros for real samples.
+///
+/// Both have to be as long as each other, and a power of two.
+pub fn fft(real: &mut [f32], imag: &mut [f32]) {
+    let n = real.len();
+    assert!(n.is_power_of_two(), "The FFT's length has to be a power of two, not {}", n);
+    assert_eq!(n, imag.len(), "The real and imaginary parts have to be as long as each other");
+
+    // Reorder by bit reversed index, so every stage combines neighbours.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j ^= bit;
+        if i < j {
+            real.swap(i, j);
+            imag.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= n {
+        let half = size / 2;
+        for k in 0..half {
+            let angle = -2.0 * PI * k as f32 / size as f32;
+            let (twiddle_re, twiddle_im) = (angle.cos(), angle.sin());
+
+            for start in (0..n).step_by(size) {
+                let (a, b) = (start + k, start + k + half);
+                let re = real[b] * twiddle_re - imag[b] * twiddle_im;
+                let im = real[b] * twiddle_im + imag[b] * twiddle_re;
+                real[b] = real[a] - re;
+                imag[b] = imag[a] - im;
+                real[a] += re;
+                imag[a] += im;
+            }
+        }
+        size *= 2;
+    }
+}
+
+/// Magnitudes of the first half of a real signal's spectrum, after a Blackman
+/// window like the one Web Audio's `AnalyserNode` uses, so a full scale sine
+/// peaks at about 0.21 rather than leaking into every bin.
+///
+/// Bin `k` is the frequency `k * sample_rate / samples.len()`.
+pub fn magnitudes(samples: &[f32]) -> Vec<f32> {
+    let n = samples.len();
+    let mut real: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| sample * blackman(i, n))
+        .collect();
+    let mut imag = vec![0.0; n];
+
+    fft(&mut real, &mut imag);
+
+    real.iter()
+        .zip(&imag)
+        .take(n / 2)
+        .map(|(re, im)| (re * re + im * im).sqrt() / n as f32)
+        .collect()
+}
+
+/// The Blackman window's weight of sample `i` out of `n`.
+fn blackman(i: usize, n: usize) -> f32 {
+    let x = 2.0 * PI * i as f32 / n as f32;
+    0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-3, "{} isn't close to {}", actual, expected);
+    }
+
+    /// The transform by its definition, slow but obviously right.
+    fn dft(samples: &[f32]) -> Vec<(f32, f32)> {
+        let n = samples.len();
+        (0..n)
+            .map(|k| {
+                samples.iter().enumerate().fold((0.0, 0.0), |(re, im), (i, sample)| {
+                    let angle = -2.0 * PI * (k * i) as f32 / n as f32;
+                    (re + sample * angle.cos(), im + sample * angle.sin())
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn impulse_has_a_flat_spectrum() {
+        let mut real = vec![0.0; 16];
+        let mut imag = vec![0.0; 16];
+        real[0] = 1.0;
+
+        fft(&mut real, &mut imag);
+
+        for (re, im) in real.iter().zip(&imag) {
+            assert_close(*re, 1.0);
+            assert_close(*im, 0.0);
+        }
+    }
+
+    #[test]
+    fn cosine_peaks_at_its_frequency() {
+        let n = 64;
+        let mut real: Vec<f32> = (0..n).map(|i| (2.0 * PI * 5.0 * i as f32 / n as f32).cos()).collect();
+        let mut imag = vec![0.0; n];
+
+        fft(&mut real, &mut imag);
+
+        for k in 0..n {
+            let expected = if k == 5 || k == n - 5 { n as f32 / 2.0 } else { 0.0 };
+            assert_close(real[k], expected);
+            assert_close(imag[k], 0.0);
+        }
+    }
+
+    #[test]
+    fn matches_the_definition() {
+        let samples: Vec<f32> = (0..32).map(|i| ((i * 7 % 11) as f32 - 5.0) / 5.0).collect();
+        let mut real = samples.clone();
+        let mut imag = vec![0.0; samples.len()];
+
+        fft(&mut real, &mut imag);
+
+        for (k, (re, im)) in dft(&samples).into_iter().enumerate() {
+            assert_close(real[k], re);
+            assert_close(imag[k], im);
+        }
+    }
+
+    #[test]
+    fn single_sample_is_its_own_spectrum() {
+        let mut real = vec![0.5];
+        let mut imag = vec![0.0];
+
+        fft(&mut real, &mut imag);
+
+        assert_eq!((real[0], imag[0]), (0.5, 0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_lengths_that_arent_powers_of_two() {
+        fft(&mut [0.0; 12], &mut [0.0; 12]);
+    }
+
+    #[test]
+    fn magnitudes_cover_half_the_spectrum() {
+        let n = 1024;
+        let samples: Vec<f32> = (0..n).map(|i| (2.0 * PI * 100.0 * i as f32 / n as f32).sin()).collect();
+
+        let magnitudes = magnitudes(&samples);
+
+        assert_eq!(magnitudes.len(), n / 2);
+        let peak = (0..magnitudes.len()).max_by(|&a, &b| magnitudes[a].partial_cmp(&magnitudes[b]).unwrap());
+        assert_eq!(peak, Some(100));
+        // Half the amplitude lands in the positive frequencies, scaled by the
+        // window's average of 0.42.
+        assert_close(magnitudes[100], 0.21);
+        // Far from the peak the window keeps leakage down.
+        assert!(magnitudes[200] < 1e-4);
+    }
+
+    #[test]
+    fn silence_has_no_magnitude() {
+        assert!(magnitudes(&[0.0; 64]).iter().all(|&magnitude| magnitude == 0.0));
+    }
+}
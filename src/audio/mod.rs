@@ -0,0 +1,245 @@
+pub mod analysis;
+pub mod fft;
+
+use stdweb::unstable::TryInto;
+use stdweb::web::{File, TypedArray};
+use stdweb::{js, Value};
+
+use crate::gl_state::Gl;
+use crate::resources::{Description, Registry, Resource};
+use crate::webgl_rendering_context::{WebGLRenderingContext, WebGLTexture};
+
+pub use self::analysis::Analysis;
+use self::analysis::{BINS, SAMPLES};
+
+/// Audio played or recorded through Web Audio, with an `AnalyserNode` at the
+/// end of the graph to read the latest samples from. The analysis itself is
+/// done in Rust.
+///
+/// Dropping the source stops it and closes its context.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/AnalyserNode
+pub struct AudioSource {
+    /// The graph's JavaScript side, `input` is set once there's audio.
+    graph: Value,
+    sample_rate: f32,
+}
+
+impl AudioSource {
+    /// Decodes an audio file and plays it on a loop.
+    ///
+    /// ## Arguments
+    ///
+    /// * `file` - The file, in any format the browser can decode.
+    /// * `on_error` - Called with a message if it can't be read or decoded.
+    pub fn file(file: &File, on_error: impl Fn(String) + 'static) -> Self {
+        let source = Self::new(on_error);
+
+        js! { @(no_return)
+            var graph = @{&source.graph};
+            var file = @{file};
+            var reader = new FileReader();
+            reader.onload = function () {
+                graph.context.decodeAudioData(reader.result, function (buffer) {
+                    if (graph.closed) {
+                        return;
+                    }
+                    var node = graph.context.createBufferSource();
+                    node.buffer = buffer;
+                    node.loop = true;
+                    node.connect(graph.analyser);
+                    graph.analyser.connect(graph.context.destination);
+                    node.start();
+                    graph.input = node;
+                }, function () {
+                    graph.error("Unable to decode " + file.name);
+                });
+            };
+            reader.onerror = function () {
+                graph.error("Unable to read " + file.name);
+            };
+            reader.readAsArrayBuffer(file);
+        };
+
+        source
+    }
+
+    /// Listens to the microphone, once the user allows it. It isn't played
+    /// back, that would feed back into it.
+    ///
+    /// ## Arguments
+    ///
+    /// * `on_error` - Called with a message if there's no microphone or it's not allowed.
+    ///
+    /// https://developer.mozilla.org/en-US/docs/Web/API/MediaDevices/getUserMedia
+    pub fn microphone(on_error: impl Fn(String) + 'static) -> Self {
+        let source = Self::new(on_error);
+
+        js! { @(no_return)
+            var graph = @{&source.graph};
+            if (!navigator.mediaDevices || !navigator.mediaDevices.getUserMedia) {
+                graph.error("This browser can't record audio");
+                return;
+            }
+            navigator.mediaDevices.getUserMedia({ audio: true }).then(function (stream) {
+                graph.stream = stream;
+                if (graph.closed) {
+                    stream.getTracks().forEach(function (track) {
+                        track.stop();
+                    });
+                    return;
+                }
+                graph.input = graph.context.createMediaStreamSource(stream);
+                graph.input.connect(graph.analyser);
+            }).catch(function (error) {
+                graph.error("Unable to use the microphone: " + error.message);
+            });
+        };
+
+        source
+    }
+
+    /// Creates the context and the analyser, without any input yet.
+    fn new(on_error: impl Fn(String) + 'static) -> Self {
+        let graph = js! {
+            var report = @{on_error};
+            var Context = window.AudioContext || window.webkitAudioContext;
+            var context = new Context();
+            // Created in response to a click, but browsers may still start
+            // it suspended.
+            context.resume();
+            var analyser = context.createAnalyser();
+            analyser.fftSize = @{SAMPLES as u32};
+            var graph = {
+                context: context,
+                analyser: analyser,
+                samples: new Float32Array(analyser.fftSize),
+                input: null,
+                stream: null,
+                closed: false,
+                report: report
+            };
+            // Decoding and asking for the microphone finish later, maybe
+            // after the source was dropped and `report` with it.
+            graph.error = function (message) {
+                if (!graph.closed) {
+                    report(message);
+                }
+            };
+            return graph;
+        };
+        let sample_rate: f64 = js! { return @{&graph}.context.sampleRate; }.try_into().unwrap_or(44100.0);
+
+        Self { graph, sample_rate: sample_rate as f32 }
+    }
+
+    /// The last `SAMPLES` samples, `None` until the audio starts.
+    pub fn samples(&self) -> Option<Vec<f32>> {
+        let samples: TypedArray<f32> = js! {
+            var graph = @{&self.graph};
+            if (!graph.input) {
+                return null;
+            }
+            graph.analyser.getFloatTimeDomainData(graph.samples);
+            return graph.samples;
+        }
+        .try_into()
+        .ok()?;
+
+        Some(samples.to_vec())
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+}
+
+impl Drop for AudioSource {
+    fn drop(&mut self) {
+        js! { @(no_return)
+            var graph = @{&self.graph};
+            graph.closed = true;
+            if (graph.stream) {
+                graph.stream.getTracks().forEach(function (track) {
+                    track.stop();
+                });
+            }
+            graph.context.close();
+            graph.report.drop();
+        };
+    }
+}
+
+/// The audio shaders see: the `iAudio` texture and what's behind
+/// `iAudioLevel` and `iAudioBands`, silence while nothing plays.
+pub struct AudioChannel {
+    source: Option<AudioSource>,
+    analysis: Analysis,
+    texture: Resource,
+    /// Whether the analysis changed since the texture was updated.
+    changed: bool,
+}
+
+impl AudioChannel {
+    /// Creates the channel's texture, silent until a source is set.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `registry` - Tracks the texture so it can be restored with the context.
+    pub fn new(gl: &Gl, registry: &mut Registry) -> Result<Self, String> {
+        let analysis = Analysis::new();
+        let texture = registry.create(gl, description(&analysis))?;
+
+        Ok(Self { source: None, analysis, texture, changed: false })
+    }
+
+    /// Replaces the audio, stopping what played before. `None` stops it.
+    pub fn set_source(&mut self, source: Option<AudioSource>) {
+        self.source = source;
+        self.analysis.clear();
+        self.changed = true;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.source.is_some()
+    }
+
+    pub fn analysis(&self) -> &Analysis {
+        &self.analysis
+    }
+
+    /// `None` while the context is lost.
+    pub fn texture(&self) -> Option<WebGLTexture> {
+        self.texture.texture()
+    }
+
+    /// Analyzes the latest samples and updates the texture, call once per
+    /// frame.
+    pub fn update(&mut self, gl: &Gl) {
+        if let Some(source) = self.source.as_ref() {
+            if let Some(samples) = source.samples() {
+                self.analysis.update(&samples, source.sample_rate());
+                self.changed = true;
+            }
+        }
+
+        if self.changed {
+            // Always a texture, which `update` accepts.
+            self.texture.update(gl, description(&self.analysis)).unwrap();
+            self.changed = false;
+        }
+    }
+}
+
+/// The texture holding an analysis, `BINS` by 2 and sampled linearly like
+/// Shadertoy's audio channels.
+fn description(analysis: &Analysis) -> Description {
+    Description::Texture {
+        width: BINS as i32,
+        height: 2,
+        filter: WebGLRenderingContext::LINEAR,
+        wrap: WebGLRenderingContext::CLAMP_TO_EDGE,
+        pixels: Some(analysis.pixels()),
+    }
+}
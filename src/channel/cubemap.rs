@@ -0,0 +1,263 @@
+//! Cubemaps from six face images or from an equirectangular panorama.
+
+use std::f32::consts::PI;
+
+/// The faces in the order of `TEXTURE_CUBE_MAP_POSITIVE_X` and the targets
+/// after it, with the names face images are recognized by.
+pub const FACES: [(&str, &[&str]); 6] = [
+    ("+X", &["px", "posx", "positivex", "right"]),
+    ("-X", &["nx", "negx", "negativex", "left"]),
+    ("+Y", &["py", "posy", "positivey", "top", "up"]),
+    ("-Y", &["ny", "negy", "negativey", "bottom", "down"]),
+    ("+Z", &["pz", "posz", "positivez", "front"]),
+    ("-Z", &["nz", "negz", "negativez", "back"]),
+];
+
+/// Orders six face images by their file names, e.g. `posx.jpg` or
+/// `skybox_right.png`, returning for each face the index of its image.
+///
+/// A name matches a face when one of the face's names is a word of it,
+/// words being split at anything but letters and digits.
+pub fn face_order(names: &[&str]) -> Result<[usize; 6], String> {
+    if names.len() != 6 {
+        return Err(format!("A cubemap needs six face images, not {}", names.len()));
+    }
+
+    let mut order = [0; 6];
+    for (face, (label, aliases)) in FACES.iter().enumerate() {
+        let matches: Vec<usize> = names
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| words(name).iter().any(|word| aliases.contains(&word.as_str())))
+            .map(|(index, _)| index)
+            .collect();
+
+        order[face] = match matches.as_slice() {
+            [index] => *index,
+            [] => return Err(format!("None of the images is named like the {} face, e.g. {}", label, aliases[1])),
+            _ => return Err(format!("More than one image is named like the {} face", label)),
+        };
+    }
+
+    Ok(order)
+}
+
+/// The lowercase words of a file name without its extension. Names like
+/// `positive_x` also count as one word, `positivex`.
+fn words(name: &str) -> Vec<String> {
+    let stem = match name.rfind('.') {
+        Some(dot) if dot > 0 => &name[..dot],
+        _ => name,
+    };
+    let words: Vec<String> = stem
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+
+    let joined = words.windows(2).map(|pair| pair.concat());
+    words.iter().cloned().chain(joined).collect()
+}
+
+/// The direction a texel of a cubemap face points in, as defined by the
+/// cube map face selection table of the OpenGL ES specification.
+///
+/// ## Arguments
+///
+/// * `face` - Index of the face in `FACES`.
+/// * `s` - Horizontal texture coordinate on the face, from 0 to 1.
+/// * `t` - Vertical texture coordinate on the face, from 0 to 1, 0 being the first row.
+pub fn direction(face: usize, s: f32, t: f32) -> [f32; 3] {
+    let (sc, tc) = (2.0 * s - 1.0, 2.0 * t - 1.0);
+    match face {
+        0 => [1.0, -tc, -sc],
+        1 => [-1.0, -tc, sc],
+        2 => [sc, 1.0, tc],
+        3 => [sc, -1.0, -tc],
+        4 => [sc, -tc, 1.0],
+        _ => [-sc, -tc, -1.0],
+    }
+}
+
+/// Converts an equirectangular panorama into the six faces of a cubemap.
+///
+/// The panorama's first row looks straight up and its center looks down
+/// -Z, the way a camera without rotation does.
+///
+/// ## Arguments
+///
+/// * `pixels` - RGBA pixels of the panorama, first row first.
+/// * `width` - Width of the panorama, usually twice its height.
+/// * `height` - Height of the panorama.
+/// * `size` - Width and height of each face.
+pub fn from_panorama(pixels: &[u8], width: usize, height: usize, size: usize) -> Vec<Vec<u8>> {
+    (0..FACES.len())
+        .map(|face| {
+            let mut texels = Vec::with_capacity(size * size * 4);
+            for y in 0..size {
+                for x in 0..size {
+                    let s = (x as f32 + 0.5) / size as f32;
+                    let t = (y as f32 + 0.5) / size as f32;
+                    let [dx, dy, dz] = direction(face, s, t);
+                    let length = (dx * dx + dy * dy + dz * dz).sqrt();
+
+                    let u = 0.5 + dx.atan2(-dz) / (2.0 * PI);
+                    let v = (dy / length).acos() / PI;
+                    texels.extend_from_slice(&sample(pixels, width, height, u, v));
+                }
+            }
+            texels
+        })
+        .collect()
+}
+
+/// The size of faces that keep about as much detail as a panorama has: a
+/// face spans a quarter of its width.
+pub fn face_size(width: usize) -> usize {
+    (width / 4).max(1).next_power_of_two()
+}
+
+/// Samples a panorama bilinearly, wrapping around horizontally and clamping
+/// at the poles.
+fn sample(pixels: &[u8], width: usize, height: usize, u: f32, v: f32) -> [u8; 4] {
+    let x = u * width as f32 - 0.5;
+    let y = (v * height as f32 - 0.5).max(0.0).min(height as f32 - 1.0);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+
+    let column = |x: f32| (x as i64).rem_euclid(width as i64) as usize;
+    let row = |y: f32| (y as usize).min(height - 1);
+    let texel = |column: usize, row: usize, channel: usize| f32::from(pixels[(row * width + column) * 4 + channel]);
+
+    let (left, right) = (column(x0), column(x0 + 1.0));
+    let (top, bottom) = (row(y0), row(y0 + 1.0));
+
+    let mut result = [0; 4];
+    for (channel, value) in result.iter_mut().enumerate() {
+        let upper = texel(left, top, channel) * (1.0 - fx) + texel(right, top, channel) * fx;
+        let lower = texel(left, bottom, channel) * (1.0 - fx) + texel(right, bottom, channel) * fx;
+        *value = (upper * (1.0 - fy) + lower * fy).round() as u8;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A panorama colored by direction: red looking up, green down, and
+    /// around the horizon blue for -Z, white for +X, black for +Z and gray
+    /// for -X.
+    fn panorama(width: usize, height: usize) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let color = if y < height / 4 {
+                    [255, 0, 0, 255]
+                } else if y >= height * 3 / 4 {
+                    [0, 255, 0, 255]
+                } else {
+                    // Quarters of the horizon centered on each axis.
+                    match (x * 8 / width + 1) % 8 / 2 {
+                        0 => [0, 0, 0, 255],
+                        1 => [128, 128, 128, 255],
+                        2 => [0, 0, 255, 255],
+                        _ => [255, 255, 255, 255],
+                    }
+                };
+                pixels.extend_from_slice(&color);
+            }
+        }
+        pixels
+    }
+
+    fn center(face: &[u8], size: usize) -> &[u8] {
+        let offset = (size / 2 * size + size / 2) * 4;
+        &face[offset..offset + 4]
+    }
+
+    #[test]
+    fn faces_point_along_their_axes() {
+        let axes = [
+            [1.0, 0.0, 0.0],
+            [-1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, -1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0],
+        ];
+
+        for (face, axis) in axes.iter().enumerate() {
+            assert_eq!(&direction(face, 0.5, 0.5), axis);
+        }
+    }
+
+    #[test]
+    fn side_faces_have_up_in_their_first_row() {
+        for &face in &[0, 1, 4, 5] {
+            assert!(direction(face, 0.5, 0.0)[1] > 0.0);
+        }
+        // The +Y face's first row is toward -Z.
+        assert!(direction(2, 0.5, 0.0)[2] < 0.0);
+    }
+
+    #[test]
+    fn panorama_lands_on_the_right_faces() {
+        let faces = from_panorama(&panorama(64, 32), 64, 32, 8);
+
+        assert_eq!(faces.len(), 6);
+        assert!(faces.iter().all(|face| face.len() == 8 * 8 * 4));
+        assert_eq!(center(&faces[0], 8), &[255, 255, 255, 255]);
+        assert_eq!(center(&faces[1], 8), &[128, 128, 128, 255]);
+        assert_eq!(center(&faces[2], 8), &[255, 0, 0, 255]);
+        assert_eq!(center(&faces[3], 8), &[0, 255, 0, 255]);
+        assert_eq!(center(&faces[4], 8), &[0, 0, 0, 255]);
+        assert_eq!(center(&faces[5], 8), &[0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn samples_wrap_around_horizontally() {
+        let mut pixels = [0, 0, 0, 255].repeat(4);
+        pixels[0..3].copy_from_slice(&[200, 200, 200]);
+
+        // Halfway between the last column and the first, wrapped around.
+        assert_eq!(sample(&pixels, 4, 1, 1.0, 0.5), [100, 100, 100, 255]);
+    }
+
+    #[test]
+    fn orders_faces_by_name() {
+        let names = ["negz.jpg", "posx.jpg", "negx.jpg", "posy.jpg", "negy.jpg", "posz.jpg"];
+
+        assert_eq!(face_order(&names), Ok([1, 2, 3, 4, 5, 0]));
+    }
+
+    #[test]
+    fn recognizes_other_naming_schemes() {
+        let names = ["sky_right.png", "sky_left.png", "sky_top.png", "sky_bottom.png", "sky_front.png", "sky_back.png"];
+        assert_eq!(face_order(&names), Ok([0, 1, 2, 3, 4, 5]));
+
+        let names = [
+            "positive_x.png",
+            "negative_x.png",
+            "positive_y.png",
+            "negative_y.png",
+            "positive_z.png",
+            "negative_z.png",
+        ];
+        assert_eq!(face_order(&names), Ok([0, 1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn rejects_missing_and_ambiguous_faces() {
+        assert!(face_order(&["px.png"; 5]).is_err());
+        assert!(face_order(&["px.png", "nx.png", "py.png", "ny.png", "pz.png", "sky.png"]).is_err());
+        assert!(face_order(&["px.png", "px_nx.png", "py.png", "ny.png", "pz.png", "nz.png"]).is_err());
+    }
+
+    #[test]
+    fn faces_keep_a_quarter_of_the_panorama() {
+        assert_eq!(face_size(2048), 512);
+        assert_eq!(face_size(1000), 256);
+        assert_eq!(face_size(2), 1);
+    }
+}
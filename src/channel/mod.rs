@@ -0,0 +1,330 @@
+pub mod cubemap;
+pub mod volume;
+
+use stdweb::js;
+use stdweb::web::{File, TypedArray};
+
+use crate::gl_state::Gl;
+use crate::resources::{Description, Registry, Resource};
+use crate::uniform::type_name;
+use crate::webgl_rendering_context::{GLenum, WebGL2RenderingContext, WebGLRenderingContext, WebGLTexture};
+
+/// Number of `iChannel` samplers.
+pub const CHANNELS: usize = 4;
+
+/// Faces converted from a panorama are at most this wide, the conversion
+/// runs on the CPU.
+const MAX_PANORAMA_FACE_SIZE: usize = 1024;
+
+/// What a channel makes of its images.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// A 2D texture from one image.
+    Image,
+    /// A cubemap from six images named after their faces, see
+    /// `cubemap::face_order`.
+    Cubemap,
+    /// A cubemap from one equirectangular panorama.
+    Panorama,
+    /// A 3D texture from one image of its slices, see `volume::from_slices`.
+    Volume,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 4] = [Kind::Image, Kind::Cubemap, Kind::Panorama, Kind::Volume];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Image => "Image",
+            Kind::Cubemap => "Cubemap faces",
+            Kind::Panorama => "Panorama",
+            Kind::Volume => "Volume",
+        }
+    }
+
+    /// The type of the samplers that read textures of this kind.
+    pub fn sampler(self) -> GLenum {
+        match self {
+            Kind::Image => WebGLRenderingContext::SAMPLER_2D,
+            Kind::Cubemap | Kind::Panorama => WebGLRenderingContext::SAMPLER_CUBE,
+            Kind::Volume => WebGL2RenderingContext::SAMPLER_3D,
+        }
+    }
+
+    /// The target textures of this kind are bound to.
+    pub fn target(self) -> GLenum {
+        match self {
+            Kind::Image => WebGLRenderingContext::TEXTURE_2D,
+            Kind::Cubemap | Kind::Panorama => WebGLRenderingContext::TEXTURE_CUBE_MAP,
+            Kind::Volume => WebGL2RenderingContext::TEXTURE_3D,
+        }
+    }
+
+    /// Whether it's made of more than one image.
+    pub fn is_multiple(self) -> bool {
+        self == Kind::Cubemap
+    }
+}
+
+/// An image decoded to RGBA pixels, first row first.
+pub struct Image {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+/// Decodes image files in any format the browser can, all at once.
+///
+/// ## Arguments
+///
+/// * `files` - The image files.
+/// * `on_load` - Called with the images in the order of the files.
+/// * `on_error` - Called with a message if any of them can't be decoded.
+pub fn decode(files: Vec<File>, on_load: impl Fn(Vec<Image>) + 'static, on_error: impl Fn(String) + 'static) {
+    let loaded = move |names: Vec<String>, widths: Vec<u32>, heights: Vec<u32>, pixels: Vec<TypedArray<u8>>| {
+        let images = names
+            .into_iter()
+            .zip(widths)
+            .zip(heights)
+            .zip(pixels)
+            .map(|(((name, width), height), pixels)| Image {
+                name,
+                width: width as usize,
+                height: height as usize,
+                pixels: pixels.to_vec(),
+            })
+            .collect();
+        on_load(images);
+    };
+
+    js! { @(no_return)
+        var loaded = @{loaded};
+        var failed = @{on_error};
+        function done() {
+            loaded.drop();
+            failed.drop();
+        }
+
+        Promise.all(@{files}.map(function (file) {
+            return new Promise(function (resolve, reject) {
+                var url = URL.createObjectURL(file);
+                var image = new Image();
+                image.onload = function () {
+                    URL.revokeObjectURL(url);
+                    var canvas = document.createElement("canvas");
+                    canvas.width = image.naturalWidth;
+                    canvas.height = image.naturalHeight;
+                    var context = canvas.getContext("2d");
+                    context.drawImage(image, 0, 0);
+                    var data = context.getImageData(0, 0, canvas.width, canvas.height).data;
+                    resolve({
+                        name: file.name,
+                        width: canvas.width,
+                        height: canvas.height,
+                        pixels: new Uint8Array(data.buffer)
+                    });
+                };
+                image.onerror = function () {
+                    URL.revokeObjectURL(url);
+                    reject("Unable to decode " + file.name);
+                };
+                image.src = url;
+            });
+        })).then(function (images) {
+            loaded(
+                images.map(function (image) { return image.name; }),
+                images.map(function (image) { return image.width; }),
+                images.map(function (image) { return image.height; }),
+                images.map(function (image) { return image.pixels; })
+            );
+            done();
+        }).catch(function (error) {
+            failed(String(error));
+            done();
+        });
+    };
+}
+
+/// Encodes RGBA pixels as a PNG data URL, drawing them on a canvas.
+fn encode(width: i32, height: i32, pixels: &[u8]) -> String {
+    let pixels = TypedArray::<u8>::from(pixels);
+    let url = js! {
+        var canvas = document.createElement("canvas");
+        canvas.width = @{width};
+        canvas.height = @{height};
+        var context = canvas.getContext("2d");
+        var image = context.createImageData(canvas.width, canvas.height);
+        image.data.set(@{pixels});
+        context.putImageData(image, 0, 0);
+        return canvas.toDataURL("image/png");
+    };
+    url.into_string().unwrap_or_default()
+}
+
+/// What a channel holds once images are loaded into it.
+struct Loaded {
+    kind: Kind,
+    texture: Resource,
+    /// A short description, e.g. `512² cubemap`.
+    summary: String,
+}
+
+/// A texture bound to one of the `iChannel` samplers: an image, a cubemap
+/// or a volume.
+pub struct Channel {
+    /// What the next images make.
+    kind: Kind,
+    loaded: Option<Loaded>,
+}
+
+impl Channel {
+    /// An empty channel, expecting an image.
+    pub fn new() -> Self {
+        Self { kind: Kind::Image, loaded: None }
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn set_kind(&mut self, kind: Kind) {
+        self.kind = kind;
+    }
+
+    /// Expects what a sampler of the type the shader declared reads, keeping
+    /// the kind when it already fits, e.g. a panorama for a `samplerCube`.
+    pub fn follow_sampler(&mut self, type_: GLenum) {
+        if self.kind.sampler() != type_ {
+            if let Some(kind) = Kind::ALL.iter().find(|kind| kind.sampler() == type_) {
+                self.kind = *kind;
+            }
+        }
+    }
+
+    /// Why a sampler of the given type can't read what the channel holds,
+    /// `None` when it can or there's nothing loaded.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - Name of the sampler.
+    /// * `type_` - The type the sampler is declared with.
+    pub fn mismatch(&self, name: &str, type_: GLenum) -> Option<String> {
+        let loaded = self.loaded.as_ref()?;
+        if loaded.kind.sampler() == type_ {
+            return None;
+        }
+
+        Some(format!(
+            "`{}` is declared as {} but holds a {}, which a {} reads",
+            name,
+            type_name(type_),
+            loaded.summary,
+            type_name(loaded.kind.sampler())
+        ))
+    }
+
+    /// Makes a texture of the channel's kind from decoded images, replacing
+    /// the one before.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `registry` - Tracks the texture so it can be restored with the context.
+    /// * `images` - The decoded images, six for a cubemap and one otherwise.
+    pub fn load(&mut self, gl: &Gl, registry: &mut Registry, images: Vec<Image>) -> Result<(), String> {
+        let (description, summary) = describe(self.kind, images)?;
+        let texture = registry.create(gl, description)?;
+        self.loaded = Some(Loaded { kind: self.kind, texture, summary });
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.loaded = None;
+    }
+
+    pub fn summary(&self) -> Option<&str> {
+        self.loaded.as_ref().map(|loaded| loaded.summary.as_str())
+    }
+
+    /// The loaded image as a PNG data URL, `None` while the channel is empty
+    /// or holds a cubemap or volume.
+    pub fn data_url(&self) -> Option<String> {
+        match self.loaded.as_ref()?.texture.description() {
+            Description::Texture { width, height, pixels: Some(pixels), .. } => Some(encode(width, height, &pixels)),
+            _ => None,
+        }
+    }
+
+    /// The texture and the target it's bound to, `None` while the channel
+    /// is empty or the context is lost.
+    pub fn texture(&self) -> Option<(GLenum, WebGLTexture)> {
+        let loaded = self.loaded.as_ref()?;
+        Some((loaded.kind.target(), loaded.texture.texture()?))
+    }
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The texture images of a kind make, with a short description of it.
+fn describe(kind: Kind, mut images: Vec<Image>) -> Result<(Description, String), String> {
+    // Cubemap faces are counted when they're ordered.
+    if !kind.is_multiple() && images.len() != 1 {
+        return Err(format!("{} takes one image, not {}", kind.name(), images.len()));
+    }
+
+    match kind {
+        Kind::Image => {
+            let image = images.remove(0);
+            // WebGL1 only repeats textures whose sides are powers of two.
+            let wrap = if image.width.is_power_of_two() && image.height.is_power_of_two() {
+                WebGLRenderingContext::REPEAT
+            } else {
+                WebGLRenderingContext::CLAMP_TO_EDGE
+            };
+            let summary = format!("{} x {} image", image.width, image.height);
+
+            let description = Description::Texture {
+                width: image.width as i32,
+                height: image.height as i32,
+                filter: WebGLRenderingContext::LINEAR,
+                wrap,
+                pixels: Some(image.pixels),
+            };
+            Ok((description, summary))
+        }
+        Kind::Cubemap => {
+            let names: Vec<&str> = images.iter().map(|image| image.name.as_str()).collect();
+            let order = cubemap::face_order(&names)?;
+            let size = images[order[0]].width;
+            if let Some(image) = images.iter().find(|image| image.width != size || image.height != size) {
+                return Err(format!("Cubemap faces are square and of the same size, {} isn't {}²", image.name, size));
+            }
+
+            let mut faces: Vec<Option<Vec<u8>>> = images.into_iter().map(|image| Some(image.pixels)).collect();
+            let faces = order.iter().map(|&index| faces[index].take().unwrap()).collect();
+            Ok((Description::Cubemap { size: size as i32, faces }, format!("{}² cubemap", size)))
+        }
+        Kind::Panorama => {
+            let image = images.remove(0);
+            let size = cubemap::face_size(image.width).min(MAX_PANORAMA_FACE_SIZE);
+            let faces = cubemap::from_panorama(&image.pixels, image.width, image.height, size);
+            Ok((Description::Cubemap { size: size as i32, faces }, format!("{}² cubemap", size)))
+        }
+        Kind::Volume => {
+            let image = images.remove(0);
+            let volume = volume::from_slices(&image.pixels, image.width, image.height)?;
+            let description = Description::Volume {
+                size: volume.size as i32,
+                filter: WebGLRenderingContext::LINEAR,
+                wrap: WebGLRenderingContext::REPEAT,
+                voxels: volume.voxels,
+            };
+            Ok((description, format!("{}³ volume", volume.size)))
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Volumes from images of their slices laid out in a grid.
+
+/// A cubic volume of RGBA voxels, slice after slice, each slice first row
+/// first, the way `texImage3D` takes them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Volume {
+    pub size: usize,
+    pub voxels: Vec<u8>,
+}
+
+/// Cuts an image into the slices of a cubic volume.
+///
+/// A volume of size `n` has `n` slices of `n` by `n` pixels, laid out in a
+/// grid from left to right, then top to bottom, so its image has `n³`
+/// pixels. A 32³ volume is e.g. a strip of 32 x 1024 pixels or a grid of
+/// 256 x 128.
+///
+/// ## Arguments
+///
+/// * `pixels` - RGBA pixels of the image, first row first.
+/// * `width` - Width of the image.
+/// * `height` - Height of the image.
+pub fn from_slices(pixels: &[u8], width: usize, height: usize) -> Result<Volume, String> {
+    let size = (width as f64 * height as f64).cbrt().round() as usize;
+    let is_grid = size > 0 && width.is_multiple_of(size) && height.is_multiple_of(size);
+    if !is_grid || size * size * size != width * height {
+        return Err(format!(
+            "A {} x {} image isn't a grid of square slices of a cubic volume, e.g. 32 x 1024 for 32³",
+            width, height
+        ));
+    }
+
+    let columns = width / size;
+    let mut voxels = Vec::with_capacity(pixels.len());
+    for slice in 0..size {
+        let (left, top) = (slice % columns * size, slice / columns * size);
+        for row in top..top + size {
+            let start = (row * width + left) * 4;
+            voxels.extend_from_slice(&pixels[start..start + size * 4]);
+        }
+    }
+
+    Ok(Volume { size, voxels })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An image where every pixel's red channel is its index.
+    fn indexed(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height).flat_map(|index| vec![index as u8, 0, 0, 255]).collect()
+    }
+
+    fn reds(volume: &Volume) -> Vec<u8> {
+        volume.voxels.chunks(4).map(|voxel| voxel[0]).collect()
+    }
+
+    #[test]
+    fn a_strip_is_already_in_order() {
+        let volume = from_slices(&indexed(2, 4), 2, 4).unwrap();
+
+        assert_eq!(volume.size, 2);
+        assert_eq!(reds(&volume), (0..8).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn a_grid_is_read_slice_by_slice() {
+        let volume = from_slices(&indexed(4, 2), 4, 2).unwrap();
+
+        // The first slice is the left half, the second the right half.
+        assert_eq!(reds(&volume), vec![0, 1, 4, 5, 2, 3, 6, 7]);
+    }
+
+    #[test]
+    fn grids_wrap_onto_the_next_row_of_slices() {
+        let volume = from_slices(&indexed(8, 8), 8, 8).unwrap();
+
+        assert_eq!(volume.size, 4);
+        // The third slice starts on the left, four rows down.
+        assert_eq!(reds(&volume)[32], 32);
+        assert_eq!(reds(&volume)[16], 4);
+    }
+
+    #[test]
+    fn rejects_images_that_arent_cubes() {
+        assert!(from_slices(&indexed(3, 3), 3, 3).is_err());
+        assert!(from_slices(&indexed(16, 2), 16, 2).is_err());
+        assert!(from_slices(&[], 0, 0).is_err());
+    }
+}
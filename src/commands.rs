@@ -0,0 +1,410 @@
+use crate::webgl_rendering_context::WebGLRenderingContext;
+
+pub use self::backend::{Object, Reference};
+
+/// The commands a `CommandBuffer` records. Each is followed by its
+/// arguments, one word per argument.
+///
+/// The numbers are what `REPLAY` switches on, the two have to match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    UseProgram = 0,
+    BindBuffer = 1,
+    BindFramebuffer = 2,
+    BindRenderbuffer = 3,
+    ActiveTexture = 4,
+    BindTexture = 5,
+    Enable = 6,
+    Disable = 7,
+    EnableVertexAttribArray = 8,
+    DisableVertexAttribArray = 9,
+    VertexAttribPointer = 10,
+    DepthFunc = 11,
+    DepthMask = 12,
+    BlendFunc = 13,
+    Viewport = 14,
+    ClearColor = 15,
+    ClearDepth = 16,
+    Clear = 17,
+    Uniform1f = 18,
+    Uniform1i = 19,
+    Uniform2f = 20,
+    Uniform3f = 21,
+    Uniform4f = 22,
+    UniformMatrix3fv = 23,
+    UniformMatrix4fv = 24,
+    DrawArrays = 25,
+    DrawElements = 26,
+}
+
+impl Command {
+    /// How many words of arguments follow the command.
+    pub fn arguments(self) -> usize {
+        match self {
+            Command::UseProgram
+            | Command::ActiveTexture
+            | Command::Enable
+            | Command::Disable
+            | Command::EnableVertexAttribArray
+            | Command::DisableVertexAttribArray
+            | Command::DepthFunc
+            | Command::DepthMask
+            | Command::ClearDepth
+            | Command::Clear => 1,
+            Command::BindBuffer
+            | Command::BindFramebuffer
+            | Command::BindRenderbuffer
+            | Command::BindTexture
+            | Command::BlendFunc
+            | Command::Uniform1f
+            | Command::Uniform1i => 2,
+            Command::Uniform2f | Command::DrawArrays => 3,
+            Command::Viewport | Command::ClearColor | Command::Uniform3f | Command::DrawElements => 4,
+            Command::Uniform4f => 5,
+            Command::VertexAttribPointer => 6,
+            Command::UniformMatrix3fv => 10,
+            Command::UniformMatrix4fv => 17,
+        }
+    }
+}
+
+/// WebGL calls encoded into 32 bit words, to be made in one go.
+///
+/// Crossing from Rust into JavaScript costs far more than most of the calls
+/// themselves. Recording a frame and replaying it with a single `js!` call
+/// crosses once instead of once per call.
+///
+/// Integers are stored as their bits, floats with `f32::to_bits`, and WebGL
+/// objects as an index into a table sent along, with 0 for `null`. The table
+/// is kept from one replay to the next, so an object's word stays the same
+/// and the common case of using the same objects every frame doesn't touch
+/// JavaScript's reference counts. Objects are looked up in it by their ids,
+/// without calling into JavaScript.
+#[derive(Debug, Default)]
+pub struct CommandBuffer {
+    words: Vec<u32>,
+    objects: Vec<Reference>,
+    ids: Ids,
+}
+
+/// The `Object::id` of each entry of a `CommandBuffer`'s object table.
+#[derive(Debug, Default)]
+struct Ids(Vec<u32>);
+
+impl Ids {
+    /// The word for the object with the given id, and whether it was added,
+    /// in which case its reference has to be added to the table as well.
+    fn word(&mut self, id: u32) -> (u32, bool) {
+        let (index, added) = match self.0.iter().position(|&existing| existing == id) {
+            Some(index) => (index, false),
+            None => {
+                self.0.push(id);
+                (self.0.len() - 1, true)
+            }
+        };
+        (index as u32 + 1, added)
+    }
+
+    /// Whether the table grew past `MAX_OBJECTS` and has to start over.
+    fn full(&self) -> bool {
+        self.0.len() > MAX_OBJECTS
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Objects in the table before it's started over after a replay. Objects in
+/// it are kept alive, the limit stops deleted ones from piling up.
+const MAX_OBJECTS: usize = 256;
+
+impl CommandBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Appends a command and its arguments.
+    pub fn push(&mut self, command: Command, arguments: &[u32]) {
+        debug_assert_eq!(arguments.len(), command.arguments(), "{:?} takes {} arguments", command, command.arguments());
+        self.words.push(command as u32);
+        self.words.extend_from_slice(arguments);
+    }
+
+    /// The word an object argument is encoded as, adding it to the table if
+    /// it isn't there yet.
+    pub fn object<T: Object>(&mut self, object: Option<&T>) -> u32 {
+        let object = match object {
+            Some(object) => object,
+            None => return 0,
+        };
+
+        let (word, added) = self.ids.word(object.id());
+        if added {
+            self.objects.push(object.reference().clone());
+        }
+        word
+    }
+
+    /// Drops everything recorded without making the calls, and the table.
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.objects.clear();
+        self.ids.clear();
+    }
+
+    /// Makes the recorded calls on `gl`, in order, and empties the buffer.
+    ///
+    /// Returns whether the object table was started over, words handed out
+    /// before then mean nothing anymore.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context the calls are made on.
+    pub fn replay(&mut self, gl: &WebGLRenderingContext) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        backend::replay(gl, &self.words, &self.objects);
+
+        self.words.clear();
+        if self.ids.full() {
+            self.objects.clear();
+            self.ids.clear();
+            return true;
+        }
+        false
+    }
+}
+
+/// The word a float argument is encoded as.
+pub fn float(value: f32) -> u32 {
+    value.to_bits()
+}
+
+/// The word a boolean argument is encoded as.
+pub fn flag(value: bool) -> u32 {
+    value as u32
+}
+
+/// Body of the JavaScript function that makes the recorded calls, taking the
+/// context, the words as a `Uint32Array` and the object table.
+const REPLAY: &str = r#"
+const ints = new Int32Array(words.buffer, words.byteOffset, words.length);
+const floats = new Float32Array(words.buffer, words.byteOffset, words.length);
+const object = (word) => word === 0 ? null : objects[word - 1];
+
+let i = 0;
+while (i < words.length) {
+    const command = words[i];
+    const a = i + 1;
+    switch (command) {
+        case 0: gl.useProgram(object(words[a])); i = a + 1; break;
+        case 1: gl.bindBuffer(words[a], object(words[a + 1])); i = a + 2; break;
+        case 2: gl.bindFramebuffer(words[a], object(words[a + 1])); i = a + 2; break;
+        case 3: gl.bindRenderbuffer(words[a], object(words[a + 1])); i = a + 2; break;
+        case 4: gl.activeTexture(words[a]); i = a + 1; break;
+        case 5: gl.bindTexture(words[a], object(words[a + 1])); i = a + 2; break;
+        case 6: gl.enable(words[a]); i = a + 1; break;
+        case 7: gl.disable(words[a]); i = a + 1; break;
+        case 8: gl.enableVertexAttribArray(words[a]); i = a + 1; break;
+        case 9: gl.disableVertexAttribArray(words[a]); i = a + 1; break;
+        case 10:
+            gl.vertexAttribPointer(words[a], ints[a + 1], words[a + 2], words[a + 3] !== 0, ints[a + 4], words[a + 5]);
+            i = a + 6;
+            break;
+        case 11: gl.depthFunc(words[a]); i = a + 1; break;
+        case 12: gl.depthMask(words[a] !== 0); i = a + 1; break;
+        case 13: gl.blendFunc(words[a], words[a + 1]); i = a + 2; break;
+        case 14: gl.viewport(ints[a], ints[a + 1], ints[a + 2], ints[a + 3]); i = a + 4; break;
+        case 15: gl.clearColor(floats[a], floats[a + 1], floats[a + 2], floats[a + 3]); i = a + 4; break;
+        case 16: gl.clearDepth(floats[a]); i = a + 1; break;
+        case 17: gl.clear(words[a]); i = a + 1; break;
+        case 18: gl.uniform1f(object(words[a]), floats[a + 1]); i = a + 2; break;
+        case 19: gl.uniform1i(object(words[a]), ints[a + 1]); i = a + 2; break;
+        case 20: gl.uniform2f(object(words[a]), floats[a + 1], floats[a + 2]); i = a + 3; break;
+        case 21: gl.uniform3f(object(words[a]), floats[a + 1], floats[a + 2], floats[a + 3]); i = a + 4; break;
+        case 22:
+            gl.uniform4f(object(words[a]), floats[a + 1], floats[a + 2], floats[a + 3], floats[a + 4]);
+            i = a + 5;
+            break;
+        case 23: gl.uniformMatrix3fv(object(words[a]), false, floats.subarray(a + 1, a + 10)); i = a + 10; break;
+        case 24: gl.uniformMatrix4fv(object(words[a]), false, floats.subarray(a + 1, a + 17)); i = a + 17; break;
+        case 25: gl.drawArrays(words[a], ints[a + 1], ints[a + 2]); i = a + 3; break;
+        case 26: gl.drawElements(words[a], ints[a + 1], words[a + 2], words[a + 3]); i = a + 4; break;
+        default: throw new Error("Unknown command " + command);
+    }
+}
+"#;
+
+#[cfg(not(feature = "web-sys-backend"))]
+mod backend {
+    use stdweb::web::TypedArray;
+    use stdweb::{js, Value};
+
+    use crate::webgl_rendering_context::WebGLRenderingContext;
+
+    pub use stdweb::Reference;
+
+    /// A WebGL object as the command buffer takes it.
+    pub trait Object {
+        /// Stays the same for the same JavaScript object.
+        fn id(&self) -> u32;
+
+        fn reference(&self) -> &Reference;
+    }
+
+    /// stdweb hands out one reference id per JavaScript object, which is
+    /// what its references compare.
+    impl<T: AsRef<Reference>> Object for T {
+        fn id(&self) -> u32 {
+            self.as_ref().as_raw() as u32
+        }
+
+        fn reference(&self) -> &Reference {
+            self.as_ref()
+        }
+    }
+
+    thread_local! {
+        static REPLAY: Value = js! {
+            return new Function("gl", "words", "objects", @{super::REPLAY});
+        };
+    }
+
+    pub fn replay(gl: &WebGLRenderingContext, words: &[u32], objects: &[Reference]) {
+        let words = TypedArray::<u32>::from(words);
+        REPLAY.with(|replay| {
+            js! { @(no_return)
+                @{replay}(@{gl}, @{words}, @{objects});
+            }
+        });
+    }
+}
+
+#[cfg(feature = "web-sys-backend")]
+mod backend {
+    use js_sys::{Array, Function, Uint32Array};
+
+    use crate::webgl_rendering_context::{Tracked, WebGLRenderingContext};
+
+    pub use wasm_bindgen::JsValue as Reference;
+
+    /// A WebGL object as the command buffer takes it.
+    pub trait Object {
+        /// Stays the same for the same JavaScript object.
+        fn id(&self) -> u32;
+
+        fn reference(&self) -> &Reference;
+    }
+
+    /// Comparing two `JsValue`s is a call into JavaScript, web-sys objects
+    /// are told apart by the id they got when they were created.
+    impl<T: AsRef<Reference>> Object for Tracked<T> {
+        fn id(&self) -> u32 {
+            Tracked::id(self)
+        }
+
+        fn reference(&self) -> &Reference {
+            self.as_ref()
+        }
+    }
+
+    thread_local! {
+        static REPLAY: Function = Function::new_with_args("gl, words, objects", super::REPLAY);
+    }
+
+    pub fn replay(gl: &WebGLRenderingContext, words: &[u32], objects: &[Reference]) {
+        let context: &Reference = (**gl).as_ref();
+        let words = Uint32Array::from(words);
+        let objects: Array = objects.iter().collect();
+        REPLAY.with(|replay| replay.call3(&Reference::NULL, context, &words, &objects).unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMANDS: &[Command] = &[
+        Command::UseProgram,
+        Command::BindBuffer,
+        Command::BindFramebuffer,
+        Command::BindRenderbuffer,
+        Command::ActiveTexture,
+        Command::BindTexture,
+        Command::Enable,
+        Command::Disable,
+        Command::EnableVertexAttribArray,
+        Command::DisableVertexAttribArray,
+        Command::VertexAttribPointer,
+        Command::DepthFunc,
+        Command::DepthMask,
+        Command::BlendFunc,
+        Command::Viewport,
+        Command::ClearColor,
+        Command::ClearDepth,
+        Command::Clear,
+        Command::Uniform1f,
+        Command::Uniform1i,
+        Command::Uniform2f,
+        Command::Uniform3f,
+        Command::Uniform4f,
+        Command::UniformMatrix3fv,
+        Command::UniformMatrix4fv,
+        Command::DrawArrays,
+        Command::DrawElements,
+    ];
+
+    /// Each `case` of `REPLAY` with how many words it reads after the command.
+    fn replayed_cases() -> Vec<(u32, usize)> {
+        REPLAY
+            .split("case ")
+            .skip(1)
+            .map(|case| {
+                let (command, body) = case.split_at(case.find(':').unwrap());
+                let advance = &body[body.find("i = a + ").unwrap() + "i = a + ".len()..];
+                let arguments = advance[..advance.find(';').unwrap()].parse().unwrap();
+                (command.parse().unwrap(), arguments)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replay_reads_the_arguments_each_command_is_recorded_with() {
+        let expected: Vec<(u32, usize)> = COMMANDS.iter().map(|&command| (command as u32, command.arguments())).collect();
+
+        assert_eq!(replayed_cases(), expected);
+    }
+
+    #[test]
+    fn objects_keep_their_word() {
+        let mut ids = Ids::default();
+
+        assert_eq!(ids.word(7), (1, true));
+        assert_eq!(ids.word(3), (2, true));
+        assert_eq!(ids.word(7), (1, false));
+        assert_eq!(ids.word(3), (2, false));
+    }
+
+    #[test]
+    fn the_table_starts_over_once_full() {
+        let mut ids = Ids::default();
+        for id in 0..MAX_OBJECTS as u32 {
+            ids.word(id);
+        }
+        assert!(!ids.full());
+
+        ids.word(MAX_OBJECTS as u32);
+        assert!(ids.full());
+
+        // What `CommandBuffer::replay` does once it is, words start from 1 again.
+        ids.clear();
+        assert_eq!(ids.word(MAX_OBJECTS as u32), (1, true));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::glsl::minify::minify;
+use crate::glsl::parser::{Shader, Version};
+use crate::glsl::translate::translate;
+use crate::glsl::{SnippetLibrary, Stage};
+use crate::models::Model;
+use crate::project::{Project, UniformValue};
+
+const RUNNER: &str = include_str!("runner.js");
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Builds a single, self contained HTML page that renders a project without
+/// the editor.
+///
+/// The sources are preprocessed, translated to GLSL ES 1.00 so they run on
+/// any WebGL context and minified. The mesh is inlined as base64, textures are
+/// already `data:` URLs and uniform values are set once at startup. A small
+/// runner script provides the injected uniforms, `iTime`, `iMouse` and so on.
+///
+/// Sources GLSL ES 1.00 can't express, like ones sampling a `sampler3D`, fail
+/// with the translator's errors worded like a driver's log.
+///
+/// ## Arguments
+///
+/// * `project` - Project to export.
+/// * `model` - Model the project draws.
+/// * `snippets` - Library the sources' includes are resolved from.
+pub fn standalone_html(project: &Project, model: &dyn Model, snippets: &SnippetLibrary) -> Result<String, String> {
+    let preprocessor = project.preprocessor(snippets);
+    let vertex = preprocessor.process(Stage::Vertex.name(), &project.vertex)?;
+    let fragment = preprocessor.process(Stage::Fragment.name(), &project.fragment)?;
+
+    let vertex = translate(&vertex.source, Stage::Vertex, Version::Es100);
+    let fragment = translate(&fragment.source, Stage::Fragment, Version::Es100);
+
+    // Sources that only compile on WebGL2 can't be exported as they are.
+    let logs: Vec<String> = [(Stage::Vertex, &vertex), (Stage::Fragment, &fragment)]
+        .iter()
+        .filter_map(|(stage, translation)| Some(format!("{} stage:\n{}", stage.name(), translation.error_log()?)))
+        .collect();
+    if !logs.is_empty() {
+        return Err(logs.join("\n"));
+    }
+
+    let mut extensions: Vec<&str> = vertex.extensions.iter().chain(fragment.extensions.iter()).cloned().collect();
+    extensions.sort();
+    extensions.dedup();
+
+    // Attributes keep their names through minification, so they can be
+    // matched to the layout before it.
+    let layout = model.layout();
+    let aliases = project.aliases();
+    let shader = Shader::parse(&vertex.source);
+    let attributes: Vec<String> = shader.globals
+        .iter()
+        .filter(|variable| variable.has_qualifier("attribute"))
+        .filter_map(|variable| {
+            let attribute = layout.get(aliases.resolve(&variable.name))?;
+            Some(format!(
+                "{{name: {}, size: {}, type: {}, normalized: {}, stride: {}, offset: {}}}",
+                js_string(&variable.name),
+                attribute.size,
+                attribute.type_,
+                attribute.normalized,
+                attribute.stride,
+                attribute.offset
+            ))
+        })
+        .collect();
+
+    let indices = model.indices();
+
+    let uniforms: Vec<String> = project.uniforms
+        .iter()
+        .map(|(name, value)| {
+            let (type_, values) = uniform_call(value);
+            format!("{{name: {}, type: \"{}\", value: [{}]}}", js_string(name), type_, values.join(", "))
+        })
+        .collect();
+
+    let textures: Vec<String> = project.textures
+        .iter()
+        .map(|texture| format!("{{uniform: {}, src: {}}}", js_string(&texture.uniform), js_string(&texture.data_url)))
+        .collect();
+
+    let data = format!(
+        "{{\n    vertex: {},\n    fragment: {},\n    extensions: [{}],\n    attributes: [{}],\n    vertices: \"{}\",\n    indices: \"{}\",\n    indexType: {},\n    indexCount: {},\n    uniforms: [{}],\n    textures: [{}]\n}}",
+        js_string(&minify(&vertex.source)),
+        js_string(&minify(&fragment.source)),
+        extensions.iter().map(|name| js_string(name)).collect::<Vec<String>>().join(", "),
+        attributes.join(", "),
+        base64(&model.vertices()),
+        base64(&indices.bytes()),
+        indices.type_(),
+        indices.len(),
+        uniforms.join(", "),
+        textures.join(", ")
+    );
+
+    Ok(format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{}</title>
+    <style>
+        html, body {{ margin: 0; height: 100%; background: #000; }}
+        canvas {{ display: block; width: 100%; height: 100%; }}
+    </style>
+</head>
+<body>
+    <canvas id="netshader"></canvas>
+    <script>
+{}
+netshaderRun(document.getElementById("netshader"), {});
+    </script>
+</body>
+</html>
+"#,
+        html_escape(&project.name),
+        RUNNER,
+        data
+    ))
+}
+
+/// The suffix of the `gl.uniform*` call that uploads a value, and its components.
+fn uniform_call(value: &UniformValue) -> (&'static str, Vec<String>) {
+    let floats = |values: &[f32]| values.iter().map(|&value| js_number(value)).collect::<Vec<String>>();
+
+    match value {
+        UniformValue::Float(value) => ("1fv", floats(&[*value])),
+        UniformValue::Vec2(values) => ("2fv", floats(values)),
+        UniformValue::Vec3(values) => ("3fv", floats(values)),
+        UniformValue::Vec4(values) => ("4fv", floats(values)),
+        UniformValue::Int(value) => ("1iv", vec![value.to_string()]),
+        UniformValue::Bool(value) => ("1iv", vec![(*value as i32).to_string()]),
+        UniformValue::Mat3(values) => ("Matrix3fv", floats(values)),
+        UniformValue::Mat4(values) => ("Matrix4fv", floats(values)),
+    }
+}
+
+fn js_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        String::from("0")
+    }
+}
+
+/// Quotes a string as a JavaScript literal that is also safe inside an
+/// inline `<script>`.
+fn js_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '<' => quoted.push_str("\\u003c"),
+            '\u{2028}' => quoted.push_str("\\u2028"),
+            '\u{2029}' => quoted.push_str("\\u2029"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for position in 0..4 {
+            if position <= chunk.len() {
+                encoded.push(BASE64[(group >> (18 - 6 * position) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,215 @@
+// Minimal WebGL runner for projects exported by netshader. It draws the
+// project's mesh every frame and provides the uniforms the editor injects.
+function netshaderRun(canvas, project) {
+    var gl = canvas.getContext("webgl");
+    if (!gl) {
+        document.body.textContent = "This page needs WebGL, which your browser does not support.";
+        return;
+    }
+
+    project.extensions.forEach(function (name) {
+        gl.getExtension(name);
+    });
+    if (project.indexType === gl.UNSIGNED_INT) {
+        gl.getExtension("OES_element_index_uint");
+    }
+
+    function compile(type, source) {
+        var shader = gl.createShader(type);
+        gl.shaderSource(shader, source);
+        gl.compileShader(shader);
+        if (!gl.getShaderParameter(shader, gl.COMPILE_STATUS)) {
+            throw new Error(gl.getShaderInfoLog(shader));
+        }
+        return shader;
+    }
+
+    var program = gl.createProgram();
+    gl.attachShader(program, compile(gl.VERTEX_SHADER, project.vertex));
+    gl.attachShader(program, compile(gl.FRAGMENT_SHADER, project.fragment));
+    gl.linkProgram(program);
+    if (!gl.getProgramParameter(program, gl.LINK_STATUS)) {
+        throw new Error(gl.getProgramInfoLog(program));
+    }
+    gl.useProgram(program);
+
+    function decode(base64) {
+        var binary = atob(base64);
+        var bytes = new Uint8Array(binary.length);
+        for (var i = 0; i < binary.length; i++) {
+            bytes[i] = binary.charCodeAt(i);
+        }
+        return bytes;
+    }
+
+    gl.bindBuffer(gl.ARRAY_BUFFER, gl.createBuffer());
+    gl.bufferData(gl.ARRAY_BUFFER, decode(project.vertices), gl.STATIC_DRAW);
+    gl.bindBuffer(gl.ELEMENT_ARRAY_BUFFER, gl.createBuffer());
+    gl.bufferData(gl.ELEMENT_ARRAY_BUFFER, decode(project.indices), gl.STATIC_DRAW);
+
+    project.attributes.forEach(function (attribute) {
+        var location = gl.getAttribLocation(program, attribute.name);
+        if (location < 0) {
+            return;
+        }
+        gl.enableVertexAttribArray(location);
+        gl.vertexAttribPointer(location, attribute.size, attribute.type, attribute.normalized, attribute.stride, attribute.offset);
+    });
+
+    function uniform(name) {
+        return gl.getUniformLocation(program, name);
+    }
+
+    project.uniforms.forEach(function (value) {
+        var setter = gl["uniform" + value.type].bind(gl);
+        if (value.type.indexOf("Matrix") === 0) {
+            setter(uniform(value.name), false, value.value);
+        } else {
+            setter(uniform(value.name), value.value);
+        }
+    });
+
+    project.textures.forEach(function (texture, unit) {
+        var handle = gl.createTexture();
+        gl.activeTexture(gl.TEXTURE0 + unit);
+        gl.bindTexture(gl.TEXTURE_2D, handle);
+        gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, 1, 1, 0, gl.RGBA, gl.UNSIGNED_BYTE, new Uint8Array([0, 0, 0, 255]));
+        gl.uniform1i(uniform(texture.uniform), unit);
+
+        var image = new Image();
+        image.onload = function () {
+            gl.activeTexture(gl.TEXTURE0 + unit);
+            gl.bindTexture(gl.TEXTURE_2D, handle);
+            gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, gl.RGBA, gl.UNSIGNED_BYTE, image);
+            gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR);
+            gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE);
+            gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE);
+        };
+        image.src = texture.src;
+    });
+
+    // xy is the position while a button is held, zw where it was pressed.
+    // z is negated once the button is released, w after the frame of the
+    // press.
+    var mouse = [0, 0, 0, 0];
+    var pressed = false;
+    function position(event) {
+        var rect = canvas.getBoundingClientRect();
+        var scale = canvas.width / rect.width;
+        return [(event.clientX - rect.left) * scale, (rect.bottom - event.clientY) * scale];
+    }
+    canvas.addEventListener("mousedown", function (event) {
+        var point = position(event);
+        pressed = true;
+        mouse = [point[0], point[1], point[0], point[1]];
+    });
+    canvas.addEventListener("mousemove", function (event) {
+        if (pressed) {
+            var point = position(event);
+            mouse[0] = point[0];
+            mouse[1] = point[1];
+        }
+    });
+    window.addEventListener("mouseup", function () {
+        pressed = false;
+        mouse[2] = -Math.abs(mouse[2]);
+    });
+
+    // One texel per key code, rows for keys down, pressed this frame and
+    // toggled by every press.
+    var keys = new Uint8Array(256 * 3 * 4);
+    var keysChanged = true;
+    function setKey(row, code, value) {
+        var offset = (row * 256 + code) * 4;
+        if (keys[offset] !== value) {
+            keys.fill(value, offset, offset + 4);
+            keysChanged = true;
+        }
+    }
+    window.addEventListener("keydown", function (event) {
+        var code = event.keyCode;
+        if (code > 255 || keys[code * 4] !== 0) {
+            return;
+        }
+        setKey(0, code, 255);
+        setKey(1, code, 255);
+        setKey(2, code, 255 - keys[(512 + code) * 4]);
+    });
+    window.addEventListener("keyup", function (event) {
+        if (event.keyCode <= 255) {
+            setKey(0, event.keyCode, 0);
+        }
+    });
+    window.addEventListener("blur", function () {
+        for (var code = 0; code < 256; code++) {
+            setKey(0, code, 0);
+        }
+    });
+
+    var keyboard = gl.createTexture();
+    var keyboardUnit = project.textures.length;
+    gl.activeTexture(gl.TEXTURE0 + keyboardUnit);
+    gl.bindTexture(gl.TEXTURE_2D, keyboard);
+    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.NEAREST);
+    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.NEAREST);
+    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE);
+    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE);
+    gl.uniform1i(uniform("iKeyboard"), keyboardUnit);
+
+    var injected = {
+        iResolution: uniform("iResolution"),
+        iTime: uniform("iTime"),
+        iTimeDelta: uniform("iTimeDelta"),
+        iFrame: uniform("iFrame"),
+        iMouse: uniform("iMouse"),
+        iDate: uniform("iDate")
+    };
+    var start = performance.now();
+    var last = start;
+    var frame = 0;
+
+    function render(now) {
+        var width = Math.round(canvas.clientWidth * window.devicePixelRatio);
+        var height = Math.round(canvas.clientHeight * window.devicePixelRatio);
+        if (canvas.width !== width || canvas.height !== height) {
+            canvas.width = width;
+            canvas.height = height;
+        }
+        gl.viewport(0, 0, canvas.width, canvas.height);
+
+        var date = new Date();
+        var seconds = date.getHours() * 3600 + date.getMinutes() * 60 + date.getSeconds() + date.getMilliseconds() / 1000;
+        gl.uniform3f(injected.iResolution, canvas.width, canvas.height, 1);
+        gl.uniform1f(injected.iTime, (now - start) / 1000);
+        gl.uniform1f(injected.iTimeDelta, (now - last) / 1000);
+        gl.uniform1i(injected.iFrame, frame);
+        gl.uniform4fv(injected.iMouse, mouse);
+        gl.uniform4f(injected.iDate, date.getFullYear(), date.getMonth(), date.getDate(), seconds);
+
+        if (keysChanged) {
+            gl.activeTexture(gl.TEXTURE0 + keyboardUnit);
+            gl.bindTexture(gl.TEXTURE_2D, keyboard);
+            gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, 256, 3, 0, gl.RGBA, gl.UNSIGNED_BYTE, keys);
+            keysChanged = false;
+        }
+
+        gl.clearColor(0, 0, 0, 1);
+        gl.clearDepth(1);
+        gl.enable(gl.DEPTH_TEST);
+        gl.depthFunc(gl.LEQUAL);
+        gl.clear(gl.COLOR_BUFFER_BIT | gl.DEPTH_BUFFER_BIT);
+        gl.drawElements(gl.TRIANGLES, project.indexCount, project.indexType, 0);
+
+        // Presses show up in exactly one frame.
+        mouse[3] = -Math.abs(mouse[3]);
+        for (var code = 0; code < 256; code++) {
+            setKey(1, code, 0);
+        }
+
+        last = now;
+        frame++;
+        window.requestAnimationFrame(render);
+    }
+
+    window.requestAnimationFrame(render);
+}
@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use nalgebra_glm::{self as glm, Vec3};
+
+use crate::models::{f32_bytes, Indices, Model, VertexLayout};
+use crate::webgl_rendering_context::GLint;
+
+pub const POSITION: &str = "aVertexPosition";
+pub const NORMAL: &str = "aVertexNormal";
+pub const TANGENT: &str = "aVertexTangent";
+pub const TEXTURE_COORD: &str = "aTextureCoord";
+
+/// An editable, CPU side copy of a model's float attributes, used to derive
+/// data the original model lacks before uploading it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Geometry {
+    attributes: Vec<(String, GLint, Vec<f32>)>,
+    indices: Vec<u32>,
+    vertex_count: usize,
+}
+
+impl Geometry {
+    /// Copies every float attribute and the indices out of a model.
+    pub fn from_model(model: &dyn Model) -> Self {
+        let layout = model.layout();
+        let vertices = model.vertices();
+        let vertex_count = model.vertex_count();
+
+        let attributes = layout.attributes
+            .iter()
+            .filter_map(|attribute| {
+                layout
+                    .read(&vertices, &attribute.name, vertex_count)
+                    .map(|values| (attribute.name.clone(), attribute.size, values))
+            })
+            .collect();
+
+        Self {
+            attributes,
+            indices: model.indices().to_u32(),
+            vertex_count,
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&[f32]> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _, _)| attribute == name)
+            .map(|(_, _, values)| values.as_slice())
+    }
+
+    fn set_attribute(&mut self, name: &str, size: GLint, values: Vec<f32>) {
+        match self.attributes.iter_mut().find(|(attribute, _, _)| attribute == name) {
+            Some(attribute) => *attribute = (name.to_string(), size, values),
+            None => self.attributes.push((name.to_string(), size, values)),
+        }
+    }
+
+    fn vec3(&self, name: &str, vertex: u32) -> Vec3 {
+        let values = self.attribute(name).unwrap();
+        let start = vertex as usize * 3;
+        glm::vec3(values[start], values[start + 1], values[start + 2])
+    }
+
+    /// Flat normals, every triangle keeps its own face normal.
+    pub fn compute_flat_normals(&mut self) -> Result<(), String> {
+        self.compute_normals(0.0)
+    }
+
+    /// Smooth normals, every position averages all of its adjacent faces.
+    pub fn compute_smooth_normals(&mut self) -> Result<(), String> {
+        self.compute_normals(PI)
+    }
+
+    /// Computes angle weighted vertex normals, only averaging faces whose normals
+    /// are within `crease_angle` radians of each other. Vertices sitting on a
+    /// crease are duplicated so each side keeps its own normal.
+    ///
+    /// Faces are matched by vertex position rather than index, so models that
+    /// store separate vertices per face, like `Cube`, still smooth across edges.
+    ///
+    /// ## Arguments
+    ///
+    /// * `crease_angle` - Largest angle in radians between faces that are smoothed together.
+    pub fn compute_normals(&mut self, crease_angle: f32) -> Result<(), String> {
+        if self.attribute(POSITION).is_none() {
+            return Err(format!("Cannot compute normals without a `{}` attribute", POSITION));
+        }
+
+        let triangles: Vec<[u32; 3]> = self.indices
+            .chunks(3)
+            .filter(|triangle| triangle.len() == 3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+
+        let face_normals: Vec<Vec3> = triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                let (a, b, c) = (self.vec3(POSITION, a), self.vec3(POSITION, b), self.vec3(POSITION, c));
+                normalize_or_zero(&(b - a).cross(&(c - a)))
+            })
+            .collect();
+
+        // Every triangle corner, grouped by the exact position it sits at.
+        let mut corners_at: HashMap<[u32; 3], Vec<(usize, f32)>> = HashMap::new();
+        for (face, triangle) in triangles.iter().enumerate() {
+            for corner in 0..3 {
+                let position = self.vec3(POSITION, triangle[corner]);
+                let previous = self.vec3(POSITION, triangle[(corner + 2) % 3]);
+                let next = self.vec3(POSITION, triangle[(corner + 1) % 3]);
+                let weight = corner_angle(&(previous - position), &(next - position));
+
+                corners_at
+                    .entry(position_key(&position))
+                    .or_insert_with(Vec::new)
+                    .push((face, weight));
+            }
+        }
+
+        // Small tolerance so coplanar faces still share normals with a zero crease.
+        let threshold = crease_angle.cos() - 1e-4;
+        let mut remap: HashMap<(u32, [i32; 3]), u32> = HashMap::new();
+        let mut sources: Vec<u32> = Vec::new();
+        let mut normals: Vec<f32> = Vec::new();
+        let mut indices = Vec::with_capacity(self.indices.len());
+
+        for (face, triangle) in triangles.iter().enumerate() {
+            for &vertex in triangle {
+                let face_normal = face_normals[face];
+                let mut normal = glm::vec3(0.0, 0.0, 0.0);
+
+                for &(other, weight) in &corners_at[&position_key(&self.vec3(POSITION, vertex))] {
+                    if glm::dot(&face_normal, &face_normals[other]) >= threshold {
+                        normal += face_normals[other] * weight;
+                    }
+                }
+
+                let normal = normalize_or_zero(&normal);
+                let key = (vertex, normal_key(&normal));
+                let index = *remap.entry(key).or_insert_with(|| {
+                    sources.push(vertex);
+                    normals.extend_from_slice(&[normal.x, normal.y, normal.z]);
+                    (sources.len() - 1) as u32
+                });
+
+                indices.push(index);
+            }
+        }
+
+        self.reindex(&sources, indices);
+        self.set_attribute(NORMAL, 3, normals);
+        Ok(())
+    }
+
+    /// Computes per vertex tangents by accumulating each triangle's UV
+    /// tangent, projected onto the vertex's normal plane and weighted by its
+    /// corner angle, then orthogonalizing the sum against the normal with
+    /// Gram-Schmidt. The fourth component holds the bitangent sign, so shaders
+    /// rebuild it as `cross(normal, tangent.xyz) * tangent.w`.
+    ///
+    /// Vertices aren't split, a vertex shared by triangles of opposite
+    /// handedness, e.g. along a UV mirror seam, gets the sign the sum of
+    /// their bitangents points to.
+    ///
+    /// Requires positions, normals and texture coordinates.
+    pub fn compute_tangents(&mut self) -> Result<(), String> {
+        for name in &[POSITION, NORMAL, TEXTURE_COORD] {
+            if self.attribute(name).is_none() {
+                return Err(format!("Cannot compute tangents without a `{}` attribute", name));
+            }
+        }
+
+        let uvs = self.attribute(TEXTURE_COORD).unwrap().to_vec();
+        let uv = |vertex: u32| glm::vec2(uvs[vertex as usize * 2], uvs[vertex as usize * 2 + 1]);
+
+        let mut tangents = vec![glm::vec3(0.0, 0.0, 0.0); self.vertex_count];
+        let mut bitangents = vec![glm::vec3(0.0, 0.0, 0.0); self.vertex_count];
+
+        for triangle in self.indices.chunks(3).filter(|triangle| triangle.len() == 3) {
+            let (p0, p1, p2) = (self.vec3(POSITION, triangle[0]), self.vec3(POSITION, triangle[1]), self.vec3(POSITION, triangle[2]));
+            let (e1, e2) = (p1 - p0, p2 - p0);
+            let (d1, d2) = (uv(triangle[1]) - uv(triangle[0]), uv(triangle[2]) - uv(triangle[0]));
+
+            let determinant = d1.x * d2.y - d2.x * d1.y;
+            if determinant.abs() < 1e-12 {
+                continue;
+            }
+
+            let tangent = (e1 * d2.y - e2 * d1.y) / determinant;
+            let bitangent = (e2 * d1.x - e1 * d2.x) / determinant;
+
+            for corner in 0..3 {
+                let vertex = triangle[corner];
+                let position = self.vec3(POSITION, vertex);
+                let previous = self.vec3(POSITION, triangle[(corner + 2) % 3]);
+                let next = self.vec3(POSITION, triangle[(corner + 1) % 3]);
+                let weight = corner_angle(&(previous - position), &(next - position));
+
+                let normal = self.vec3(NORMAL, vertex);
+                let projected = normalize_or_zero(&(tangent - normal * glm::dot(&normal, &tangent)));
+
+                tangents[vertex as usize] += projected * weight;
+                bitangents[vertex as usize] += bitangent * weight;
+            }
+        }
+
+        let mut values = Vec::with_capacity(self.vertex_count * 4);
+        for vertex in 0..self.vertex_count {
+            let normal = self.vec3(NORMAL, vertex as u32);
+            let mut tangent = normalize_or_zero(&(tangents[vertex] - normal * glm::dot(&normal, &tangents[vertex])));
+
+            if tangent == glm::vec3(0.0, 0.0, 0.0) {
+                tangent = any_perpendicular(&normal);
+            }
+
+            let handedness = if glm::dot(&normal.cross(&tangent), &bitangents[vertex]) < 0.0 { -1.0 } else { 1.0 };
+            values.extend_from_slice(&[tangent.x, tangent.y, tangent.z, handedness]);
+        }
+
+        self.set_attribute(TANGENT, 4, values);
+        Ok(())
+    }
+
+    /// Rebuilds every attribute so that new vertex `i` copies old vertex `sources[i]`.
+    fn reindex(&mut self, sources: &[u32], indices: Vec<u32>) {
+        for (_, size, values) in self.attributes.iter_mut() {
+            let size = *size as usize;
+            *values = sources
+                .iter()
+                .flat_map(|&source| values[source as usize * size..(source as usize + 1) * size].to_vec())
+                .collect();
+        }
+
+        self.vertex_count = sources.len();
+        self.indices = indices;
+    }
+}
+
+impl Model for Geometry {
+    fn layout(&self) -> VertexLayout {
+        let attributes: Vec<(&str, GLint)> = self.attributes
+            .iter()
+            .map(|(name, size, _)| (name.as_str(), *size))
+            .collect();
+
+        VertexLayout::planar(&attributes, self.vertex_count)
+    }
+
+    fn vertices(&self) -> Vec<u8> {
+        self.attributes
+            .iter()
+            .flat_map(|(_, _, values)| f32_bytes(values))
+            .collect()
+    }
+
+    fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    fn indices(&self) -> Indices {
+        Indices::from_u32(self.indices.clone())
+    }
+}
+
+fn normalize_or_zero(v: &Vec3) -> Vec3 {
+    let length = glm::length(v);
+    if length > 1e-12 {
+        v / length
+    } else {
+        glm::vec3(0.0, 0.0, 0.0)
+    }
+}
+
+fn corner_angle(a: &Vec3, b: &Vec3) -> f32 {
+    let (a, b) = (normalize_or_zero(a), normalize_or_zero(b));
+    glm::dot(&a, &b).clamp(-1.0, 1.0).acos()
+}
+
+fn any_perpendicular(normal: &Vec3) -> Vec3 {
+    let axis = if normal.x.abs() < 0.9 { glm::vec3(1.0, 0.0, 0.0) } else { glm::vec3(0.0, 1.0, 0.0) };
+    normalize_or_zero(&normal.cross(&axis))
+}
+
+fn position_key(position: &Vec3) -> [u32; 3] {
+    // Adding zero folds -0.0 into 0.0 so both land on the same key.
+    [(position.x + 0.0).to_bits(), (position.y + 0.0).to_bits(), (position.z + 0.0).to_bits()]
+}
+
+fn normal_key(normal: &Vec3) -> [i32; 3] {
+    let quantize = |value: f32| (value * 1e5).round() as i32;
+    [quantize(normal.x), quantize(normal.y), quantize(normal.z)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Cube;
+
+    /// The normal of each face of `Cube`, in the order its faces are stored.
+    const CUBE_AXES: [[f32; 3]; 6] = [
+        [0.0, 0.0, 1.0],
+        [0.0, 0.0, -1.0],
+        [0.0, 1.0, 0.0],
+        [0.0, -1.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [-1.0, 0.0, 0.0],
+    ];
+
+    fn cube() -> Geometry {
+        Geometry::from_model(&Cube::new())
+    }
+
+    /// The positions of every triangle's corners.
+    fn triangles(geometry: &Geometry) -> Vec<[Vec3; 3]> {
+        geometry
+            .indices
+            .chunks(3)
+            .map(|triangle| {
+                [
+                    geometry.vec3(POSITION, triangle[0]),
+                    geometry.vec3(POSITION, triangle[1]),
+                    geometry.vec3(POSITION, triangle[2]),
+                ]
+            })
+            .collect()
+    }
+
+    fn assert_near(a: &Vec3, b: &Vec3) {
+        assert!(glm::distance(a, b) < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn flat_cube_normals_are_the_face_axes() {
+        let mut geometry = cube();
+        geometry.compute_flat_normals().unwrap();
+
+        // Both triangles of a face share its four vertices.
+        assert_eq!(geometry.vertex_count, 24);
+        for (triangle, corners) in geometry.indices.chunks(3).enumerate() {
+            let [x, y, z] = CUBE_AXES[triangle / 2];
+            for &vertex in corners {
+                assert_near(&geometry.vec3(NORMAL, vertex), &glm::vec3(x, y, z));
+            }
+        }
+    }
+
+    #[test]
+    fn creases_below_a_right_angle_keep_the_cube_flat() {
+        let mut flat = cube();
+        flat.compute_flat_normals().unwrap();
+
+        let mut creased = cube();
+        creased.compute_normals(PI / 2.0 - 0.1).unwrap();
+
+        assert_eq!(creased, flat);
+    }
+
+    #[test]
+    fn smooth_cube_normals_point_out_of_the_corners() {
+        let mut geometry = cube();
+        geometry.compute_smooth_normals().unwrap();
+
+        for corners in geometry.indices.chunks(3) {
+            for &vertex in corners {
+                let expected = glm::normalize(&geometry.vec3(POSITION, vertex));
+                assert_near(&geometry.vec3(NORMAL, vertex), &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_are_unit_length_and_orthogonal_to_the_normals() {
+        let mut geometry = cube();
+        geometry.compute_flat_normals().unwrap();
+        geometry.compute_tangents().unwrap();
+
+        let tangents = geometry.attribute(TANGENT).unwrap().to_vec();
+        assert_eq!(tangents.len(), geometry.vertex_count * 4);
+        for (vertex, tangent) in tangents.chunks(4).enumerate() {
+            let normal = geometry.vec3(NORMAL, vertex as u32);
+            let xyz = glm::vec3(tangent[0], tangent[1], tangent[2]);
+
+            assert!((glm::length(&xyz) - 1.0).abs() < 1e-5);
+            assert!(glm::dot(&normal, &xyz).abs() < 1e-5);
+            assert!(tangent[3] == 1.0 || tangent[3] == -1.0);
+        }
+    }
+
+    #[test]
+    fn tangents_need_texture_coordinates() {
+        let mut geometry = cube();
+        geometry.compute_flat_normals().unwrap();
+        geometry.attributes.retain(|(name, _, _)| name != TEXTURE_COORD);
+
+        assert!(geometry.compute_tangents().is_err());
+    }
+
+    #[test]
+    fn reindex_preserves_triangles() {
+        let mut geometry = cube();
+        let before = triangles(&geometry);
+
+        // Reverse the vertices and duplicate the first one.
+        let count = geometry.vertex_count as u32;
+        let mut sources: Vec<u32> = (0..count).rev().collect();
+        sources.push(0);
+        let indices = geometry
+            .indices
+            .iter()
+            .map(|&index| if index == 0 { count } else { count - 1 - index })
+            .collect();
+        geometry.reindex(&sources, indices);
+
+        assert_eq!(geometry.vertex_count, 25);
+        assert_eq!(triangles(&geometry), before);
+        assert_eq!(geometry.attribute("aVertexColor").unwrap().len(), 25 * 4);
+    }
+
+    #[test]
+    fn computing_normals_preserves_triangles() {
+        let mut geometry = cube();
+        let before = triangles(&geometry);
+        geometry.compute_normals(PI / 4.0).unwrap();
+
+        assert_eq!(triangles(&geometry), before);
+    }
+}
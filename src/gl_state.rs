@@ -0,0 +1,502 @@
+use std::cell::RefCell;
+use std::ops::Deref;
+
+use crate::commands::{self, Command, CommandBuffer, Object};
+use crate::webgl_rendering_context::{
+    GLbitfield, GLboolean, GLclampf, GLenum, GLfloat, GLint, GLintptr, GLsizei, GLuint, WebGLBuffer, WebGLFramebuffer,
+    WebGLProgram, WebGLRenderbuffer, WebGLRenderingContext, WebGLTexture, WebGLUniformLocation,
+};
+
+/// How many state changes were sent to WebGL and how many were skipped for
+/// setting what was already set, and in how many batches the calls went.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CallCounts {
+    pub issued: u32,
+    pub skipped: u32,
+    pub batches: u32,
+}
+
+/// The state last set through `Gl`, `None` where it isn't known.
+///
+/// Objects are kept as the words the command buffer encodes them as, which
+/// stay the same for an object as long as its table isn't started over.
+#[derive(Default)]
+struct Shadow {
+    program: Option<u32>,
+    array_buffer: Option<u32>,
+    element_array_buffer: Option<u32>,
+    framebuffer: Option<u32>,
+    renderbuffer: Option<u32>,
+    active_texture: Option<GLenum>,
+    /// Texture bound per texture unit and target.
+    textures: Vec<((GLenum, GLenum), u32)>,
+    capabilities: Vec<(GLenum, bool)>,
+    attribute_arrays: Vec<(GLuint, bool)>,
+    /// Buffer and layout each attribute was pointed at.
+    attribute_pointers: Vec<(GLuint, (u32, GLint, GLenum, GLboolean, GLsizei, GLintptr))>,
+    depth_func: Option<GLenum>,
+    depth_mask: Option<GLboolean>,
+    blend_func: Option<(GLenum, GLenum)>,
+    viewport: Option<(GLint, GLint, GLsizei, GLsizei)>,
+    clear_color: Option<(GLclampf, GLclampf, GLclampf, GLclampf)>,
+    clear_depth: Option<GLclampf>,
+    counts: CallCounts,
+}
+
+impl Shadow {
+    /// Forgets the objects bound, for when the words they're known by are
+    /// reused.
+    fn forget_objects(&mut self) {
+        self.program = None;
+        self.array_buffer = None;
+        self.element_array_buffer = None;
+        self.framebuffer = None;
+        self.renderbuffer = None;
+        self.textures.clear();
+        self.attribute_pointers.clear();
+    }
+}
+
+/// Records `value` as the current state. Returns whether the call has to be
+/// made, i.e. the value is new or the state wasn't known.
+fn change<T: PartialEq>(current: &mut Option<T>, value: T, counts: &mut CallCounts) -> bool {
+    if current.as_ref() == Some(&value) {
+        counts.skipped += 1;
+        return false;
+    }
+
+    *current = Some(value);
+    counts.issued += 1;
+    true
+}
+
+/// Same as `change`, for state kept per key such as a capability or an
+/// attribute location.
+fn change_keyed<K: PartialEq, T: PartialEq>(entries: &mut Vec<(K, T)>, key: K, value: T, counts: &mut CallCounts) -> bool {
+    match entries.iter_mut().find(|(existing, _)| *existing == key) {
+        Some((_, current)) if *current == value => {
+            counts.skipped += 1;
+            return false;
+        }
+        Some((_, current)) => *current = value,
+        None => entries.push((key, value)),
+    }
+
+    counts.issued += 1;
+    true
+}
+
+/// A WebGL context that remembers the state set through it and leaves out
+/// calls that wouldn't change anything.
+///
+/// Every call is a trip into JavaScript, and the renderer sets most of its
+/// state again every frame. The methods shadow the context's own, so code
+/// holding a `&Gl` goes through the cache without changes.
+///
+/// The calls that are made aren't made right away either, they're recorded
+/// into a `CommandBuffer` and sent over together by `submit`. Anything else,
+/// in particular calls that return a value, goes through `Deref`, which
+/// submits what's recorded first so the order is kept.
+///
+/// State changed by going around it, e.g. with `js!`, makes the cache wrong,
+/// `reset` forgets everything it knows.
+pub struct Gl {
+    context: WebGLRenderingContext,
+    shadow: RefCell<Shadow>,
+    commands: RefCell<CommandBuffer>,
+}
+
+impl Gl {
+    pub fn new(context: WebGLRenderingContext) -> Self {
+        Self {
+            context,
+            shadow: RefCell::new(Shadow::default()),
+            commands: RefCell::new(CommandBuffer::new()),
+        }
+    }
+
+    /// Forgets the known state, e.g. after the context was restored with
+    /// everything back at its defaults. Calls not submitted yet are dropped,
+    /// the objects they use are gone. The counts are kept.
+    pub fn reset(&self) {
+        let mut shadow = self.shadow.borrow_mut();
+        let counts = shadow.counts;
+        *shadow = Shadow { counts, ..Shadow::default() };
+        self.commands.borrow_mut().clear();
+    }
+
+    /// Makes the recorded calls, in a single call into JavaScript.
+    pub fn submit(&self) {
+        let mut commands = self.commands.borrow_mut();
+        if commands.is_empty() {
+            return;
+        }
+
+        let restarted = commands.replay(&self.context);
+        let mut shadow = self.shadow.borrow_mut();
+        shadow.counts.batches += 1;
+        if restarted {
+            shadow.forget_objects();
+        }
+    }
+
+    fn record(&self, command: Command, arguments: &[u32]) {
+        self.commands.borrow_mut().push(command, arguments);
+    }
+
+    fn object<T: Object>(&self, object: Option<&T>) -> u32 {
+        self.commands.borrow_mut().object(object)
+    }
+
+    /// Returns the calls counted since the last time and starts over.
+    pub fn take_counts(&self) -> CallCounts {
+        std::mem::replace(&mut self.shadow.borrow_mut().counts, CallCounts::default())
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/useProgram
+    pub fn use_program(&self, program: Option<&WebGLProgram>) {
+        let program = self.object(program);
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change(&mut shadow.program, program, &mut shadow.counts) {
+            self.record(Command::UseProgram, &[program]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/bindBuffer
+    pub fn bind_buffer(&self, target: GLenum, buffer: Option<&WebGLBuffer>) {
+        let buffer = self.object(buffer);
+        let shadow = &mut *self.shadow.borrow_mut();
+        let current = match target {
+            WebGLRenderingContext::ARRAY_BUFFER => &mut shadow.array_buffer,
+            WebGLRenderingContext::ELEMENT_ARRAY_BUFFER => &mut shadow.element_array_buffer,
+            _ => return self.record(Command::BindBuffer, &[target, buffer]),
+        };
+
+        if change(current, buffer, &mut shadow.counts) {
+            self.record(Command::BindBuffer, &[target, buffer]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/bindFramebuffer
+    pub fn bind_framebuffer(&self, target: GLenum, framebuffer: Option<&WebGLFramebuffer>) {
+        let framebuffer = self.object(framebuffer);
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change(&mut shadow.framebuffer, framebuffer, &mut shadow.counts) {
+            self.record(Command::BindFramebuffer, &[target, framebuffer]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/bindRenderbuffer
+    pub fn bind_renderbuffer(&self, target: GLenum, renderbuffer: Option<&WebGLRenderbuffer>) {
+        let renderbuffer = self.object(renderbuffer);
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change(&mut shadow.renderbuffer, renderbuffer, &mut shadow.counts) {
+            self.record(Command::BindRenderbuffer, &[target, renderbuffer]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/activeTexture
+    pub fn active_texture(&self, texture: GLenum) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change(&mut shadow.active_texture, texture, &mut shadow.counts) {
+            self.record(Command::ActiveTexture, &[texture]);
+        }
+    }
+
+    /// Binds a texture to the active texture unit. While the unit isn't
+    /// known neither is what's bound to it, so the call is always made.
+    ///
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/bindTexture
+    pub fn bind_texture(&self, target: GLenum, texture: Option<&WebGLTexture>) {
+        let texture = self.object(texture);
+        let shadow = &mut *self.shadow.borrow_mut();
+        let unit = match shadow.active_texture {
+            Some(unit) => unit,
+            None => {
+                shadow.counts.issued += 1;
+                return self.record(Command::BindTexture, &[target, texture]);
+            }
+        };
+
+        if change_keyed(&mut shadow.textures, (unit, target), texture, &mut shadow.counts) {
+            self.record(Command::BindTexture, &[target, texture]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/enable
+    pub fn enable(&self, capability: GLenum) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change_keyed(&mut shadow.capabilities, capability, true, &mut shadow.counts) {
+            self.record(Command::Enable, &[capability]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/disable
+    pub fn disable(&self, capability: GLenum) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change_keyed(&mut shadow.capabilities, capability, false, &mut shadow.counts) {
+            self.record(Command::Disable, &[capability]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/enableVertexAttribArray
+    pub fn enable_vertex_attrib_array(&self, index: GLuint) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change_keyed(&mut shadow.attribute_arrays, index, true, &mut shadow.counts) {
+            self.record(Command::EnableVertexAttribArray, &[index]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/disableVertexAttribArray
+    pub fn disable_vertex_attrib_array(&self, index: GLuint) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change_keyed(&mut shadow.attribute_arrays, index, false, &mut shadow.counts) {
+            self.record(Command::DisableVertexAttribArray, &[index]);
+        }
+    }
+
+    /// Points an attribute at the bound `ARRAY_BUFFER`, which is part of
+    /// what's remembered. While the buffer isn't known the call is always made.
+    ///
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/vertexAttribPointer
+    pub fn vertex_attrib_pointer(
+        &self,
+        index: GLuint,
+        size: GLint,
+        type_: GLenum,
+        normalized: GLboolean,
+        stride: GLsizei,
+        offset: GLintptr,
+    ) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        let buffer = match shadow.array_buffer {
+            Some(buffer) => buffer,
+            None => {
+                shadow.counts.issued += 1;
+                return self.record_attrib_pointer(index, size, type_, normalized, stride, offset);
+            }
+        };
+
+        let pointer = (buffer, size, type_, normalized, stride, offset);
+        if change_keyed(&mut shadow.attribute_pointers, index, pointer, &mut shadow.counts) {
+            self.record_attrib_pointer(index, size, type_, normalized, stride, offset);
+        }
+    }
+
+    fn record_attrib_pointer(&self, index: GLuint, size: GLint, type_: GLenum, normalized: GLboolean, stride: GLsizei, offset: GLintptr) {
+        // Offsets into a buffer fit in 32 bits, buffers can't be that large.
+        let arguments = [index, size as u32, type_, commands::flag(normalized), stride as u32, offset as u32];
+        self.record(Command::VertexAttribPointer, &arguments);
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/depthFunc
+    pub fn depth_func(&self, func: GLenum) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change(&mut shadow.depth_func, func, &mut shadow.counts) {
+            self.record(Command::DepthFunc, &[func]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/depthMask
+    pub fn depth_mask(&self, flag: GLboolean) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change(&mut shadow.depth_mask, flag, &mut shadow.counts) {
+            self.record(Command::DepthMask, &[commands::flag(flag)]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/blendFunc
+    pub fn blend_func(&self, source: GLenum, destination: GLenum) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change(&mut shadow.blend_func, (source, destination), &mut shadow.counts) {
+            self.record(Command::BlendFunc, &[source, destination]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/viewport
+    pub fn viewport(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change(&mut shadow.viewport, (x, y, width, height), &mut shadow.counts) {
+            self.record(Command::Viewport, &[x as u32, y as u32, width as u32, height as u32]);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/clearColor
+    pub fn clear_color(&self, red: GLclampf, green: GLclampf, blue: GLclampf, alpha: GLclampf) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change(&mut shadow.clear_color, (red, green, blue, alpha), &mut shadow.counts) {
+            let arguments = [commands::float(red), commands::float(green), commands::float(blue), commands::float(alpha)];
+            self.record(Command::ClearColor, &arguments);
+        }
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/clearDepth
+    pub fn clear_depth(&self, depth: GLclampf) {
+        let shadow = &mut *self.shadow.borrow_mut();
+        if change(&mut shadow.clear_depth, depth, &mut shadow.counts) {
+            self.record(Command::ClearDepth, &[commands::float(depth)]);
+        }
+    }
+
+    // Calls below are recorded but not cached, uniforms are cached by
+    // `Uniform` and draws always have an effect.
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/clear
+    pub fn clear(&self, mask: GLbitfield) {
+        self.record(Command::Clear, &[mask]);
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/uniform
+    pub fn uniform1f(&self, location: Option<&WebGLUniformLocation>, x: GLfloat) {
+        self.record(Command::Uniform1f, &[self.object(location), commands::float(x)]);
+    }
+
+    pub fn uniform1i(&self, location: Option<&WebGLUniformLocation>, x: GLint) {
+        self.record(Command::Uniform1i, &[self.object(location), x as u32]);
+    }
+
+    pub fn uniform2f(&self, location: Option<&WebGLUniformLocation>, x: GLfloat, y: GLfloat) {
+        self.record(Command::Uniform2f, &[self.object(location), commands::float(x), commands::float(y)]);
+    }
+
+    pub fn uniform3f(&self, location: Option<&WebGLUniformLocation>, x: GLfloat, y: GLfloat, z: GLfloat) {
+        let arguments = [self.object(location), commands::float(x), commands::float(y), commands::float(z)];
+        self.record(Command::Uniform3f, &arguments);
+    }
+
+    pub fn uniform4f(&self, location: Option<&WebGLUniformLocation>, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat) {
+        let arguments = [
+            self.object(location),
+            commands::float(x),
+            commands::float(y),
+            commands::float(z),
+            commands::float(w),
+        ];
+        self.record(Command::Uniform4f, &arguments);
+    }
+
+    /// Uploads a column major matrix. WebGL1 doesn't allow transposing, so
+    /// there's no argument for it.
+    ///
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/uniformMatrix
+    pub fn uniform_matrix3fv(&self, location: Option<&WebGLUniformLocation>, value: &[f32]) {
+        self.record_matrix(Command::UniformMatrix3fv, location, value, 9);
+    }
+
+    pub fn uniform_matrix4fv(&self, location: Option<&WebGLUniformLocation>, value: &[f32]) {
+        self.record_matrix(Command::UniformMatrix4fv, location, value, 16);
+    }
+
+    fn record_matrix(&self, command: Command, location: Option<&WebGLUniformLocation>, value: &[f32], len: usize) {
+        assert_eq!(value.len(), len, "A matrix uniform takes {} values", len);
+
+        let mut arguments = Vec::with_capacity(len + 1);
+        arguments.push(self.object(location));
+        arguments.extend(value.iter().map(|&value| commands::float(value)));
+        self.record(command, &arguments);
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/drawArrays
+    pub fn draw_arrays(&self, mode: GLenum, first: GLint, count: GLsizei) {
+        self.record(Command::DrawArrays, &[mode, first as u32, count as u32]);
+    }
+
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/drawElements
+    pub fn draw_elements(&self, mode: GLenum, count: GLsizei, type_: GLenum, offset: GLintptr) {
+        self.record(Command::DrawElements, &[mode, count as u32, type_, offset as u32]);
+    }
+}
+
+impl Deref for Gl {
+    type Target = WebGLRenderingContext;
+
+    /// Submits the recorded calls, whatever is done with the context next
+    /// has to see their effects.
+    fn deref(&self) -> &WebGLRenderingContext {
+        self.submit();
+        &self.context
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXTURE0: GLenum = WebGLRenderingContext::TEXTURE0;
+    const TEXTURE1: GLenum = WebGLRenderingContext::TEXTURE1;
+    const TEXTURE_2D: GLenum = WebGLRenderingContext::TEXTURE_2D;
+    const TEXTURE_CUBE_MAP: GLenum = WebGLRenderingContext::TEXTURE_CUBE_MAP;
+
+    #[test]
+    fn change_skips_what_is_already_set() {
+        let mut counts = CallCounts::default();
+        let mut current = None;
+
+        assert!(change(&mut current, 1, &mut counts));
+        assert!(!change(&mut current, 1, &mut counts));
+        assert!(change(&mut current, 2, &mut counts));
+        assert!(!change(&mut current, 2, &mut counts));
+
+        assert_eq!(current, Some(2));
+        assert_eq!(counts, CallCounts { issued: 2, skipped: 2, batches: 0 });
+    }
+
+    #[test]
+    fn change_keyed_caches_each_key_separately() {
+        let mut counts = CallCounts::default();
+        let mut capabilities = Vec::new();
+
+        assert!(change_keyed(&mut capabilities, WebGLRenderingContext::DEPTH_TEST, true, &mut counts));
+        assert!(change_keyed(&mut capabilities, WebGLRenderingContext::BLEND, true, &mut counts));
+        assert!(!change_keyed(&mut capabilities, WebGLRenderingContext::DEPTH_TEST, true, &mut counts));
+        assert!(change_keyed(&mut capabilities, WebGLRenderingContext::BLEND, false, &mut counts));
+        assert!(!change_keyed(&mut capabilities, WebGLRenderingContext::BLEND, false, &mut counts));
+
+        assert_eq!(capabilities.len(), 2);
+        assert_eq!(counts, CallCounts { issued: 3, skipped: 2, batches: 0 });
+    }
+
+    #[test]
+    fn textures_are_cached_per_unit_and_target() {
+        let mut counts = CallCounts::default();
+        let mut textures = Vec::new();
+
+        assert!(change_keyed(&mut textures, (TEXTURE0, TEXTURE_2D), 1, &mut counts));
+        // The same texture on another unit, or another target of the same
+        // unit, is a binding of its own.
+        assert!(change_keyed(&mut textures, (TEXTURE1, TEXTURE_2D), 1, &mut counts));
+        assert!(change_keyed(&mut textures, (TEXTURE0, TEXTURE_CUBE_MAP), 2, &mut counts));
+
+        assert!(!change_keyed(&mut textures, (TEXTURE0, TEXTURE_2D), 1, &mut counts));
+        assert!(!change_keyed(&mut textures, (TEXTURE1, TEXTURE_2D), 1, &mut counts));
+        assert!(!change_keyed(&mut textures, (TEXTURE0, TEXTURE_CUBE_MAP), 2, &mut counts));
+
+        assert!(change_keyed(&mut textures, (TEXTURE1, TEXTURE_2D), 3, &mut counts));
+        assert!(!change_keyed(&mut textures, (TEXTURE0, TEXTURE_2D), 1, &mut counts));
+
+        assert_eq!(counts, CallCounts { issued: 4, skipped: 4, batches: 0 });
+    }
+
+    #[test]
+    fn forgetting_objects_keeps_the_rest_of_the_state() {
+        let mut shadow = Shadow::default();
+        let mut counts = CallCounts::default();
+        change(&mut shadow.program, 1, &mut counts);
+        change(&mut shadow.array_buffer, 2, &mut counts);
+        change(&mut shadow.active_texture, TEXTURE1, &mut counts);
+        change_keyed(&mut shadow.textures, (TEXTURE1, TEXTURE_2D), 3, &mut counts);
+        change_keyed(&mut shadow.capabilities, WebGLRenderingContext::DEPTH_TEST, true, &mut counts);
+        change(&mut shadow.viewport, (0, 0, 640, 480), &mut counts);
+
+        shadow.forget_objects();
+
+        // The words objects were known by may mean other objects now.
+        assert!(change(&mut shadow.program, 1, &mut counts));
+        assert!(change(&mut shadow.array_buffer, 2, &mut counts));
+        assert!(change_keyed(&mut shadow.textures, (TEXTURE1, TEXTURE_2D), 3, &mut counts));
+
+        assert!(!change(&mut shadow.active_texture, TEXTURE1, &mut counts));
+        assert!(!change_keyed(&mut shadow.capabilities, WebGLRenderingContext::DEPTH_TEST, true, &mut counts));
+        assert!(!change(&mut shadow.viewport, (0, 0, 640, 480), &mut counts));
+    }
+}
@@ -0,0 +1,567 @@
+use super::builtins;
+use super::diagnostic::{Diagnostic, Severity, Stage};
+use super::lexer::TokenKind;
+use super::parser::{Shader, Variable, Version};
+use super::Preprocessed;
+
+const FLOAT_TYPES: &[&str] = &[
+    "float", "vec2", "vec3", "vec4", "mat2", "mat3", "mat4",
+    "mat2x2", "mat2x3", "mat2x4", "mat3x2", "mat3x3", "mat3x4", "mat4x2", "mat4x3", "mat4x4",
+];
+
+const ARITHMETIC: &[&str] = &["+", "-", "*", "/", "<", ">", "<=", ">=", "==", "!="];
+const ASSIGNMENT: &[&str] = &["=", "+=", "-=", "*=", "/="];
+const RELATIONAL: &[&str] = &["<", ">", "<=", ">=", "==", "!="];
+
+/// Built-ins that only take floating point arguments in every GLSL ES version.
+const FLOAT_ONLY_FUNCTIONS: &[&str] = &[
+    "radians", "degrees", "sin", "cos", "tan", "asin", "acos", "atan", "sinh", "cosh", "tanh",
+    "pow", "exp", "log", "exp2", "log2", "sqrt", "inversesqrt", "floor", "ceil", "fract", "trunc",
+    "round", "mix", "step", "smoothstep", "length", "distance", "dot", "cross", "normalize",
+    "reflect", "refract", "faceforward",
+];
+
+/// Built-ins that gained integer overloads in GLSL ES 3.00.
+const FLOAT_ONLY_FUNCTIONS_ES100: &[&str] = &["abs", "sign", "min", "max", "clamp", "mod"];
+
+/// A problem found in one stage, located in the expanded source.
+struct Finding {
+    severity: Severity,
+    line: u32,
+    message: String,
+}
+
+/// Lints a vertex and fragment shader pair for mistakes that drivers handle
+/// inconsistently, so they are caught before they only fail on some GPUs.
+///
+/// Both sources are expected to be preprocessed, findings are mapped back to
+/// the original files through their source maps.
+pub fn analyze(vertex: &Preprocessed, fragment: &Preprocessed) -> Vec<Diagnostic> {
+    let vertex_shader = Shader::parse(&vertex.source);
+    let fragment_shader = Shader::parse(&fragment.source);
+
+    let mut diagnostics = Vec::new();
+    let mut report = |source: &Preprocessed, findings: Vec<Finding>| {
+        for finding in findings {
+            let location = source.source_map.lookup(finding.line).cloned();
+            diagnostics.push(Diagnostic { severity: finding.severity, location, message: finding.message });
+        }
+    };
+
+    report(vertex, lint_stage(Stage::Vertex, &vertex_shader));
+    report(fragment, lint_stage(Stage::Fragment, &fragment_shader));
+
+    let (vertex_findings, fragment_findings) = lint_varyings(&vertex_shader, &fragment_shader);
+    report(vertex, vertex_findings);
+    report(fragment, fragment_findings);
+
+    diagnostics
+}
+
+fn lint_stage(stage: Stage, shader: &Shader) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if stage == Stage::Fragment && !shader.precisions.iter().any(|precision| precision.type_name == "float") {
+        let line = shader.significant().first().map_or(1, |&index| shader.tokens[index].line);
+        findings.push(Finding {
+            severity: Severity::Error,
+            line,
+            message: String::from("Fragment shaders have no default float precision, add `precision mediump float;`"),
+        });
+    }
+
+    if shader.version == Version::Es100 {
+        lint_loops(shader, &mut findings);
+    }
+
+    lint_literals(shader, &mut findings);
+
+    for uniform in shader.globals.iter().filter(|variable| variable.has_qualifier("uniform")) {
+        if shader.references(&uniform.name).is_empty() {
+            findings.push(Finding {
+                severity: Severity::Warning,
+                line: uniform.line,
+                message: format!("Uniform `{}` is declared but never used, the driver will optimize it away", uniform.name),
+            });
+        }
+    }
+
+    findings.sort_by_key(|finding| finding.line);
+    findings
+}
+
+/// The variables a stage passes to, or receives from, the other stage.
+pub fn varyings<'s>(shader: &'s Shader, stage: Stage) -> Vec<&'s Variable> {
+    let qualifier = match (shader.version, stage) {
+        (Version::Es100, _) => "varying",
+        (Version::Es300, Stage::Vertex) => "out",
+        (Version::Es300, Stage::Fragment) => "in",
+    };
+
+    shader.globals
+        .iter()
+        .filter(|variable| variable.has_qualifier(qualifier))
+        .collect()
+}
+
+fn lint_varyings(vertex: &Shader, fragment: &Shader) -> (Vec<Finding>, Vec<Finding>) {
+    let vertex_varyings = varyings(vertex, Stage::Vertex);
+    let fragment_varyings = varyings(fragment, Stage::Fragment);
+    let mut vertex_findings = Vec::new();
+    let mut fragment_findings = Vec::new();
+
+    for varying in &vertex_varyings {
+        if vertex.references(&varying.name).is_empty() {
+            vertex_findings.push(unused_varying(varying));
+        } else if !fragment_varyings.iter().any(|other| other.name == varying.name) {
+            vertex_findings.push(Finding {
+                severity: Severity::Warning,
+                line: varying.line,
+                message: format!("Varying `{}` is written by the vertex stage but the fragment stage never declares it", varying.name),
+            });
+        }
+    }
+
+    for varying in &fragment_varyings {
+        let used = !fragment.references(&varying.name).is_empty();
+        if !used {
+            fragment_findings.push(unused_varying(varying));
+        }
+
+        if !vertex_varyings.iter().any(|other| other.name == varying.name) {
+            fragment_findings.push(Finding {
+                // Drivers only reject the link when the varying is actually read.
+                severity: if used { Severity::Error } else { Severity::Warning },
+                line: varying.line,
+                message: format!("Varying `{}` is declared in the fragment stage but not in the vertex stage", varying.name),
+            });
+        }
+    }
+
+    (vertex_findings, fragment_findings)
+}
+
+fn unused_varying(varying: &Variable) -> Finding {
+    Finding {
+        severity: Severity::Warning,
+        line: varying.line,
+        message: format!("Varying `{}` is declared but never used", varying.name),
+    }
+}
+
+/// GLSL ES 1.00 Appendix A only guarantees `for` loops of the form
+/// `for (type i = constant; i op constant; i++)`, WebGL1 rejects the rest.
+fn lint_loops(shader: &Shader, findings: &mut Vec<Finding>) {
+    for for_loop in &shader.loops {
+        let text = |indices: &[usize]| -> Vec<&str> { indices.iter().map(|&index| shader.tokens[index].text).collect() };
+        let init = shader.significant_in(for_loop.init);
+        let condition = shader.significant_in(for_loop.condition);
+        let increment = shader.significant_in(for_loop.increment);
+
+        let init_text = text(&init);
+        let index = match init_text.as_slice() {
+            [type_name, name, "=", ..] if *type_name == "int" || *type_name == "float" => *name,
+            _ => {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    line: for_loop.line,
+                    message: String::from("WebGL1 requires the loop index to be declared and initialized in the `for` statement"),
+                });
+                continue;
+            }
+        };
+
+        if !is_constant(shader, &init[3..]) {
+            findings.push(Finding {
+                severity: Severity::Error,
+                line: for_loop.line,
+                message: format!("Loop index `{}` must start at a constant expression on WebGL1", index),
+            });
+        }
+
+        let condition_text = text(&condition);
+        let bounded = condition_text.len() > 2
+            && condition_text[0] == index
+            && RELATIONAL.contains(&condition_text[1])
+            && is_constant(shader, &condition[2..]);
+
+        if !bounded {
+            findings.push(Finding {
+                severity: Severity::Error,
+                line: for_loop.line,
+                message: format!(
+                    "Loop condition `{}` is not `{} <op> constant`, WebGL1 only allows loops with constant bounds",
+                    condition_text.join(" "),
+                    index
+                ),
+            });
+        }
+
+        let increment_text = text(&increment);
+        let stepped = match increment_text.as_slice() {
+            [name, "++"] | [name, "--"] | ["++", name] | ["--", name] => *name == index,
+            [name, operator, ..] if *operator == "+=" || *operator == "-=" => *name == index && is_constant(shader, &increment[2..]),
+            _ => false,
+        };
+
+        if !stepped {
+            findings.push(Finding {
+                severity: Severity::Error,
+                line: for_loop.line,
+                message: format!("Loop index `{}` must change by a constant step on WebGL1", index),
+            });
+        }
+    }
+}
+
+/// Whether every token of an expression is a literal, operator, constructor,
+/// built-in function, macro or `const` variable.
+fn is_constant(shader: &Shader, indices: &[usize]) -> bool {
+    !indices.is_empty() && indices.iter().all(|&index| {
+        let token = &shader.tokens[index];
+        match token.kind {
+            TokenKind::IntLiteral | TokenKind::FloatLiteral => true,
+            TokenKind::Symbol => !ASSIGNMENT.contains(&token.text),
+            TokenKind::Identifier => {
+                token.text == "true"
+                    || token.text == "false"
+                    || builtins::is_type(token.text)
+                    || builtins::is_function(token.text)
+                    || shader.macros.iter().any(|name| name == token.text)
+                    || shader.variable_at(token.text, index).map_or(false, |variable| variable.has_qualifier("const"))
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Finds integer literals mixed into floating point expressions, which GLSL ES
+/// never converts implicitly but some desktop drivers silently accept.
+fn lint_literals(shader: &Shader, findings: &mut Vec<Finding>) {
+    let significant = shader.significant();
+    let text = |position: usize| significant.get(position).map_or("", |&index| shader.tokens[index].text);
+
+    for (position, &index) in significant.iter().enumerate() {
+        let literal = &shader.tokens[index];
+        if literal.kind != TokenKind::IntLiteral {
+            continue;
+        }
+
+        // Treat a unary minus as part of the literal.
+        let mut before = position;
+        if position >= 2 && text(position - 1) == "-" && is_operand_start(text(position - 2)) {
+            before = position - 1;
+        }
+
+        let left_is_float = before >= 2
+            && (ARITHMETIC.contains(&text(before - 1)) || ASSIGNMENT.contains(&text(before - 1)))
+            && operand_before_is_float(shader, &significant, before - 2);
+
+        let right_is_float = ARITHMETIC.contains(&text(position + 1))
+            && operand_after_is_float(shader, &significant, position + 2);
+
+        let float_argument = before >= 1
+            && (text(before - 1) == "(" || text(before - 1) == ",")
+            && (text(position + 1) == ")" || text(position + 1) == ",")
+            && enclosing_call(shader, &significant, before - 1).map_or(false, |name| {
+                FLOAT_ONLY_FUNCTIONS.contains(&name)
+                    || (shader.version == Version::Es100 && FLOAT_ONLY_FUNCTIONS_ES100.contains(&name))
+            });
+
+        if left_is_float || right_is_float || float_argument {
+            findings.push(Finding {
+                severity: Severity::Error,
+                line: literal.line,
+                message: format!(
+                    "Integer literal `{}` used where a float is expected, write `{}.0`",
+                    literal.text,
+                    literal.text.trim_end_matches(|c| c == 'u' || c == 'U')
+                ),
+            });
+        }
+    }
+}
+
+fn is_operand_start(previous: &str) -> bool {
+    previous == "(" || previous == "," || previous == "?" || previous == ":"
+        || ARITHMETIC.contains(&previous) || ASSIGNMENT.contains(&previous)
+}
+
+fn is_float_type(type_name: &str) -> bool {
+    FLOAT_TYPES.contains(&type_name)
+}
+
+/// Whether the operand ending at `position` is known to be floating point.
+fn operand_before_is_float(shader: &Shader, significant: &[usize], position: usize) -> bool {
+    let index = significant[position];
+    let token = &shader.tokens[index];
+
+    match token.kind {
+        TokenKind::FloatLiteral => true,
+        TokenKind::Identifier => {
+            // A swizzle like `v.x` is floating point when `v` is a float vector.
+            if position >= 2 && shader.tokens[significant[position - 1]].is(".") {
+                let base = &shader.tokens[significant[position - 2]];
+                return base.kind == TokenKind::Identifier
+                    && shader.variable_at(base.text, significant[position - 2])
+                        .map_or(false, |variable| variable.type_name.starts_with("vec"));
+            }
+            shader.variable_at(token.text, index)
+                .map_or(false, |variable| !variable.array && is_float_type(&variable.type_name))
+        }
+        _ => false,
+    }
+}
+
+/// Whether the operand starting at `position` is known to be floating point.
+fn operand_after_is_float(shader: &Shader, significant: &[usize], position: usize) -> bool {
+    let index = match significant.get(position) {
+        Some(&index) => index,
+        None => return false,
+    };
+    let token = &shader.tokens[index];
+    let next = significant.get(position + 1).map_or("", |&next| shader.tokens[next].text);
+
+    match token.kind {
+        TokenKind::FloatLiteral => true,
+        TokenKind::Identifier if next == "(" || next == "[" => false,
+        TokenKind::Identifier => shader.variable_at(token.text, index).map_or(false, |variable| {
+            if next == "." {
+                variable.type_name.starts_with("vec")
+            } else {
+                !variable.array && is_float_type(&variable.type_name)
+            }
+        }),
+        _ => false,
+    }
+}
+
+/// Name of the function called by the argument list containing `position`.
+fn enclosing_call<'a>(shader: &Shader<'a>, significant: &[usize], position: usize) -> Option<&'a str> {
+    let mut depth = 0;
+    for scan in (0..=position).rev() {
+        match shader.tokens[significant[scan]].text {
+            ")" | "]" => depth += 1,
+            "[" => depth -= 1,
+            "(" if depth == 0 => {
+                let name = &shader.tokens[*significant.get(scan.checked_sub(1)?)?];
+                return if name.kind == TokenKind::Identifier { Some(name.text) } else { None };
+            }
+            "(" => depth -= 1,
+            ";" | "{" | "}" => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glsl::{Preprocessor, SnippetLibrary};
+
+    const VERTEX: &str = "\
+attribute vec4 aPosition;
+varying vec2 vUv;
+void main() {
+    vUv = aPosition.xy;
+    gl_Position = aPosition;
+}";
+
+    fn analyze_pair(vertex: &str, fragment: &str) -> Vec<Diagnostic> {
+        let library = SnippetLibrary::new();
+        let preprocessor = Preprocessor::new(&library);
+        analyze(&preprocessor.process("vertex", vertex).unwrap(), &preprocessor.process("fragment", fragment).unwrap())
+    }
+
+    /// Analyzes a fragment shader against `VERTEX`.
+    fn analyze_fragment(fragment: &str) -> Vec<Diagnostic> {
+        analyze_pair(VERTEX, fragment)
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<(Severity, u32, &str)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.location.as_ref().unwrap().line, diagnostic.message.as_str()))
+            .collect()
+    }
+
+    fn fragment(body: &str) -> String {
+        format!("precision mediump float;\nvarying vec2 vUv;\n{}", body)
+    }
+
+    #[test]
+    fn a_clean_pair_has_no_findings() {
+        let diagnostics = analyze_fragment(&fragment("void main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }"));
+
+        assert_eq!(diagnostics, vec![]);
+    }
+
+    #[test]
+    fn fragment_shaders_need_a_float_precision() {
+        let diagnostics = analyze_fragment("varying vec2 vUv;\nvoid main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }");
+
+        assert_eq!(
+            messages(&diagnostics),
+            vec![(Severity::Error, 1, "Fragment shaders have no default float precision, add `precision mediump float;`")]
+        );
+        // An int precision doesn't cover floats, vertex shaders have a default.
+        assert_eq!(analyze_fragment("precision mediump int;\nvarying vec2 vUv;\nvoid main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }").len(), 1);
+    }
+
+    #[test]
+    fn loops_need_constant_bounds_on_webgl1() {
+        let diagnostics = analyze_fragment(&fragment(
+            "uniform int count;\nvoid main() {\n    float sum = 0.0;\n    for (int i = 0; i < count; i++) { sum += 0.1; }\n    gl_FragColor = vec4(vUv, sum, 1.0);\n}",
+        ));
+
+        assert_eq!(
+            messages(&diagnostics),
+            vec![(Severity::Error, 6, "Loop condition `i < count` is not `i <op> constant`, WebGL1 only allows loops with constant bounds")]
+        );
+    }
+
+    #[test]
+    fn loops_may_use_constants_and_macros() {
+        let diagnostics = analyze_fragment(&fragment(
+            "#define STEPS 8\nconst int START = 1;\nvoid main() {\n    float sum = 0.0;\n    for (int i = START; i <= STEPS * 2; i += 2) { sum += 0.1; }\n    gl_FragColor = vec4(vUv, sum, 1.0);\n}",
+        ));
+
+        assert_eq!(diagnostics, vec![]);
+    }
+
+    #[test]
+    fn reports_every_part_of_a_malformed_loop() {
+        let diagnostics = analyze_fragment(&fragment(
+            "uniform float start;\nvoid main() {\n    float sum = 0.0;\n    for (float x = start; x < 1.0; x *= 2.0) { sum += x; }\n    int i = 0;\n    for (i = 0; i < 4; i++) { sum += 0.1; }\n    gl_FragColor = vec4(vUv, sum, 1.0);\n}",
+        ));
+
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                (Severity::Error, 6, "Loop index `x` must start at a constant expression on WebGL1"),
+                (Severity::Error, 6, "Loop index `x` must change by a constant step on WebGL1"),
+                (Severity::Error, 8, "WebGL1 requires the loop index to be declared and initialized in the `for` statement"),
+            ]
+        );
+    }
+
+    #[test]
+    fn es300_loops_are_unrestricted() {
+        let vertex = "#version 300 es\nin vec4 aPosition;\nout vec2 vUv;\nvoid main() { vUv = aPosition.xy; gl_Position = aPosition; }";
+        let fragment = "#version 300 es\nprecision mediump float;\nuniform int count;\nin vec2 vUv;\nout vec4 color;\nvoid main() {\n    float sum = 0.0;\n    for (int i = 0; i < count; i++) { sum += 0.1; }\n    color = vec4(vUv, sum, 1.0);\n}";
+
+        assert_eq!(analyze_pair(vertex, fragment), vec![]);
+    }
+
+    #[test]
+    fn integer_literals_in_float_expressions() {
+        let diagnostics = analyze_fragment(&fragment(
+            "void main() {\n    float a = 1;\n    vec2 b = vUv * 2;\n    float c = a - -3 + pow(a, 2);\n    float d = b.x / 4;\n    gl_FragColor = vec4(a, c, d, 1.0);\n}",
+        ));
+        let found: Vec<(u32, &str)> = messages(&diagnostics).into_iter().map(|(_, line, message)| (line, message)).collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (4, "Integer literal `1` used where a float is expected, write `1.0`"),
+                (5, "Integer literal `2` used where a float is expected, write `2.0`"),
+                (6, "Integer literal `3` used where a float is expected, write `3.0`"),
+                (6, "Integer literal `2` used where a float is expected, write `2.0`"),
+                (7, "Integer literal `4` used where a float is expected, write `4.0`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn integer_literals_in_integer_places() {
+        let diagnostics = analyze_fragment(&fragment(
+            "uniform vec2 points[4];\nvoid main() {\n    int i = 1 + 2;\n    vec2 p = points[2] + float(i);\n    gl_FragColor = vec4(vUv + p, 0.0, 1.0);\n}",
+        ));
+
+        assert_eq!(diagnostics, vec![]);
+    }
+
+    #[test]
+    fn abs_takes_integers_from_es300_on() {
+        let es100 = analyze_fragment(&fragment("void main() { float a = abs(2); gl_FragColor = vec4(vUv, a, 1.0); }"));
+        assert_eq!(es100.len(), 1);
+
+        let vertex = "#version 300 es\nin vec4 aPosition;\nout vec2 vUv;\nvoid main() { vUv = aPosition.xy; gl_Position = aPosition; }";
+        let fragment = "#version 300 es\nprecision mediump float;\nin vec2 vUv;\nout vec4 color;\nvoid main() { int a = abs(2); color = vec4(vUv, float(a), 1.0); }";
+        assert_eq!(analyze_pair(vertex, fragment), vec![]);
+    }
+
+    #[test]
+    fn unused_uniforms_are_reported_per_stage() {
+        let vertex = "uniform mat4 uUnused;\nattribute vec4 aPosition;\nvarying vec2 vUv;\nvoid main() { vUv = aPosition.xy; gl_Position = aPosition; }";
+        let diagnostics = analyze_pair(vertex, &fragment("uniform float uTime;\nvoid main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }"));
+
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                (Severity::Warning, 1, "Uniform `uUnused` is declared but never used, the driver will optimize it away"),
+                (Severity::Warning, 3, "Uniform `uTime` is declared but never used, the driver will optimize it away"),
+            ]
+        );
+        assert_eq!(diagnostics[0].location.as_ref().unwrap().file, "vertex");
+        assert_eq!(diagnostics[1].location.as_ref().unwrap().file, "fragment");
+    }
+
+    #[test]
+    fn unused_varyings_are_reported() {
+        let vertex = "attribute vec4 aPosition;\nvarying vec2 vUv;\nvarying vec3 vNormal;\nvoid main() { vUv = aPosition.xy; gl_Position = aPosition; }";
+        let diagnostics = analyze_pair(vertex, "precision mediump float;\nvarying vec2 vUv;\nvarying vec3 vNormal;\nvoid main() { gl_FragColor = vec4(1.0); }");
+
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                (Severity::Warning, 3, "Varying `vNormal` is declared but never used"),
+                (Severity::Warning, 2, "Varying `vUv` is declared but never used"),
+                (Severity::Warning, 3, "Varying `vNormal` is declared but never used"),
+            ]
+        );
+    }
+
+    #[test]
+    fn varyings_the_fragment_stage_never_declares() {
+        let diagnostics = analyze_pair(VERTEX, "precision mediump float;\nvoid main() { gl_FragColor = vec4(1.0); }");
+
+        assert_eq!(
+            messages(&diagnostics),
+            vec![(Severity::Warning, 2, "Varying `vUv` is written by the vertex stage but the fragment stage never declares it")]
+        );
+    }
+
+    #[test]
+    fn varyings_only_in_the_fragment_stage() {
+        let read = analyze_fragment(&fragment("varying vec4 vColor;\nvoid main() { gl_FragColor = vColor + vec4(vUv, 0.0, 0.0); }"));
+        assert_eq!(
+            messages(&read),
+            vec![(Severity::Error, 3, "Varying `vColor` is declared in the fragment stage but not in the vertex stage")]
+        );
+
+        // Unread, the driver links it anyway.
+        let unread = analyze_fragment(&fragment("varying vec4 vColor;\nvoid main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }"));
+        assert_eq!(
+            messages(&unread),
+            vec![
+                (Severity::Warning, 3, "Varying `vColor` is declared but never used"),
+                (Severity::Warning, 3, "Varying `vColor` is declared in the fragment stage but not in the vertex stage"),
+            ]
+        );
+    }
+
+    #[test]
+    fn findings_point_into_included_snippets() {
+        let mut library = SnippetLibrary::new();
+        library.insert("unused.glsl", "uniform float uUnused;");
+        let preprocessor = Preprocessor::new(&library);
+        let vertex = preprocessor.process("vertex", VERTEX).unwrap();
+        let fragment = preprocessor.process("fragment", &fragment("#include \"unused.glsl\"\nvoid main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }")).unwrap();
+
+        let diagnostics = analyze(&vertex, &fragment);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].location.as_ref().unwrap().to_string(), "unused.glsl:1");
+    }
+}
@@ -0,0 +1,189 @@
+//! Built-in vocabulary of GLSL ES 1.00 and 3.00, shared by the editor
+//! completions and the static analyzer.
+
+pub const TYPES: &[&str] = &[
+    "void", "bool", "int", "uint", "float",
+    "vec2", "vec3", "vec4", "bvec2", "bvec3", "bvec4",
+    "ivec2", "ivec3", "ivec4", "uvec2", "uvec3", "uvec4",
+    "mat2", "mat3", "mat4", "mat2x2", "mat2x3", "mat2x4",
+    "mat3x2", "mat3x3", "mat3x4", "mat4x2", "mat4x3", "mat4x4",
+    "sampler2D", "samplerCube", "sampler3D", "sampler2DArray", "sampler2DShadow",
+    "samplerCubeShadow", "sampler2DArrayShadow", "isampler2D", "isampler3D",
+    "isamplerCube", "isampler2DArray", "usampler2D", "usampler3D", "usamplerCube",
+    "usampler2DArray",
+];
+
+pub const QUALIFIERS: &[&str] = &[
+    "attribute", "uniform", "varying", "const", "in", "out", "inout", "centroid",
+    "flat", "smooth", "invariant", "layout", "highp", "mediump", "lowp", "precision",
+];
+
+pub const KEYWORDS: &[&str] = &[
+    "break", "continue", "do", "for", "while", "if", "else", "switch", "case",
+    "default", "return", "discard", "struct", "true", "false",
+];
+
+pub const VARIABLES: &[(&str, &str)] = &[
+    ("vec4", "gl_Position"),
+    ("float", "gl_PointSize"),
+    ("vec4", "gl_FragCoord"),
+    ("bool", "gl_FrontFacing"),
+    ("vec4", "gl_FragColor"),
+    ("vec4", "gl_FragData"),
+    ("vec2", "gl_PointCoord"),
+    ("float", "gl_FragDepth"),
+    ("int", "gl_VertexID"),
+    ("int", "gl_InstanceID"),
+];
+
+/// Uniforms the app provides to every shader, Shadertoy style.
+pub const INJECTED_UNIFORMS: &[(&str, &str, &str)] = &[
+    ("vec3", "iResolution", "Viewport resolution in pixels, z is the pixel aspect ratio"),
+    ("float", "iTime", "Playback time in seconds"),
+    ("float", "iTimeDelta", "Render time of the last frame in seconds"),
+    ("int", "iFrame", "Frame number since the shader was compiled"),
+    ("vec4", "iMouse", "Mouse pixel coordinates, xy while dragging, zw click, z < 0 once released, w < 0 after the click's frame"),
+    ("sampler2D", "iKeyboard", "Key states by key code, 256x3: y 0 down, 1 pressed this frame, 2 toggled"),
+    ("sampler2D", "iAudio", "Audio, 512x2: y 0 spectrum from 0 to 1, y 1 waveform mapped onto 0 to 1"),
+    ("float", "iAudioLevel", "Root mean square of the latest audio samples"),
+    ("vec3", "iAudioBands", "Average spectrum of the bass, mids and highs"),
+    ("sampler2D", "iChannel0", "Channel 0: an image, or a cubemap or volume when declared as samplerCube or sampler3D"),
+    ("sampler2D", "iChannel1", "Channel 1: an image, or a cubemap or volume when declared as samplerCube or sampler3D"),
+    ("sampler2D", "iChannel2", "Channel 2: an image, or a cubemap or volume when declared as samplerCube or sampler3D"),
+    ("sampler2D", "iChannel3", "Channel 3: an image, or a cubemap or volume when declared as samplerCube or sampler3D"),
+    ("vec4", "iDate", "Year, month, day and seconds since midnight"),
+];
+
+/// Built-in functions and every overload's signature. `genType` stands for
+/// `float`, `vec2`, `vec3` or `vec4`.
+pub const FUNCTIONS: &[(&str, &[&str])] = &[
+    // Angle and trigonometry
+    ("radians", &["genType radians(genType degrees)"]),
+    ("degrees", &["genType degrees(genType radians)"]),
+    ("sin", &["genType sin(genType angle)"]),
+    ("cos", &["genType cos(genType angle)"]),
+    ("tan", &["genType tan(genType angle)"]),
+    ("asin", &["genType asin(genType x)"]),
+    ("acos", &["genType acos(genType x)"]),
+    ("atan", &["genType atan(genType y, genType x)", "genType atan(genType y_over_x)"]),
+    ("sinh", &["genType sinh(genType x)"]),
+    ("cosh", &["genType cosh(genType x)"]),
+    ("tanh", &["genType tanh(genType x)"]),
+    ("asinh", &["genType asinh(genType x)"]),
+    ("acosh", &["genType acosh(genType x)"]),
+    ("atanh", &["genType atanh(genType x)"]),
+    // Exponential
+    ("pow", &["genType pow(genType x, genType y)"]),
+    ("exp", &["genType exp(genType x)"]),
+    ("log", &["genType log(genType x)"]),
+    ("exp2", &["genType exp2(genType x)"]),
+    ("log2", &["genType log2(genType x)"]),
+    ("sqrt", &["genType sqrt(genType x)"]),
+    ("inversesqrt", &["genType inversesqrt(genType x)"]),
+    // Common
+    ("abs", &["genType abs(genType x)"]),
+    ("sign", &["genType sign(genType x)"]),
+    ("floor", &["genType floor(genType x)"]),
+    ("trunc", &["genType trunc(genType x)"]),
+    ("round", &["genType round(genType x)"]),
+    ("roundEven", &["genType roundEven(genType x)"]),
+    ("ceil", &["genType ceil(genType x)"]),
+    ("fract", &["genType fract(genType x)"]),
+    ("mod", &["genType mod(genType x, float y)", "genType mod(genType x, genType y)"]),
+    ("modf", &["genType modf(genType x, out genType i)"]),
+    ("min", &["genType min(genType x, genType y)", "genType min(genType x, float y)"]),
+    ("max", &["genType max(genType x, genType y)", "genType max(genType x, float y)"]),
+    ("clamp", &[
+        "genType clamp(genType x, genType minVal, genType maxVal)",
+        "genType clamp(genType x, float minVal, float maxVal)",
+    ]),
+    ("mix", &[
+        "genType mix(genType x, genType y, genType a)",
+        "genType mix(genType x, genType y, float a)",
+        "genType mix(genType x, genType y, genBType a)",
+    ]),
+    ("step", &["genType step(genType edge, genType x)", "genType step(float edge, genType x)"]),
+    ("smoothstep", &[
+        "genType smoothstep(genType edge0, genType edge1, genType x)",
+        "genType smoothstep(float edge0, float edge1, genType x)",
+    ]),
+    ("isnan", &["genBType isnan(genType x)"]),
+    ("isinf", &["genBType isinf(genType x)"]),
+    ("floatBitsToInt", &["genIType floatBitsToInt(genType value)"]),
+    ("floatBitsToUint", &["genUType floatBitsToUint(genType value)"]),
+    ("intBitsToFloat", &["genType intBitsToFloat(genIType value)"]),
+    ("uintBitsToFloat", &["genType uintBitsToFloat(genUType value)"]),
+    // Packing
+    ("packSnorm2x16", &["uint packSnorm2x16(vec2 v)"]),
+    ("unpackSnorm2x16", &["vec2 unpackSnorm2x16(uint p)"]),
+    ("packUnorm2x16", &["uint packUnorm2x16(vec2 v)"]),
+    ("unpackUnorm2x16", &["vec2 unpackUnorm2x16(uint p)"]),
+    ("packHalf2x16", &["uint packHalf2x16(vec2 v)"]),
+    ("unpackHalf2x16", &["vec2 unpackHalf2x16(uint v)"]),
+    // Geometric
+    ("length", &["float length(genType x)"]),
+    ("distance", &["float distance(genType p0, genType p1)"]),
+    ("dot", &["float dot(genType x, genType y)"]),
+    ("cross", &["vec3 cross(vec3 x, vec3 y)"]),
+    ("normalize", &["genType normalize(genType x)"]),
+    ("faceforward", &["genType faceforward(genType N, genType I, genType Nref)"]),
+    ("reflect", &["genType reflect(genType I, genType N)"]),
+    ("refract", &["genType refract(genType I, genType N, float eta)"]),
+    // Matrix
+    ("matrixCompMult", &["mat matrixCompMult(mat x, mat y)"]),
+    ("outerProduct", &["mat outerProduct(vec c, vec r)"]),
+    ("transpose", &["mat transpose(mat m)"]),
+    ("determinant", &["float determinant(mat m)"]),
+    ("inverse", &["mat inverse(mat m)"]),
+    // Vector relational
+    ("lessThan", &["bvec lessThan(vec x, vec y)"]),
+    ("lessThanEqual", &["bvec lessThanEqual(vec x, vec y)"]),
+    ("greaterThan", &["bvec greaterThan(vec x, vec y)"]),
+    ("greaterThanEqual", &["bvec greaterThanEqual(vec x, vec y)"]),
+    ("equal", &["bvec equal(vec x, vec y)"]),
+    ("notEqual", &["bvec notEqual(vec x, vec y)"]),
+    ("any", &["bool any(bvec x)"]),
+    ("all", &["bool all(bvec x)"]),
+    ("not", &["bvec not(bvec x)"]),
+    // Texture lookup, GLSL ES 1.00
+    ("texture2D", &[
+        "vec4 texture2D(sampler2D sampler, vec2 coord)",
+        "vec4 texture2D(sampler2D sampler, vec2 coord, float bias)",
+    ]),
+    ("texture2DProj", &["vec4 texture2DProj(sampler2D sampler, vec3 coord)", "vec4 texture2DProj(sampler2D sampler, vec4 coord)"]),
+    ("texture2DLod", &["vec4 texture2DLod(sampler2D sampler, vec2 coord, float lod)"]),
+    ("textureCube", &[
+        "vec4 textureCube(samplerCube sampler, vec3 coord)",
+        "vec4 textureCube(samplerCube sampler, vec3 coord, float bias)",
+    ]),
+    ("textureCubeLod", &["vec4 textureCubeLod(samplerCube sampler, vec3 coord, float lod)"]),
+    // Texture lookup, GLSL ES 3.00
+    ("texture", &[
+        "gvec4 texture(gsampler2D sampler, vec2 P)",
+        "gvec4 texture(gsampler3D sampler, vec3 P)",
+        "gvec4 texture(gsamplerCube sampler, vec3 P)",
+        "gvec4 texture(gsampler2DArray sampler, vec3 P)",
+    ]),
+    ("textureProj", &["gvec4 textureProj(gsampler2D sampler, vec3 P)"]),
+    ("textureLod", &["gvec4 textureLod(gsampler2D sampler, vec2 P, float lod)"]),
+    ("textureOffset", &["gvec4 textureOffset(gsampler2D sampler, vec2 P, ivec2 offset)"]),
+    ("texelFetch", &["gvec4 texelFetch(gsampler2D sampler, ivec2 P, int lod)"]),
+    ("textureGrad", &["gvec4 textureGrad(gsampler2D sampler, vec2 P, vec2 dPdx, vec2 dPdy)"]),
+    ("textureSize", &["ivec2 textureSize(gsampler2D sampler, int lod)"]),
+    // Fragment processing
+    ("dFdx", &["genType dFdx(genType p)"]),
+    ("dFdy", &["genType dFdy(genType p)"]),
+    ("fwidth", &["genType fwidth(genType p)"]),
+];
+
+pub fn is_type(word: &str) -> bool {
+    TYPES.contains(&word)
+}
+
+pub fn is_function(word: &str) -> bool {
+    FUNCTIONS.iter().any(|(name, _)| *name == word)
+}
+
+pub fn is_injected_uniform(word: &str) -> bool {
+    INJECTED_UNIFORMS.iter().any(|(_, name, _)| *name == word)
+}
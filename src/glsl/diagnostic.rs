@@ -0,0 +1,95 @@
+use std::fmt;
+
+use super::SourceLocation;
+
+/// The two programmable stages of a WebGL program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Vertex,
+    Fragment,
+}
+
+impl Stage {
+    /// The file name sources of this stage are reported under.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Vertex => "vertex",
+            Stage::Fragment => "fragment",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a shader, either by the driver or by our own analysis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Where the problem is, `None` when the driver did not say.
+    pub location: Option<SourceLocation>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(location: Option<SourceLocation>, message: String) -> Self {
+        Self { severity: Severity::Error, location, message }
+    }
+
+    pub fn warning(location: Option<SourceLocation>, message: String) -> Self {
+        Self { severity: Severity::Warning, location, message }
+    }
+
+    /// Parses a driver info log, already remapped by `SourceMap::remap_log`,
+    /// into one diagnostic per line containing `ERROR:` or `WARNING:`. Logs
+    /// in any other format become a single error holding the whole log.
+    pub fn from_info_log(log: &str) -> Vec<Self> {
+        let diagnostics: Vec<Self> = log.lines()
+            .filter_map(|line| {
+                let (severity, rest) = if let Some(start) = line.find("ERROR:") {
+                    (Severity::Error, &line[start + "ERROR:".len()..])
+                } else if let Some(start) = line.find("WARNING:") {
+                    (Severity::Warning, &line[start + "WARNING:".len()..])
+                } else {
+                    return None;
+                };
+
+                let mut parts = rest.trim().splitn(3, ':');
+                let location = match (parts.next(), parts.next().and_then(|line| line.trim().parse().ok())) {
+                    (Some(file), Some(line)) => Some(SourceLocation { file: file.trim().to_string(), line }),
+                    _ => None,
+                };
+
+                let message = match (&location, parts.next()) {
+                    (Some(_), Some(message)) => message.trim().to_string(),
+                    _ => rest.trim().to_string(),
+                };
+
+                Some(Self { severity, location, message })
+            })
+            .collect();
+
+        if diagnostics.is_empty() && !log.trim().is_empty() {
+            return vec![Self::error(None, log.trim().to_string())];
+        }
+
+        diagnostics
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        match &self.location {
+            Some(location) => write!(f, "{}: {}:{}: {}", severity, location.file, location.line, self.message),
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use super::lexer::{tokenize, Token, TokenKind};
+
+const INDENT: &str = "    ";
+
+/// Keywords whose parenthesized header may be followed by a single statement
+/// instead of a block.
+const CONTROL: &[&str] = &["if", "for", "while", "switch"];
+
+/// Qualifiers that open a block declaration such as `uniform Lights { ... } lights;`.
+const BLOCK_QUALIFIERS: &[&str] = &["struct", "uniform", "in", "out"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Brace {
+    Block,
+    /// A struct or interface block body, closed by `};` or `} name;`.
+    Declaration,
+    /// A switch body, whose statements are indented past the case labels.
+    Switch,
+}
+
+/// Pretty-prints a shader: four space indentation, braces on the line of the
+/// statement that opens them, spaces around binary operators and after commas,
+/// and at most one blank line in a row.
+///
+/// Comments are kept, trailing comments stay on the line they trail and
+/// preprocessor directives are left as they are, flush with the left margin.
+pub fn format(source: &str) -> String {
+    let tokens = tokenize(source);
+    let mut formatter = Formatter {
+        tokens: &tokens,
+        output: String::new(),
+        indent: 0,
+        continuation: 0,
+        line_start: true,
+        pending_newline: false,
+        newlines: 0,
+        previous: None,
+        before_previous: None,
+        parens: Vec::new(),
+        braces: Vec::new(),
+        header_closed: None,
+        case_label: false,
+    };
+
+    for index in 0..tokens.len() {
+        formatter.token(index);
+    }
+
+    let mut output = formatter.output.trim_end().to_string();
+    output.push('\n');
+    output
+}
+
+struct Formatter<'t, 'a> {
+    tokens: &'t [Token<'a>],
+    output: String,
+    indent: usize,
+    /// Extra indentation for the single statement body of a control
+    /// statement written without braces.
+    continuation: usize,
+    line_start: bool,
+    /// Set after `;`, `{` and `}`, the line is ended once the next token shows
+    /// whether a trailing comment should stay on it.
+    pending_newline: bool,
+    /// Newlines in the source since the previous token.
+    newlines: usize,
+    previous: Option<&'t Token<'a>>,
+    before_previous: Option<&'t Token<'a>>,
+    /// The control keyword each open parenthesis is the header of, if any.
+    parens: Vec<Option<&'a str>>,
+    braces: Vec<Brace>,
+    /// The keyword of a control statement whose header was just closed.
+    header_closed: Option<&'a str>,
+    case_label: bool,
+}
+
+impl<'t, 'a> Formatter<'t, 'a> {
+    fn previous_is(&self, text: &str) -> bool {
+        self.previous.map_or(false, |token| token.is(text))
+    }
+
+    fn next_significant(&self, index: usize) -> Option<&'t Token<'a>> {
+        self.tokens[index + 1..].iter().find(|token| !token.is_trivia())
+    }
+
+    fn newline(&mut self) {
+        let trimmed = self.output.trim_end_matches(' ').len();
+        self.output.truncate(trimmed);
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        self.line_start = true;
+        self.pending_newline = false;
+    }
+
+    /// Ends a pending line, keeping a single blank line where the source
+    /// had one or more.
+    fn flush(&mut self, closing: bool) {
+        if self.pending_newline {
+            self.newline();
+        }
+
+        let after_open = self.previous_is("{");
+        if self.line_start && self.newlines > 1 && !after_open && !closing && !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        self.newlines = 0;
+    }
+
+    fn write(&mut self, text: &str, space: bool) {
+        if self.line_start {
+            for _ in 0..self.indent + self.continuation {
+                self.output.push_str(INDENT);
+            }
+            self.line_start = false;
+        } else if space {
+            self.output.push(' ');
+        }
+        self.output.push_str(text);
+    }
+
+    fn token(&mut self, index: usize) {
+        let token = &self.tokens[index];
+
+        match token.kind {
+            TokenKind::Whitespace => {
+                self.newlines += token.text.matches('\n').count();
+            }
+            TokenKind::LineComment => {
+                if self.newlines == 0 && !self.line_start {
+                    self.write(token.text.trim_end(), true);
+                } else {
+                    self.flush(false);
+                    if !self.line_start {
+                        self.newline();
+                    }
+                    self.write(token.text.trim_end(), true);
+                }
+                self.newline();
+                self.newlines = 0;
+            }
+            TokenKind::BlockComment => {
+                if self.newlines == 0 && !self.line_start {
+                    self.write(token.text, true);
+                } else {
+                    let own_line = self.newlines > 0 || self.output.is_empty();
+                    self.flush(false);
+                    if own_line && !self.line_start {
+                        self.newline();
+                    }
+                    self.write(token.text, !self.line_start);
+                    if own_line {
+                        self.pending_newline = true;
+                    }
+                }
+                self.newlines = 0;
+            }
+            TokenKind::Directive => {
+                self.flush(false);
+                if !self.line_start {
+                    self.newline();
+                }
+                self.output.push_str(token.text.trim());
+                self.line_start = false;
+                self.newline();
+            }
+            _ => self.significant(index),
+        }
+    }
+
+    fn significant(&mut self, index: usize) {
+        let token = &self.tokens[index];
+        let text = token.text;
+        let closing = text == "}";
+
+        // A control statement without braces, keep its body on the same line
+        // unless the source put it on its own line.
+        let header = self.header_closed.take();
+        if header.is_some() {
+            if text != "{" && text != ";" && self.newlines > 0 {
+                self.continuation += 1;
+                self.pending_newline = true;
+            }
+        }
+
+        let brace = if closing { self.braces.pop().unwrap_or(Brace::Block) } else { Brace::Block };
+        if closing {
+            let depth = if brace == Brace::Switch { 2 } else { 1 };
+            self.indent = self.indent.saturating_sub(depth);
+            self.continuation = 0;
+            if !self.line_start {
+                self.pending_newline = true;
+            }
+        }
+
+        self.flush(closing);
+        let space = self.space_before(token);
+
+        match text {
+            "(" => {
+                let control = self.previous
+                    .map(|previous| previous.text)
+                    .filter(|previous| CONTROL.contains(previous));
+                self.parens.push(control);
+                self.write(text, space);
+            }
+            ")" => {
+                self.write(text, space);
+                self.header_closed = self.parens.pop().unwrap_or(None);
+            }
+            "{" => {
+                let declaration = self.previous_is("struct")
+                    || self.before_previous.map_or(false, |token| BLOCK_QUALIFIERS.contains(&token.text) && self.braces.is_empty());
+                let brace = if header == Some("switch") {
+                    Brace::Switch
+                } else if declaration {
+                    Brace::Declaration
+                } else {
+                    Brace::Block
+                };
+                self.braces.push(brace);
+                self.write(text, space);
+                self.indent += if brace == Brace::Switch { 2 } else { 1 };
+                self.continuation = 0;
+                self.pending_newline = true;
+            }
+            "}" => {
+                self.write(text, space);
+                let next = self.next_significant(index).map_or("", |token| token.text);
+                if brace != Brace::Declaration && next != "else" && next != ";" {
+                    self.pending_newline = true;
+                }
+            }
+            ";" => {
+                self.write(text, space);
+                if self.parens.is_empty() {
+                    self.continuation = 0;
+                    self.pending_newline = true;
+                }
+            }
+            ":" if self.case_label => {
+                self.write(text, false);
+                self.case_label = false;
+                self.pending_newline = true;
+            }
+            "else" => {
+                self.write(text, space);
+                let next = self.next_significant(index).map_or("", |token| token.text);
+                if next != "{" && next != "if" {
+                    self.header_closed = Some("else");
+                }
+            }
+            "case" | "default" => {
+                // Labels sit one level out from the statements they label.
+                self.case_label = true;
+                self.indent = self.indent.saturating_sub(1);
+                self.write(text, space);
+                self.indent += 1;
+            }
+            _ => self.write(text, space),
+        }
+
+        self.before_previous = self.previous;
+        self.previous = Some(token);
+    }
+
+    /// Whether a significant token is separated from the previous one by a space.
+    fn space_before(&self, token: &Token) -> bool {
+        let previous = match self.previous {
+            Some(previous) => previous,
+            None => return false,
+        };
+        let text = token.text;
+
+        if [")", "]", ";", ",", "."].contains(&text) || previous.is(".") || previous.is("(") || previous.is("[") {
+            return false;
+        }
+
+        if ["-", "+", "!", "~"].contains(&previous.text) && previous.kind == TokenKind::Symbol && self.is_unary(self.before_previous) {
+            return false;
+        }
+
+        if text == "(" || text == "[" {
+            // Calls, constructors and indexing hug the name, keywords don't.
+            let operand = previous.kind == TokenKind::Identifier || previous.is(")") || previous.is("]");
+            return !operand || CONTROL.contains(&previous.text) || previous.is("return");
+        }
+
+        if text == "++" || text == "--" {
+            let postfix = previous.kind == TokenKind::Identifier || previous.is(")") || previous.is("]");
+            return !postfix;
+        }
+
+        if previous.is("++") || previous.is("--") {
+            // Prefix increments hug their operand.
+            return !self.is_unary(self.before_previous) || text == ")";
+        }
+
+        true
+    }
+
+    /// Whether an operator following this token is a prefix operator.
+    fn is_unary(&self, before: Option<&Token>) -> bool {
+        match before {
+            None => true,
+            Some(token) => match token.kind {
+                TokenKind::Symbol => !token.is(")") && !token.is("]"),
+                TokenKind::Identifier => token.is("return") || token.is("case"),
+                _ => false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn puts_braces_on_the_opening_line_and_indents_blocks() {
+        let source = "void main()\n{\nfloat x=1.0;\nif(x>0.0)\n{\nx=-x;\n}\nelse{x+=f(x,2.0);}\n}";
+
+        assert_eq!(
+            format(source),
+            "void main() {\n    float x = 1.0;\n    if (x > 0.0) {\n        x = -x;\n    } else {\n        x += f(x, 2.0);\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_single_statement_bodies_where_the_source_put_them() {
+        let source = "void main() {\nif (a) b();\nfor (int i = 0; i < 4; i++)\nc(i);\n}";
+
+        assert_eq!(
+            format(source),
+            "void main() {\n    if (a) b();\n    for (int i = 0; i < 4; i++)\n        c(i);\n}\n"
+        );
+    }
+
+    #[test]
+    fn closes_declaration_blocks_with_their_name() {
+        let source = "struct Light {\nvec3 color;\n};\nuniform Lights {\nLight lights[4];\n} uLights;";
+
+        assert_eq!(
+            format(source),
+            "struct Light {\n    vec3 color;\n};\nuniform Lights {\n    Light lights[4];\n} uLights;\n"
+        );
+    }
+
+    #[test]
+    fn indents_case_labels_inside_switch() {
+        let source = "void main() {\nswitch (mode) {\ncase 0:\ncolor = a;\nbreak;\ndefault:\ncolor = -b;\n}\n}";
+
+        assert_eq!(
+            format(source),
+            "void main() {\n    switch (mode) {\n        case 0:\n            color = a;\n            break;\n        default:\n            color = -b;\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_comments_and_directives() {
+        let source = "#define SCALE 2.0\n// Scales the input.\nfloat scale(float x) { /* inline */ return x*SCALE; // trailing\n}\n\n\n\n/* Entry point. */\nvoid main() {}";
+
+        assert_eq!(
+            format(source),
+            "#define SCALE 2.0\n// Scales the input.\nfloat scale(float x) { /* inline */\n    return x * SCALE; // trailing\n}\n\n/* Entry point. */\nvoid main() {\n}\n"
+        );
+    }
+
+    #[test]
+    fn is_idempotent() {
+        let sources = [
+            "void main()\n{\nfloat x=1.0;\nif(x>0.0)\n{\nx=-x;\n}\nelse{x+=f(x,2.0);}\n}",
+            "void main() {\nswitch (mode) {\ncase 0:\ncolor = a;\nbreak;\ndefault:\ncolor = -b;\n}\n}",
+            "#define SCALE 2.0\n// Scales the input.\nfloat scale(float x) { /* inline */ return x*SCALE; // trailing\n}\n\n\n\nvoid main() {}",
+            "struct Light {\nvec3 color;\n};\nvoid main() {\nif (a) b();\nfor (int i = 0; i < 4; i++)\nc(i);\n}",
+        ];
+
+        for source in &sources {
+            let formatted = format(source);
+            assert_eq!(format(&formatted), formatted);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use super::analyzer::varyings;
+use super::diagnostic::{Diagnostic, Stage};
+use super::parser::{Shader, Variable};
+use super::{Preprocessed, SourceLocation};
+
+/// Compares the varyings declared by both stages before linking, reporting
+/// type mismatches as errors and precision mismatches as warnings, with the
+/// location of both declarations. GLSL ES lets precisions differ, but the
+/// fragment stage silently loses range when it asks for less.
+pub fn validate(vertex: &Preprocessed, fragment: &Preprocessed) -> Vec<Diagnostic> {
+    let vertex_shader = Shader::parse(&vertex.source);
+    let fragment_shader = Shader::parse(&fragment.source);
+    let mut diagnostics = Vec::new();
+
+    for input in varyings(&fragment_shader, Stage::Fragment) {
+        let output = match varyings(&vertex_shader, Stage::Vertex).into_iter().find(|output| output.name == input.name) {
+            Some(output) => output,
+            None => continue,
+        };
+
+        let output_location = locate(vertex, output);
+        let input_location = locate(fragment, input);
+
+        if output.type_name != input.type_name || output.array != input.array {
+            diagnostics.push(Diagnostic::error(
+                Some(input_location.clone()),
+                format!(
+                    "Varying `{}` is declared as `{}` in the vertex stage ({}) but as `{}` in the fragment stage ({})",
+                    input.name,
+                    describe_type(output),
+                    output_location,
+                    describe_type(input),
+                    input_location
+                ),
+            ));
+            continue;
+        }
+
+        // Stage defaults differ by design, highp for vertex and usually mediump
+        // for fragment, so only explicitly qualified varyings are compared.
+        if output.precision.is_none() && input.precision.is_none() {
+            continue;
+        }
+
+        let output_precision = precision(&vertex_shader, Stage::Vertex, output);
+        let input_precision = precision(&fragment_shader, Stage::Fragment, input);
+
+        if output_precision != input_precision {
+            diagnostics.push(Diagnostic::warning(
+                Some(input_location.clone()),
+                format!(
+                    "Varying `{}` is `{}` in the vertex stage ({}) but `{}` in the fragment stage ({}), results differ between GPUs",
+                    input.name,
+                    output_precision.unwrap_or("no precision"),
+                    output_location,
+                    input_precision.unwrap_or("no precision"),
+                    input_location
+                ),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Finds varyings the fragment stage reads that the vertex stage declares but
+/// never assigns. Such programs link, but the values read are undefined.
+pub fn unwritten_varyings(vertex: &Preprocessed, fragment: &Preprocessed) -> Vec<Diagnostic> {
+    let vertex_shader = Shader::parse(&vertex.source);
+    let fragment_shader = Shader::parse(&fragment.source);
+
+    varyings(&fragment_shader, Stage::Fragment)
+        .into_iter()
+        .filter(|input| !fragment_shader.references(&input.name).is_empty())
+        .filter_map(|input| {
+            let output = varyings(&vertex_shader, Stage::Vertex).into_iter().find(|output| output.name == input.name)?;
+            let written = vertex_shader
+                .references(&output.name)
+                .into_iter()
+                .any(|token| vertex_shader.is_write(token));
+
+            if written {
+                return None;
+            }
+
+            Some(Diagnostic::warning(
+                Some(locate(fragment, input)),
+                format!(
+                    "Varying `{}` is read by the fragment stage but never written by the vertex stage ({})",
+                    input.name,
+                    locate(vertex, output)
+                ),
+            ))
+        })
+        .collect()
+}
+
+fn locate(source: &Preprocessed, variable: &Variable) -> SourceLocation {
+    source.source_map
+        .lookup(variable.line)
+        .cloned()
+        .unwrap_or(SourceLocation { file: String::new(), line: variable.line })
+}
+
+fn describe_type(variable: &Variable) -> String {
+    if variable.array {
+        format!("{}[]", variable.type_name)
+    } else {
+        variable.type_name.clone()
+    }
+}
+
+/// The precision a varying ends up with: its own qualifier, else the stage's
+/// default precision for its base type. Vertex shaders default to `highp`.
+fn precision<'s>(shader: &'s Shader, stage: Stage, variable: &'s Variable) -> Option<&'s str> {
+    if let Some(precision) = &variable.precision {
+        return Some(precision);
+    }
+
+    let base = if variable.type_name.starts_with('i') || variable.type_name.starts_with('u') { "int" } else { "float" };
+    let default = shader.precisions
+        .iter()
+        .rev()
+        .find(|statement| statement.type_name == base)
+        .map(|statement| statement.precision.as_str());
+
+    match (default, stage) {
+        (Some(precision), _) => Some(precision),
+        (None, Stage::Vertex) => Some("highp"),
+        (None, Stage::Fragment) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glsl::{Preprocessor, Severity, SnippetLibrary};
+
+    /// Preprocesses both stages and hands them to a check.
+    fn check(check: fn(&Preprocessed, &Preprocessed) -> Vec<Diagnostic>, vertex: &str, fragment: &str) -> Vec<(Severity, u32, String)> {
+        let library = SnippetLibrary::new();
+        let preprocessor = Preprocessor::new(&library);
+        let vertex = preprocessor.process("vertex", vertex).unwrap();
+        let fragment = preprocessor.process("fragment", fragment).unwrap();
+
+        check(&vertex, &fragment)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.location.unwrap().line, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn matching_varyings_pass() {
+        let vertex = "varying vec2 vUv;\nvoid main() { vUv = vec2(0.0); }";
+        let fragment = "precision mediump float;\nvarying vec2 vUv;\nvoid main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }";
+
+        assert_eq!(check(validate, vertex, fragment), vec![]);
+        assert_eq!(check(unwritten_varyings, vertex, fragment), vec![]);
+    }
+
+    #[test]
+    fn a_type_mismatch_is_an_error() {
+        let vertex = "varying vec3 vColor;\nvoid main() { vColor = vec3(1.0); }";
+        let fragment = "precision mediump float;\nvarying vec4 vColor;\nvoid main() { gl_FragColor = vColor; }";
+
+        assert_eq!(check(validate, vertex, fragment), vec![(
+            Severity::Error,
+            2,
+            String::from("Varying `vColor` is declared as `vec3` in the vertex stage (vertex:1) but as `vec4` in the fragment stage (fragment:2)"),
+        )]);
+    }
+
+    #[test]
+    fn an_array_mismatch_is_an_error() {
+        let vertex = "varying float vWeights[2];\nvoid main() { vWeights[0] = 1.0; }";
+        let fragment = "precision mediump float;\nvarying float vWeights;\nvoid main() { gl_FragColor = vec4(vWeights); }";
+
+        let diagnostics = check(validate, vertex, fragment);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, Severity::Error);
+        assert!(diagnostics[0].2.contains("`float[]` in the vertex stage"));
+    }
+
+    #[test]
+    fn a_precision_mismatch_is_a_warning() {
+        let vertex = "varying highp vec2 vUv;\nvoid main() { vUv = vec2(0.0); }";
+        let fragment = "precision mediump float;\nvarying mediump vec2 vUv;\nvoid main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }";
+
+        assert_eq!(check(validate, vertex, fragment), vec![(
+            Severity::Warning,
+            2,
+            String::from("Varying `vUv` is `highp` in the vertex stage (vertex:1) but `mediump` in the fragment stage (fragment:2), results differ between GPUs"),
+        )]);
+    }
+
+    #[test]
+    fn default_precisions_fill_in_for_a_qualified_side() {
+        // The vertex stage defaults to highp, which the fragment asks for.
+        let vertex = "varying vec2 vUv;\nvoid main() { vUv = vec2(0.0); }";
+        let fragment = "precision mediump float;\nvarying highp vec2 vUv;\nvoid main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }";
+
+        assert_eq!(check(validate, vertex, fragment), vec![]);
+    }
+
+    #[test]
+    fn unqualified_varyings_keep_their_stage_defaults() {
+        let vertex = "varying vec2 vUv;\nvoid main() { vUv = vec2(0.0); }";
+        let fragment = "precision mediump float;\nvarying vec2 vUv;\nvoid main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }";
+
+        assert_eq!(check(validate, vertex, fragment), vec![]);
+    }
+
+    #[test]
+    fn a_varying_read_but_never_written_is_a_warning() {
+        let vertex = "varying vec2 vUv;\nvoid main() { gl_Position = vec4(0.0); }";
+        let fragment = "precision mediump float;\nvarying vec2 vUv;\nvoid main() { gl_FragColor = vec4(vUv, 0.0, 1.0); }";
+
+        assert_eq!(check(unwritten_varyings, vertex, fragment), vec![(
+            Severity::Warning,
+            2,
+            String::from("Varying `vUv` is read by the fragment stage but never written by the vertex stage (vertex:1)"),
+        )]);
+    }
+
+    #[test]
+    fn a_varying_the_fragment_never_reads_needs_no_write() {
+        let vertex = "varying vec2 vUv;\nvoid main() { gl_Position = vec4(0.0); }";
+        let fragment = "precision mediump float;\nvarying vec2 vUv;\nvoid main() { gl_FragColor = vec4(1.0); }";
+
+        assert_eq!(check(unwritten_varyings, vertex, fragment), vec![]);
+    }
+}
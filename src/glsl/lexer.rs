@@ -0,0 +1,245 @@
+/// The kinds of token in a GLSL ES source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Identifier,
+    IntLiteral,
+    FloatLiteral,
+    /// Operators and punctuation, e.g. `+=`, `(` or `;`.
+    Symbol,
+    /// A whole preprocessor line, including any `\` continuations.
+    Directive,
+    LineComment,
+    BlockComment,
+    Whitespace,
+}
+
+/// A slice of the source along with its kind and 1-based position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub offset: usize,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl<'a> Token<'a> {
+    /// Whitespace and comments, which carry no meaning for the parser.
+    pub fn is_trivia(&self) -> bool {
+        match self.kind {
+            TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment => true,
+            _ => false,
+        }
+    }
+
+    pub fn is(&self, text: &str) -> bool {
+        self.text == text && self.kind != TokenKind::LineComment && self.kind != TokenKind::BlockComment
+    }
+}
+
+const SYMBOLS: &[&str] = &[
+    "<<=", ">>=",
+    "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "^^",
+    "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+];
+
+/// Splits a source into tokens. Nothing is dropped, concatenating the text of
+/// every token reproduces the source exactly, so tools can rewrite a shader
+/// token by token without disturbing comments or layout.
+pub fn tokenize<'a>(source: &'a str) -> Vec<Token<'a>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    let mut line = 1;
+    let mut column = 1;
+    let mut line_start = true;
+
+    while offset < bytes.len() {
+        let start = offset;
+        let c = bytes[offset];
+
+        let kind = if c.is_ascii_whitespace() {
+            while offset < bytes.len() && bytes[offset].is_ascii_whitespace() {
+                offset += 1;
+            }
+            TokenKind::Whitespace
+        } else if source[offset..].starts_with("//") {
+            while offset < bytes.len() && bytes[offset] != b'\n' {
+                offset += 1;
+            }
+            TokenKind::LineComment
+        } else if source[offset..].starts_with("/*") {
+            offset = source[offset + 2..]
+                .find("*/")
+                .map(|end| offset + 2 + end + 2)
+                .unwrap_or(bytes.len());
+            TokenKind::BlockComment
+        } else if c == b'#' && line_start {
+            while offset < bytes.len() && bytes[offset] != b'\n' {
+                if bytes[offset] == b'\\' && bytes.get(offset + 1) == Some(&b'\n') {
+                    offset += 1;
+                }
+                offset += 1;
+            }
+            TokenKind::Directive
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            while offset < bytes.len() && (bytes[offset].is_ascii_alphanumeric() || bytes[offset] == b'_') {
+                offset += 1;
+            }
+            TokenKind::Identifier
+        } else if c.is_ascii_digit() || (c == b'.' && bytes.get(offset + 1).map_or(false, u8::is_ascii_digit)) {
+            let (end, kind) = number(bytes, offset);
+            offset = end;
+            kind
+        } else {
+            let symbol = SYMBOLS.iter().find(|symbol| source[offset..].starts_with(*symbol));
+            offset += symbol.map_or(source[offset..].chars().next().unwrap().len_utf8(), |symbol| symbol.len());
+            TokenKind::Symbol
+        };
+
+        let text = &source[start..offset];
+        tokens.push(Token { kind, text, offset: start, line, column });
+
+        for c in text.chars() {
+            if c == '\n' {
+                line += 1;
+                column = 1;
+                line_start = true;
+            } else {
+                column += 1;
+                if !c.is_whitespace() {
+                    line_start = false;
+                }
+            }
+        }
+    }
+
+    tokens
+}
+
+fn number(bytes: &[u8], start: usize) -> (usize, TokenKind) {
+    let mut offset = start;
+    let digits = |offset: &mut usize, hex: bool| {
+        while *offset < bytes.len() && (bytes[*offset].is_ascii_digit() || (hex && bytes[*offset].is_ascii_hexdigit())) {
+            *offset += 1;
+        }
+    };
+
+    if bytes[offset] == b'0' && (bytes.get(offset + 1) == Some(&b'x') || bytes.get(offset + 1) == Some(&b'X')) {
+        offset += 2;
+        digits(&mut offset, true);
+        if offset < bytes.len() && (bytes[offset] == b'u' || bytes[offset] == b'U') {
+            offset += 1;
+        }
+        return (offset, TokenKind::IntLiteral);
+    }
+
+    let mut kind = TokenKind::IntLiteral;
+    digits(&mut offset, false);
+
+    if offset < bytes.len() && bytes[offset] == b'.' {
+        kind = TokenKind::FloatLiteral;
+        offset += 1;
+        digits(&mut offset, false);
+    }
+
+    if offset < bytes.len() && (bytes[offset] == b'e' || bytes[offset] == b'E') {
+        let mut exponent = offset + 1;
+        if exponent < bytes.len() && (bytes[exponent] == b'+' || bytes[exponent] == b'-') {
+            exponent += 1;
+        }
+        if exponent < bytes.len() && bytes[exponent].is_ascii_digit() {
+            kind = TokenKind::FloatLiteral;
+            offset = exponent;
+            digits(&mut offset, false);
+        }
+    }
+
+    if offset < bytes.len() {
+        match bytes[offset] {
+            b'f' | b'F' if kind == TokenKind::FloatLiteral => offset += 1,
+            b'u' | b'U' if kind == TokenKind::IntLiteral => offset += 1,
+            _ => {}
+        }
+    }
+
+    (offset, kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(source)
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    #[test]
+    fn tokens_reproduce_the_source() {
+        let source = "#version 300 es\n// comment\nuniform vec2 v; /* block\n comment */\nvoid main() { v.x <<= 2; }\n";
+        let text: String = tokenize(source).iter().map(|token| token.text).collect();
+
+        assert_eq!(text, source);
+    }
+
+    #[test]
+    fn tells_int_and_float_literals_apart() {
+        assert_eq!(
+            kinds("1 1.0 .5 1. 1e3 1.5e-2 2.0f 0x1F 7u 1e"),
+            vec![
+                (TokenKind::IntLiteral, "1"),
+                (TokenKind::FloatLiteral, "1.0"),
+                (TokenKind::FloatLiteral, ".5"),
+                (TokenKind::FloatLiteral, "1."),
+                (TokenKind::FloatLiteral, "1e3"),
+                (TokenKind::FloatLiteral, "1.5e-2"),
+                (TokenKind::FloatLiteral, "2.0f"),
+                (TokenKind::IntLiteral, "0x1F"),
+                (TokenKind::IntLiteral, "7u"),
+                (TokenKind::IntLiteral, "1"),
+                (TokenKind::Identifier, "e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn prefers_the_longest_symbol() {
+        let symbols: Vec<&str> = kinds("a<<=b<=c++-d").into_iter().map(|(_, text)| text).collect();
+
+        assert_eq!(symbols, vec!["a", "<<=", "b", "<=", "c", "++", "-", "d"]);
+    }
+
+    #[test]
+    fn directives_only_start_lines() {
+        assert_eq!(
+            kinds("  #define A \\\n  1\nx # y"),
+            vec![
+                (TokenKind::Directive, "#define A \\\n  1"),
+                (TokenKind::Identifier, "x"),
+                (TokenKind::Symbol, "#"),
+                (TokenKind::Identifier, "y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tracks_lines_and_columns() {
+        let tokens = tokenize("a\n  /* x\n */ b");
+        let b = tokens.iter().find(|token| token.text == "b").unwrap();
+
+        assert_eq!((b.line, b.column), (3, 5));
+        assert_eq!(b.offset, 13);
+    }
+
+    #[test]
+    fn comments_are_trivia() {
+        let tokens = tokenize("// a\n/* b */");
+
+        assert!(tokens.iter().all(Token::is_trivia));
+        assert!(!tokens[0].is("// a"));
+    }
+}
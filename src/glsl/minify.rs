@@ -0,0 +1,465 @@
+use std::collections::{HashMap, HashSet};
+
+use super::builtins;
+use super::lexer::{Token, TokenKind};
+use super::parser::{Shader, Variable};
+
+/// Qualifiers of the variables the app and the other stage refer to by name.
+const INTERFACE_QUALIFIERS: &[&str] = &["uniform", "attribute", "varying", "in", "out"];
+
+/// Words reserved for future use by GLSL ES that short names must avoid.
+const RESERVED: &[&str] = &[
+    "asm", "class", "union", "enum", "typedef", "template", "this", "packed", "goto", "inline",
+    "noinline", "volatile", "public", "static", "extern", "external", "interface", "long", "short",
+    "double", "half", "fixed", "unsigned", "superp", "input", "output", "sizeof", "cast",
+    "namespace", "using", "main",
+];
+
+/// Symbol pairs that would lex as a different token if written without a
+/// space between them.
+const JOINED_SYMBOLS: &[&str] = &[
+    "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "^^",
+    "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "//", "/*",
+];
+
+const FIRST_CHARACTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const CHARACTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_";
+
+/// Shrinks a shader for export: comments and whitespace are stripped, float
+/// literals shortened, `const`s initialized with a literal are inlined and
+/// functions, locals and private globals get the shortest free names.
+///
+/// Uniforms, attributes, varyings, `main`, struct members and anything a
+/// directive mentions keep their names, so the app and the other stage can
+/// still find them.
+pub fn minify(source: &str) -> String {
+    let shader = Shader::parse(source);
+    let significant = shader.significant();
+    let mut replacements: HashMap<usize, String> = HashMap::new();
+    let mut removed: HashSet<usize> = HashSet::new();
+
+    inline_constants(&shader, &significant, &mut replacements, &mut removed);
+    let names = rename(&shader, &significant);
+
+    let mut output = String::new();
+    let mut previous: Option<(TokenKind, String)> = None;
+
+    for (index, token) in shader.tokens.iter().enumerate() {
+        if token.is_trivia() || removed.contains(&index) {
+            continue;
+        }
+
+        if token.kind == TokenKind::Directive {
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+            output.push_str(token.text.trim());
+            output.push('\n');
+            previous = None;
+            continue;
+        }
+
+        let after_dot = previous.as_ref().map_or(false, |(_, text)| text == ".");
+        let text = match replacements.get(&index) {
+            Some(replacement) => replacement.clone(),
+            None => match token.kind {
+                TokenKind::FloatLiteral => shorten_float(token.text),
+                TokenKind::Identifier if !after_dot => names.get(token.text).cloned().unwrap_or_else(|| token.text.to_string()),
+                _ => token.text.to_string(),
+            },
+        };
+
+        if let Some((kind, previous_text)) = &previous {
+            if needs_space(*kind, previous_text, token.kind, &text) {
+                output.push(' ');
+            }
+        }
+
+        output.push_str(&text);
+        previous = Some((token.kind, text));
+    }
+
+    if !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+/// Replaces every use of a `const` initialized with a single literal by the
+/// literal and drops its declaration.
+fn inline_constants(shader: &Shader, significant: &[usize], replacements: &mut HashMap<usize, String>, removed: &mut HashSet<usize>) {
+    let variables: Vec<&Variable> = shader.globals
+        .iter()
+        .chain(shader.functions.iter().flat_map(|function| function.parameters.iter().chain(function.locals.iter())))
+        .collect();
+
+    for variable in &variables {
+        if !variable.has_qualifier("const") || variable.array {
+            continue;
+        }
+
+        // Only names declared once, so no use can refer to a shadowing variable.
+        if variables.iter().filter(|other| other.name == variable.name).count() != 1 {
+            continue;
+        }
+
+        let literal = match literal(shader, significant, variable) {
+            Some(literal) => literal,
+            None => continue,
+        };
+
+        let declaration = match declaration_range(shader, significant, variable) {
+            Some(declaration) => declaration,
+            None => continue,
+        };
+
+        for index in shader.references(&variable.name) {
+            replacements.insert(index, literal.clone());
+        }
+        removed.extend(declaration);
+    }
+}
+
+/// The initializer of a variable if it's a single, possibly negated, literal.
+fn literal(shader: &Shader, significant: &[usize], variable: &Variable) -> Option<String> {
+    let (start, end) = variable.initializer?;
+    let tokens: Vec<&Token> = significant
+        .iter()
+        .filter(|&&index| index >= start && index < end)
+        .map(|&index| &shader.tokens[index])
+        .collect();
+
+    let value = |token: &Token| match token.kind {
+        TokenKind::FloatLiteral => Some(shorten_float(token.text)),
+        TokenKind::IntLiteral => Some(token.text.to_string()),
+        _ if token.is("true") || token.is("false") => Some(token.text.to_string()),
+        _ => None,
+    };
+
+    match tokens.as_slice() {
+        [literal] => value(literal),
+        [minus, literal] if minus.is("-") => value(literal).map(|literal| format!("(-{})", literal)),
+        _ => None,
+    }
+}
+
+/// Token indices of a declaration that declares only `variable`, from its
+/// first qualifier through the `;`.
+fn declaration_range(shader: &Shader, significant: &[usize], variable: &Variable) -> Option<Vec<usize>> {
+    let name = significant.iter().position(|&index| index == variable.token)?;
+    let (_, end) = variable.initializer?;
+    let semicolon = significant.iter().position(|&index| index >= end)?;
+    if shader.tokens[significant[semicolon]].text != ";" {
+        return None;
+    }
+
+    let mut start = name;
+    while start > 0 {
+        let text = shader.tokens[significant[start - 1]].text;
+        if text == ";" || text == "{" || text == "}" {
+            break;
+        }
+        if text == "," || text == "(" {
+            return None;
+        }
+        start -= 1;
+    }
+
+    Some(significant[start..=semicolon].to_vec())
+}
+
+/// Picks short names for the functions, locals and private globals, the most
+/// used names first.
+///
+/// Every occurrence of a name gets the same new name and new names never
+/// clash with existing ones, so shadowing and overloads work as before.
+fn rename(shader: &Shader, significant: &[usize]) -> HashMap<String, String> {
+    let mut kept: HashSet<&str> = HashSet::new();
+    let mut used: HashSet<&str> = HashSet::new();
+
+    for token in &shader.tokens {
+        match token.kind {
+            TokenKind::Identifier => {
+                used.insert(token.text);
+            }
+            TokenKind::Directive => {
+                for word in token.text[1..].split(|c: char| !c.is_ascii_alphanumeric() && c != '_') {
+                    kept.insert(word);
+                    used.insert(word);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for variable in &shader.globals {
+        if variable.qualifiers.iter().any(|qualifier| INTERFACE_QUALIFIERS.contains(&qualifier.as_str())) {
+            kept.insert(&variable.name);
+        }
+    }
+
+    // Struct members and interface block fields are looked up by name.
+    let mut depth = 0;
+    let mut block = false;
+    for (position, &index) in significant.iter().enumerate() {
+        let token = &shader.tokens[index];
+        match token.text {
+            "{" => {
+                if depth == 0 {
+                    block = position > 0 && !shader.tokens[significant[position - 1]].is(")");
+                }
+                depth += 1;
+            }
+            "}" => depth -= 1,
+            ";" if depth == 0 => block = false,
+            _ if block && token.kind == TokenKind::Identifier => {
+                kept.insert(token.text);
+            }
+            _ => {}
+        }
+    }
+
+    let mut candidates: Vec<&str> = shader.functions
+        .iter()
+        .filter(|function| function.name != "main" && !builtins::is_function(&function.name))
+        .map(|function| function.name.as_str())
+        .chain(shader.functions.iter().flat_map(|function| {
+            function.parameters.iter().chain(function.locals.iter()).map(|variable| variable.name.as_str())
+        }))
+        .chain(shader.globals.iter().map(|variable| variable.name.as_str()))
+        .filter(|name| !kept.contains(name) && !name.starts_with("gl_"))
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    let uses = |name: &str| shader.tokens.iter().filter(|token| token.kind == TokenKind::Identifier && token.text == name).count();
+    candidates.sort_by_key(|name| std::cmp::Reverse(uses(name)));
+
+    let mut names = HashMap::new();
+    let mut counter = 0;
+    for candidate in candidates {
+        let name = loop {
+            let name = short_name(counter);
+            counter += 1;
+            let reserved = builtins::TYPES.contains(&name.as_str())
+                || builtins::QUALIFIERS.contains(&name.as_str())
+                || builtins::KEYWORDS.contains(&name.as_str())
+                || builtins::is_function(&name)
+                || RESERVED.contains(&name.as_str());
+            if !reserved && !used.contains(name.as_str()) {
+                break name;
+            }
+        };
+
+        // Keep the original if it's already as short.
+        if name.len() < candidate.len() {
+            names.insert(candidate.to_string(), name);
+        } else {
+            counter -= 1;
+        }
+    }
+
+    names
+}
+
+/// The n-th identifier in order of length: `a` to `Z`, then `aa`, `ba` and so on.
+fn short_name(mut counter: usize) -> String {
+    let mut name = String::new();
+    name.push(FIRST_CHARACTERS[counter % FIRST_CHARACTERS.len()] as char);
+    counter /= FIRST_CHARACTERS.len();
+
+    while counter > 0 {
+        counter -= 1;
+        name.push(CHARACTERS[counter % CHARACTERS.len()] as char);
+        counter /= CHARACTERS.len();
+    }
+
+    name
+}
+
+/// Drops redundant zeros from a float literal, `0.50` becomes `.5` and `1.0`
+/// becomes `1.`.
+fn shorten_float(text: &str) -> String {
+    if text.contains(|c: char| c == 'e' || c == 'E' || c == 'f' || c == 'F') {
+        return text.to_string();
+    }
+
+    let mut parts = text.splitn(2, '.');
+    let whole = parts.next().unwrap_or("").trim_start_matches('0');
+    let fraction = parts.next().unwrap_or("").trim_end_matches('0');
+
+    if whole.is_empty() && fraction.is_empty() {
+        String::from("0.")
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+fn needs_space(previous_kind: TokenKind, previous: &str, kind: TokenKind, text: &str) -> bool {
+    match (previous_kind, kind) {
+        (TokenKind::Symbol, TokenKind::Symbol) => {
+            let mut joined = String::new();
+            joined.extend(previous.chars().last());
+            joined.extend(text.chars().next());
+            JOINED_SYMBOLS.contains(&joined.as_str())
+        }
+        // Identifiers and literals run together otherwise.
+        (TokenKind::Symbol, _) | (_, TokenKind::Symbol) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glsl::lexer::tokenize;
+
+    const SOURCE: &str = "\
+#define SCALE 2.0
+precision mediump float;
+struct Material { vec3 albedo; float roughness; };
+uniform Material uMaterial;
+uniform float uTime;
+varying vec3 vNormal;
+const float PI = 3.14159;
+const vec3 LIGHT = vec3(0.0, 1.0, 0.0);
+float brightness = 0.50;
+
+// Lambert term, scaled.
+float lambert(vec3 normal, vec3 light) {
+    float amount = max(dot(normal, light), 0.0);
+    return amount * SCALE / PI;
+}
+
+void main() {
+    float roughness = uMaterial.roughness;
+    vec3 color = uMaterial.albedo * lambert(normalize(vNormal), LIGHT) * brightness;
+    gl_FragColor = vec4(color * (1.0 - roughness) * sin(uTime), 1.0);
+}
+";
+
+    /// The interface of a shader: its uniforms, attributes and varyings
+    /// with their qualifiers and types, and its struct members.
+    fn interface(shader: &Shader) -> Vec<(Vec<String>, String, String)> {
+        shader.globals
+            .iter()
+            .filter(|variable| INTERFACE_QUALIFIERS.iter().any(|qualifier| variable.has_qualifier(qualifier)))
+            .map(|variable| (variable.qualifiers.clone(), variable.type_name.clone(), variable.name.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_interface() {
+        let minified = minify(SOURCE);
+        let (before, after) = (Shader::parse(SOURCE), Shader::parse(&minified));
+
+        assert_eq!(interface(&after), interface(&before));
+        assert!(after.function("main").is_some());
+        assert_eq!(after.structs, vec!["Material"]);
+        // Struct members are selected by name.
+        assert!(minified.contains("struct Material{vec3 albedo;float roughness;}"));
+        assert!(minified.contains("uMaterial.roughness"));
+        assert!(minified.contains("uMaterial.albedo"));
+    }
+
+    #[test]
+    fn output_parses_like_the_source() {
+        let minified = minify(SOURCE);
+        let (before, after) = (Shader::parse(SOURCE), Shader::parse(&minified));
+
+        assert_eq!(after.functions.len(), before.functions.len());
+        let precisions = |shader: &Shader| -> Vec<(String, String)> {
+            shader.precisions.iter().map(|precision| (precision.precision.clone(), precision.type_name.clone())).collect()
+        };
+        assert_eq!(precisions(&after), precisions(&before));
+        assert_eq!(after.macros, before.macros);
+        // Minifying again only renames what's already short.
+        assert_eq!(minify(&minified).len(), minified.len());
+    }
+
+    fn identifiers(source: &str) -> Vec<&str> {
+        tokenize(source)
+            .into_iter()
+            .filter(|token| token.kind == TokenKind::Identifier)
+            .map(|token| token.text)
+            .collect()
+    }
+
+    #[test]
+    fn renames_functions_locals_and_private_globals() {
+        let minified = minify(SOURCE);
+        let identifiers = identifiers(&minified);
+
+        for name in &["lambert", "normal", "light", "amount", "color", "brightness", "LIGHT"] {
+            assert!(!identifiers.contains(name), "`{}` wasn't renamed in {}", name, minified);
+        }
+        // Built-ins keep their names, and so does a local named like a
+        // struct member, since members aren't renamed.
+        for name in &["normalize", "max", "dot", "sin", "gl_FragColor", "roughness"] {
+            assert!(identifiers.contains(name));
+        }
+    }
+
+    #[test]
+    fn inlines_literal_constants() {
+        let minified = minify(SOURCE);
+
+        assert!(!minified.contains("PI"));
+        assert!(minified.contains("/3.14159"));
+        assert!(!minified.contains("const float"));
+        // Only single literals are inlined, `LIGHT` is renamed instead.
+        assert!(minified.contains("const vec3"));
+        // Macros are left to the preprocessor.
+        assert!(minified.starts_with("#define SCALE 2.0\n"));
+        assert!(minified.contains("*SCALE/"));
+    }
+
+    #[test]
+    fn leaves_shadowed_constants() {
+        let source = "const float a = 1.0;\nvoid main() { const float a = 2.0; gl_FragColor = vec4(a); }";
+        let minified = minify(source);
+
+        assert_eq!(minified.matches("const float").count(), 2);
+    }
+
+    #[test]
+    fn constants_named_like_struct_members_keep_the_members() {
+        let source = "const float power = 2.0;\nstruct Light { float power; };\nuniform Light light;\nvoid main() { gl_FragColor = vec4(light.power * power); }";
+        let minified = minify(source);
+
+        assert!(minified.contains("struct Light{float power;}"));
+        assert!(minified.contains("light.power*2."));
+    }
+
+    #[test]
+    fn strips_comments_and_whitespace() {
+        let minified = minify(SOURCE);
+
+        assert!(!minified.contains("//"));
+        assert!(!minified.contains("  "));
+        assert_eq!(minified.lines().count(), 2);
+    }
+
+    #[test]
+    fn keeps_tokens_apart() {
+        assert_eq!(minify("float f(float a, float b) { return a - -b + +a; }"), "float f(float a,float b){return a- -b+ +a;}\n");
+        assert_eq!(minify("int f(int a) { return a + ++a; }"), "int f(int a){return a+ ++a;}\n");
+    }
+
+    #[test]
+    fn shortens_floats() {
+        assert_eq!(shorten_float("0.50"), ".5");
+        assert_eq!(shorten_float("1.0"), "1.");
+        assert_eq!(shorten_float("10.0"), "10.");
+        assert_eq!(shorten_float("0.0"), "0.");
+        assert_eq!(shorten_float("2.5e3"), "2.5e3");
+    }
+
+    #[test]
+    fn short_names_grow_after_running_out() {
+        assert_eq!(short_name(0), "a");
+        assert_eq!(short_name(51), "Z");
+        assert_eq!(short_name(52), "aa");
+        assert_eq!(short_name(53), "ba");
+    }
+}
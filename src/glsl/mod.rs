@@ -0,0 +1,13 @@
+pub mod analyzer;
+pub mod builtins;
+pub mod diagnostic;
+pub mod format;
+pub mod interface;
+pub mod lexer;
+pub mod minify;
+pub mod parser;
+pub mod preprocessor;
+pub mod translate;
+
+pub use self::diagnostic::{Diagnostic, Severity, Stage};
+pub use self::preprocessor::{Preprocessed, Preprocessor, SnippetLibrary, SourceLocation, SourceMap};
@@ -0,0 +1,703 @@
+use std::collections::HashSet;
+
+use super::builtins;
+use super::lexer::{tokenize, Token, TokenKind};
+
+/// The GLSL ES language version a shader is written against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Version {
+    /// GLSL ES 1.00, used by WebGL1.
+    Es100,
+    /// GLSL ES 3.00, declared with `#version 300 es` and used by WebGL2.
+    Es300,
+}
+
+/// Storage, interpolation and precision qualifiers that can prefix a declaration.
+pub const DECLARATION_QUALIFIERS: &[&str] = &[
+    "const", "attribute", "uniform", "varying", "in", "out", "inout", "centroid",
+    "flat", "smooth", "invariant", "highp", "mediump", "lowp",
+];
+
+pub const PRECISIONS: &[&str] = &["highp", "mediump", "lowp"];
+
+/// A declared variable, parameter or struct member.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    /// Storage and interpolation qualifiers, e.g. `uniform` or `const`.
+    pub qualifiers: Vec<String>,
+    pub precision: Option<String>,
+    pub type_name: String,
+    pub name: String,
+    pub array: bool,
+    /// Index of the token naming the variable.
+    pub token: usize,
+    pub line: u32,
+    pub column: u32,
+    /// Token range of the initializer, if any.
+    pub initializer: Option<(usize, usize)>,
+}
+
+impl Variable {
+    pub fn has_qualifier(&self, qualifier: &str) -> bool {
+        self.qualifiers.iter().any(|entry| entry == qualifier)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub return_type: String,
+    pub name: String,
+    pub parameters: Vec<Variable>,
+    pub locals: Vec<Variable>,
+    /// Token range between the braces of the body, `None` for prototypes.
+    pub body: Option<(usize, usize)>,
+    pub token: usize,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// A `for` statement, with the token ranges of its three clauses. Ranges are
+/// half open indices into `Shader::tokens`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForLoop {
+    pub init: (usize, usize),
+    pub condition: (usize, usize),
+    pub increment: (usize, usize),
+    pub line: u32,
+    pub column: u32,
+}
+
+/// A default precision statement such as `precision mediump float;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Precision {
+    pub precision: String,
+    pub type_name: String,
+    pub line: u32,
+}
+
+/// A lightweight syntax tree of a shader: its global declarations, functions
+/// and loops, with every node pointing back into the token stream.
+///
+/// It only understands as much of GLSL as the analysis and rewriting tools
+/// need; expressions are left as token ranges.
+#[derive(Debug, Clone)]
+pub struct Shader<'a> {
+    pub tokens: Vec<Token<'a>>,
+    pub version: Version,
+    pub globals: Vec<Variable>,
+    pub functions: Vec<Function>,
+    pub structs: Vec<String>,
+    pub precisions: Vec<Precision>,
+    pub loops: Vec<ForLoop>,
+    /// Names introduced by `#define`.
+    pub macros: Vec<String>,
+    /// Indices of the tokens that name a declaration rather than use it.
+    declarations: HashSet<usize>,
+}
+
+impl<'a> Shader<'a> {
+    pub fn parse(source: &'a str) -> Self {
+        let tokens = tokenize(source);
+        let mut shader = Self {
+            version: Version::Es100,
+            globals: Vec::new(),
+            functions: Vec::new(),
+            structs: Vec::new(),
+            precisions: Vec::new(),
+            loops: Vec::new(),
+            macros: Vec::new(),
+            declarations: HashSet::new(),
+            tokens,
+        };
+
+        for token in shader.tokens.iter().filter(|token| token.kind == TokenKind::Directive) {
+            let words: Vec<&str> = token.text[1..].split_whitespace().collect();
+            match words.as_slice() {
+                ["version", "300", ..] => shader.version = Version::Es300,
+                ["define", name, ..] => {
+                    let name = name.split('(').next().unwrap();
+                    shader.macros.push(name.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        let significant = shader.significant();
+
+        Parser { shader: &mut shader, significant: &significant, position: 0 }.parse_globals();
+        shader
+    }
+
+    /// Indices of the tokens that aren't whitespace, comments or directives.
+    pub fn significant(&self) -> Vec<usize> {
+        self.significant_in((0, self.tokens.len()))
+    }
+
+    /// Significant token indices inside a half open token range.
+    pub fn significant_in(&self, range: (usize, usize)) -> Vec<usize> {
+        (range.0..range.1.min(self.tokens.len()))
+            .filter(|&index| !self.tokens[index].is_trivia() && self.tokens[index].kind != TokenKind::Directive)
+            .collect()
+    }
+
+    pub fn global(&self, name: &str) -> Option<&Variable> {
+        self.globals.iter().find(|variable| variable.name == name)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name && function.body.is_some())
+    }
+
+    /// The function whose body contains a token.
+    pub fn function_at(&self, token: usize) -> Option<&Function> {
+        self.functions.iter().find(|function| match function.body {
+            Some((start, end)) => token > start && token < end,
+            None => false,
+        })
+    }
+
+    /// Looks up the variable a name refers to at a token, searching the
+    /// enclosing function's locals and parameters before the globals.
+    pub fn variable_at(&self, name: &str, token: usize) -> Option<&Variable> {
+        if let Some(function) = self.function_at(token) {
+            let local = function.locals
+                .iter()
+                .chain(function.parameters.iter())
+                .find(|variable| variable.name == name);
+            if local.is_some() {
+                return local;
+            }
+        }
+        self.global(name)
+    }
+
+    pub fn is_declaration(&self, token: usize) -> bool {
+        self.declarations.contains(&token)
+    }
+
+    /// Identifier tokens that use a name, excluding its declarations and
+    /// field selections such as `color.name`.
+    pub fn references(&self, name: &str) -> Vec<usize> {
+        let significant = self.significant();
+        significant
+            .iter()
+            .enumerate()
+            .filter(|&(position, &index)| {
+                let token = &self.tokens[index];
+                token.kind == TokenKind::Identifier
+                    && token.text == name
+                    && !self.is_declaration(index)
+                    && (position == 0 || !self.tokens[significant[position - 1]].is("."))
+            })
+            .map(|(_, &index)| index)
+            .collect()
+    }
+
+    /// Whether the identifier at a token is assigned to, including through a
+    /// swizzle or index such as `v.xy = ...` or `v[0] += ...`.
+    pub fn is_write(&self, token: usize) -> bool {
+        let significant = self.significant();
+        let position = match significant.iter().position(|&index| index == token) {
+            Some(position) => position,
+            None => return false,
+        };
+
+        if position > 0 {
+            let previous = &self.tokens[significant[position - 1]];
+            if previous.is("++") || previous.is("--") {
+                return true;
+            }
+        }
+
+        let mut next = position + 1;
+        loop {
+            match significant.get(next).map(|&index| &self.tokens[index]) {
+                Some(token) if token.is(".") => next += 2,
+                Some(token) if token.is("[") => {
+                    next = match_close(&self.tokens, &significant, next) + 1;
+                }
+                Some(token) => {
+                    return ["=", "+=", "-=", "*=", "/=", "%=", "<<=", ">>=", "&=", "|=", "^=", "++", "--"]
+                        .iter()
+                        .any(|operator| token.is(operator));
+                }
+                None => return false,
+            }
+        }
+    }
+
+    /// Whether a type name is a built-in type or a struct declared in this shader.
+    pub fn is_type(&self, name: &str) -> bool {
+        builtins::is_type(name) || self.structs.iter().any(|entry| entry == name)
+    }
+}
+
+/// Position in `significant` of the bracket closing the one at `position`.
+pub fn match_close(tokens: &[Token], significant: &[usize], position: usize) -> usize {
+    let mut depth = 0;
+    for (offset, &index) in significant[position..].iter().enumerate() {
+        let text = tokens[index].text;
+        if text == "(" || text == "[" || text == "{" {
+            depth += 1;
+        } else if text == ")" || text == "]" || text == "}" {
+            depth -= 1;
+            if depth == 0 {
+                return position + offset;
+            }
+        }
+    }
+    significant.len() - 1
+}
+
+struct Parser<'s, 'a> {
+    shader: &'s mut Shader<'a>,
+    significant: &'s [usize],
+    position: usize,
+}
+
+impl<'s, 'a> Parser<'s, 'a> {
+    fn token(&self, position: usize) -> Option<&Token<'a>> {
+        self.significant.get(position).map(|&index| &self.shader.tokens[index])
+    }
+
+    fn text(&self, position: usize) -> &'a str {
+        self.significant.get(position).map_or("", |&index| self.shader.tokens[index].text)
+    }
+
+    fn is_identifier(&self, position: usize) -> bool {
+        self.token(position).map_or(false, |token| token.kind == TokenKind::Identifier)
+    }
+
+    fn close(&self, position: usize) -> usize {
+        match_close(&self.shader.tokens, self.significant, position)
+    }
+
+    /// Position of the next `;` at bracket depth zero, or the end.
+    fn statement_end(&self, mut position: usize, end: usize) -> usize {
+        while position < end {
+            match self.text(position) {
+                ";" => return position,
+                "(" | "[" | "{" => position = self.close(position) + 1,
+                _ => position += 1,
+            }
+        }
+        end
+    }
+
+    fn parse_globals(&mut self) {
+        let end = self.significant.len();
+
+        while self.position < end {
+            let start = self.position;
+
+            match self.text(start) {
+                ";" => self.position += 1,
+                "precision" => {
+                    let statement_end = self.statement_end(start, end);
+                    if statement_end >= start + 3 {
+                        let line = self.token(start).unwrap().line;
+                        self.shader.precisions.push(Precision {
+                            precision: self.text(start + 1).to_string(),
+                            type_name: self.text(start + 2).to_string(),
+                            line,
+                        });
+                    }
+                    self.position = statement_end + 1;
+                }
+                _ => {
+                    let mut variables = Vec::new();
+                    self.position = self.parse_declaration(start, end, &mut variables, true);
+                    self.shader.globals.extend(variables);
+                }
+            }
+
+            if self.position <= start {
+                self.position = start + 1;
+            }
+        }
+    }
+
+    /// Parses a declaration, function or struct starting at `start`, returning
+    /// the position just past it. Unrecognized statements are skipped.
+    fn parse_declaration(&mut self, start: usize, end: usize, variables: &mut Vec<Variable>, global: bool) -> usize {
+        let mut position = start;
+        let mut qualifiers = Vec::new();
+        let mut precision = None;
+
+        loop {
+            let text = self.text(position);
+            if text == "layout" && self.text(position + 1) == "(" {
+                position = self.close(position + 1) + 1;
+            } else if PRECISIONS.contains(&text) {
+                precision = Some(text.to_string());
+                position += 1;
+            } else if DECLARATION_QUALIFIERS.contains(&text) {
+                qualifiers.push(text.to_string());
+                position += 1;
+            } else {
+                break;
+            }
+        }
+
+        if self.text(position) == "struct" {
+            position += 1;
+            if self.is_identifier(position) {
+                let name = self.text(position).to_string();
+                self.shader.structs.push(name);
+                position += 1;
+            }
+            if self.text(position) == "{" {
+                // Members aren't variables, but naming one isn't a use either.
+                let close = self.close(position);
+                let mut members = Vec::new();
+                position += 1;
+                while position < close {
+                    position = self.parse_declaration(position, close, &mut members, false).max(position + 1);
+                }
+                position = close + 1;
+            }
+            // Declarators can follow the closing brace, `struct S { ... } s;`.
+            let type_name = self.shader.structs.last().cloned().unwrap_or_default();
+            return self.parse_declarators(position, end, &qualifiers, &precision, &type_name, variables);
+        }
+
+        let type_name = self.text(position);
+        if !self.is_identifier(position) || !self.is_identifier(position + 1) {
+            return self.statement_end(start, end) + 1;
+        }
+
+        let name_position = position + 1;
+        if global && self.text(name_position + 1) == "(" {
+            return self.parse_function(type_name, name_position, end);
+        }
+
+        self.parse_declarators(name_position, end, &qualifiers, &precision, type_name, variables)
+    }
+
+    fn parse_declarators(
+        &mut self,
+        mut position: usize,
+        end: usize,
+        qualifiers: &[String],
+        precision: &Option<String>,
+        type_name: &str,
+        variables: &mut Vec<Variable>,
+    ) -> usize {
+        while position < end && self.is_identifier(position) {
+            let index = self.significant[position];
+            let token = self.shader.tokens[index].clone();
+            let mut variable = Variable {
+                qualifiers: qualifiers.to_vec(),
+                precision: precision.clone(),
+                type_name: type_name.to_string(),
+                name: token.text.to_string(),
+                array: false,
+                token: index,
+                line: token.line,
+                column: token.column,
+                initializer: None,
+            };
+            self.shader.declarations.insert(index);
+            position += 1;
+
+            if self.text(position) == "[" {
+                variable.array = true;
+                position = self.close(position) + 1;
+            }
+
+            if self.text(position) == "=" {
+                let initializer_start = position + 1;
+                while position < end && self.text(position) != "," && self.text(position) != ";" {
+                    match self.text(position) {
+                        "(" | "[" | "{" => position = self.close(position) + 1,
+                        _ => position += 1,
+                    }
+                }
+                if position > initializer_start {
+                    variable.initializer = Some(self.range(initializer_start, position));
+                }
+            }
+
+            variables.push(variable);
+
+            if self.text(position) == "," {
+                position += 1;
+            } else {
+                break;
+            }
+        }
+
+        self.statement_end(position, end) + 1
+    }
+
+    fn parse_function(&mut self, return_type: &str, name_position: usize, end: usize) -> usize {
+        let index = self.significant[name_position];
+        let token = self.shader.tokens[index].clone();
+        self.shader.declarations.insert(index);
+
+        let parameters_end = self.close(name_position + 1);
+        let mut parameters = Vec::new();
+        let mut position = name_position + 2;
+
+        while position < parameters_end {
+            let parameter_end = {
+                let mut scan = position;
+                while scan < parameters_end && self.text(scan) != "," {
+                    scan += 1;
+                }
+                scan
+            };
+
+            let mut scan = position;
+            let mut qualifiers = Vec::new();
+            let mut precision = None;
+            while scan < parameter_end {
+                let text = self.text(scan);
+                if PRECISIONS.contains(&text) {
+                    precision = Some(text.to_string());
+                } else if DECLARATION_QUALIFIERS.contains(&text) {
+                    qualifiers.push(text.to_string());
+                } else {
+                    break;
+                }
+                scan += 1;
+            }
+
+            if scan + 1 < parameter_end && self.is_identifier(scan + 1) {
+                let type_name = self.text(scan).to_string();
+                let index = self.significant[scan + 1];
+                let name = self.shader.tokens[index].clone();
+                self.shader.declarations.insert(index);
+                parameters.push(Variable {
+                    qualifiers,
+                    precision,
+                    type_name,
+                    name: name.text.to_string(),
+                    array: self.text(scan + 2) == "[",
+                    token: index,
+                    line: name.line,
+                    column: name.column,
+                    initializer: None,
+                });
+            }
+
+            position = parameter_end + 1;
+        }
+
+        let mut function = Function {
+            return_type: return_type.to_string(),
+            name: token.text.to_string(),
+            parameters,
+            locals: Vec::new(),
+            body: None,
+            token: index,
+            line: token.line,
+            column: token.column,
+        };
+
+        if self.text(parameters_end + 1) != "{" {
+            self.shader.functions.push(function);
+            return self.statement_end(parameters_end + 1, end) + 1;
+        }
+
+        let body_start = parameters_end + 1;
+        let body_end = self.close(body_start);
+        function.body = Some((self.significant[body_start], self.significant[body_end]));
+        function.locals = self.parse_body(body_start + 1, body_end);
+        self.shader.functions.push(function);
+
+        body_end + 1
+    }
+
+    /// Collects local declarations and `for` loops from a function body.
+    fn parse_body(&mut self, start: usize, end: usize) -> Vec<Variable> {
+        let mut locals = Vec::new();
+        let mut position = start;
+        let mut statement_start = true;
+
+        while position < end {
+            let text = self.text(position);
+
+            if text == "for" && self.text(position + 1) == "(" {
+                let header_end = self.close(position + 1);
+                let first = self.statement_end(position + 2, header_end);
+                let second = self.statement_end(first + 1, header_end);
+                let token = self.token(position).unwrap().clone();
+
+                self.parse_local(position + 2, first + 1, &mut locals);
+                self.shader.loops.push(ForLoop {
+                    init: self.range(position + 2, first),
+                    condition: self.range(first + 1, second),
+                    increment: self.range(second + 1, header_end),
+                    line: token.line,
+                    column: token.column,
+                });
+
+                position = header_end + 1;
+                statement_start = true;
+                continue;
+            }
+
+            if statement_start && self.starts_declaration(position) {
+                position = self.parse_local(position, end, &mut locals);
+                statement_start = true;
+                continue;
+            }
+
+            statement_start = text == ";" || text == "{" || text == "}" || (text == ")" && self.text(position + 1) == "{");
+            position += 1;
+        }
+
+        locals
+    }
+
+    fn parse_local(&mut self, start: usize, end: usize, locals: &mut Vec<Variable>) -> usize {
+        if self.starts_declaration(start) {
+            self.parse_declaration(start, end, locals, false)
+        } else {
+            start
+        }
+    }
+
+    /// Whether the tokens at `position` look like `[qualifiers] type name`.
+    fn starts_declaration(&self, mut position: usize) -> bool {
+        while DECLARATION_QUALIFIERS.contains(&self.text(position)) {
+            position += 1;
+        }
+        (self.shader.is_type(self.text(position)) && self.is_identifier(position + 1))
+            || self.text(position) == "struct"
+    }
+
+    /// Token index range covering significant positions `start..end`.
+    fn range(&self, start: usize, end: usize) -> (usize, usize) {
+        if start >= end {
+            let index = self.significant.get(start).cloned().unwrap_or(0);
+            return (index, index);
+        }
+        (self.significant[start], self.significant[end - 1] + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+#version 300 es
+#define STEPS 4
+precision highp float;
+struct Light { vec3 position; float power; };
+uniform Light light;
+uniform mediump vec2 offsets[STEPS];
+in vec3 vNormal;
+out vec4 color;
+
+float shade(vec3 normal, float power) {
+    float total = 0.0;
+    for (int i = 0; i < STEPS; i++) {
+        total += dot(normal, light.position) * power;
+    }
+    return total;
+}
+
+void main() {
+    vec3 normal = normalize(vNormal);
+    color = vec4(vec3(shade(normal, light.power)), 1.0);
+    color.a = 0.5;
+}
+";
+
+    fn text<'a>(shader: &Shader<'a>, range: (usize, usize)) -> Vec<&'a str> {
+        shader.significant_in(range).iter().map(|&index| shader.tokens[index].text).collect()
+    }
+
+    #[test]
+    fn reads_the_version_macros_and_precisions() {
+        let shader = Shader::parse(SOURCE);
+
+        assert_eq!(shader.version, Version::Es300);
+        assert_eq!(shader.macros, vec!["STEPS"]);
+        assert_eq!(shader.precisions.len(), 1);
+        assert_eq!((shader.precisions[0].precision.as_str(), shader.precisions[0].type_name.as_str()), ("highp", "float"));
+        assert_eq!(Shader::parse("void main() {}").version, Version::Es100);
+    }
+
+    #[test]
+    fn collects_globals_with_their_qualifiers() {
+        let shader = Shader::parse(SOURCE);
+        let names: Vec<&str> = shader.globals.iter().map(|variable| variable.name.as_str()).collect();
+        assert_eq!(names, vec!["light", "offsets", "vNormal", "color"]);
+
+        let offsets = shader.global("offsets").unwrap();
+        assert!(offsets.has_qualifier("uniform"));
+        assert_eq!(offsets.precision.as_deref(), Some("mediump"));
+        assert_eq!(offsets.type_name, "vec2");
+        assert!(offsets.array);
+        assert_eq!((offsets.line, offsets.column), (6, 22));
+
+        assert_eq!(shader.structs, vec!["Light"]);
+        assert!(shader.is_type("Light"));
+        assert_eq!(shader.global("light").unwrap().type_name, "Light");
+    }
+
+    #[test]
+    fn collects_functions_with_parameters_and_locals() {
+        let shader = Shader::parse(SOURCE);
+        let shade = shader.function("shade").unwrap();
+
+        assert_eq!(shade.return_type, "float");
+        let parameters: Vec<&str> = shade.parameters.iter().map(|variable| variable.name.as_str()).collect();
+        assert_eq!(parameters, vec!["normal", "power"]);
+        let locals: Vec<&str> = shade.locals.iter().map(|variable| variable.name.as_str()).collect();
+        assert!(locals.contains(&"total"));
+        assert!(locals.contains(&"i"));
+
+        // `normal` means the parameter in `shade` and the local in `main`.
+        let total = shade.locals.iter().find(|variable| variable.name == "total").unwrap();
+        assert_eq!(shader.variable_at("normal", total.token).unwrap().line, 10);
+        let main = shader.function("main").unwrap();
+        assert_eq!(shader.variable_at("normal", main.body.unwrap().0 + 1).unwrap().line, 19);
+    }
+
+    #[test]
+    fn splits_for_loops_into_clauses() {
+        let shader = Shader::parse(SOURCE);
+
+        assert_eq!(shader.loops.len(), 1);
+        let for_loop = &shader.loops[0];
+        assert_eq!(for_loop.line, 12);
+        assert_eq!(text(&shader, for_loop.init), vec!["int", "i", "=", "0"]);
+        assert_eq!(text(&shader, for_loop.condition), vec!["i", "<", "STEPS"]);
+        assert_eq!(text(&shader, for_loop.increment), vec!["i", "++"]);
+    }
+
+    #[test]
+    fn references_skip_declarations_and_fields() {
+        let shader = Shader::parse(SOURCE);
+
+        // `light.power` selects a field, it doesn't use a `power` variable.
+        let lines: Vec<u32> = shader.references("power").iter().map(|&index| shader.tokens[index].line).collect();
+        assert_eq!(lines, vec![13]);
+        assert_eq!(shader.references("vNormal").len(), 1);
+        assert!(shader.references("offsets").is_empty());
+    }
+
+    #[test]
+    fn tells_writes_from_reads() {
+        let shader = Shader::parse(SOURCE);
+        let writes: Vec<bool> = shader.references("color").iter().map(|&index| shader.is_write(index)).collect();
+        assert_eq!(writes, vec![true, true]);
+
+        let total: Vec<bool> = shader.references("total").iter().map(|&index| shader.is_write(index)).collect();
+        assert_eq!(total, vec![true, false]);
+    }
+
+    #[test]
+    fn matches_brackets() {
+        let shader = Shader::parse("f(a[(1)], { b });");
+        let significant = shader.significant();
+
+        assert_eq!(match_close(&shader.tokens, &significant, 1), significant.len() - 2);
+        assert_eq!(match_close(&shader.tokens, &significant, 3), 7);
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Named GLSL snippets that shaders can pull in with `#include "name.glsl"`.
+///
+/// The library starts out with the built-in snippets, users can add their own
+/// or replace the built-in ones with edited copies.
+#[derive(Debug, Clone)]
+pub struct SnippetLibrary {
+    snippets: BTreeMap<String, String>,
+}
+
+impl SnippetLibrary {
+    pub fn new() -> Self {
+        let mut library = Self { snippets: BTreeMap::new() };
+        library.insert("hash.glsl", include_str!("snippets/hash.glsl"));
+        library.insert("noise.glsl", include_str!("snippets/noise.glsl"));
+        library.insert("sdf.glsl", include_str!("snippets/sdf.glsl"));
+        library.insert("color.glsl", include_str!("snippets/color.glsl"));
+        library
+    }
+
+    /// Adds a snippet, replacing any existing snippet with the same name.
+    pub fn insert(&mut self, name: &str, source: &str) {
+        self.snippets.insert(name.to_string(), source.to_string());
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.snippets.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.snippets.get(name).map(String::as_str)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.snippets.keys().map(String::as_str)
+    }
+}
+
+impl Default for SnippetLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A line in one of the original, unexpanded sources.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Maps every line of an expanded source back to where it came from.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    lines: Vec<SourceLocation>,
+}
+
+impl SourceMap {
+    /// Looks up the original location of a 1-based line in the expanded source.
+    pub fn lookup(&self, line: u32) -> Option<&SourceLocation> {
+        if line == 0 {
+            return None;
+        }
+        self.lines.get(line as usize - 1)
+    }
+
+    /// Rewrites the `0:<line>:` locations in a driver info log, such as
+    /// `ERROR: 0:12: 'foo' : undeclared identifier`, to `<file>:<line>:`
+    /// locations in the original sources.
+    pub fn remap_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| self.remap_log_line(line))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn remap_log_line(&self, line: &str) -> String {
+        let bytes = line.as_bytes();
+
+        for start in 0..bytes.len() {
+            if start > 0 && bytes[start - 1].is_ascii_digit() {
+                continue;
+            }
+
+            let string_end = digits_end(bytes, start);
+            if string_end == start || bytes.get(string_end) != Some(&b':') {
+                continue;
+            }
+
+            let line_end = digits_end(bytes, string_end + 1);
+            if line_end == string_end + 1 || bytes.get(line_end) != Some(&b':') {
+                continue;
+            }
+
+            let expanded_line: u32 = line[string_end + 1..line_end].parse().unwrap_or(0);
+            return match self.lookup(expanded_line) {
+                Some(location) => format!("{}{}:{}{}", &line[..start], location.file, location.line, &line[line_end..]),
+                None => line.to_string(),
+            };
+        }
+
+        line.to_string()
+    }
+}
+
+fn digits_end(bytes: &[u8], start: usize) -> usize {
+    let mut end = start;
+    while end < bytes.len() && bytes[end].is_ascii_digit() {
+        end += 1;
+    }
+    end
+}
+
+/// The output of the preprocessor, ready to hand to `shader::load`.
+#[derive(Debug, Clone)]
+pub struct Preprocessed {
+    pub source: String,
+    pub source_map: SourceMap,
+}
+
+/// Expands `#include` directives from a snippet library and injects `#define`s,
+/// keeping track of where every output line originated.
+///
+/// Each snippet is included at most once per shader, so snippets that include
+/// each other don't produce duplicate definitions.
+pub struct Preprocessor<'a> {
+    library: &'a SnippetLibrary,
+    defines: Vec<(String, String)>,
+}
+
+impl<'a> Preprocessor<'a> {
+    pub fn new(library: &'a SnippetLibrary) -> Self {
+        Self { library, defines: Vec::new() }
+    }
+
+    /// Injects `#define <name> <value>` at the top of every processed shader,
+    /// after the `#version` directive if there is one.
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Expands a shader source.
+    ///
+    /// ## Arguments
+    ///
+    /// * `file` - Name used for the source in error messages, e.g. `fragment`.
+    /// * `source` - Shader source code.
+    pub fn process(&self, file: &str, source: &str) -> Result<Preprocessed, String> {
+        let mut output = Output::default();
+        let mut included = Vec::new();
+        let mut lines = source.lines().enumerate().peekable();
+
+        // `#version` has to stay the very first line, so defines go after it.
+        while let Some((index, line)) = lines.peek() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                output.push(line, file, *index as u32 + 1);
+                lines.next();
+            } else {
+                if trimmed.starts_with("#version") {
+                    output.push(line, file, *index as u32 + 1);
+                    lines.next();
+                }
+                break;
+            }
+        }
+
+        for (name, value) in &self.defines {
+            output.push(&format!("#define {} {}", name, value), "<defines>", 0);
+        }
+
+        let rest: Vec<(usize, &str)> = lines.collect();
+        self.expand(file, rest, &mut output, &mut included, &mut vec![file.to_string()])?;
+
+        Ok(Preprocessed {
+            source: output.lines.join("\n"),
+            source_map: SourceMap { lines: output.locations },
+        })
+    }
+
+    fn expand(
+        &self,
+        file: &str,
+        lines: Vec<(usize, &str)>,
+        output: &mut Output,
+        included: &mut Vec<String>,
+        stack: &mut Vec<String>,
+    ) -> Result<(), String> {
+        for (index, line) in lines {
+            let number = index as u32 + 1;
+
+            let name = match parse_include(line) {
+                Some(Ok(name)) => name,
+                Some(Err(error)) => return Err(format!("{}:{}: {}", file, number, error)),
+                None => {
+                    output.push(line, file, number);
+                    continue;
+                }
+            };
+
+            if stack.iter().any(|entry| entry == name) {
+                return Err(format!("{}:{}: `{}` includes itself through {}", file, number, name, stack.join(" -> ")));
+            }
+
+            if included.iter().any(|entry| entry == name) {
+                // Keep the line count stable so locations stay easy to follow.
+                output.push("", file, number);
+                continue;
+            }
+
+            let snippet = self.library
+                .get(name)
+                .ok_or_else(|| format!("{}:{}: unknown include `{}`", file, number, name))?;
+
+            included.push(name.to_string());
+            stack.push(name.to_string());
+            self.expand(name, snippet.lines().enumerate().collect(), output, included, stack)?;
+            stack.pop();
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Output {
+    lines: Vec<String>,
+    locations: Vec<SourceLocation>,
+}
+
+impl Output {
+    fn push(&mut self, line: &str, file: &str, number: u32) {
+        self.lines.push(line.to_string());
+        self.locations.push(SourceLocation { file: file.to_string(), line: number });
+    }
+}
+
+/// Recognizes `#include "name"` and `#include <name>`, returning `None` for
+/// any other line.
+fn parse_include(line: &str) -> Option<Result<&str, String>> {
+    let directive = line.trim_start();
+    if !directive.starts_with('#') {
+        return None;
+    }
+
+    let directive = directive[1..].trim_start();
+    if !directive.starts_with("include") {
+        return None;
+    }
+
+    let argument = directive["include".len()..].trim();
+    let (open, close) = match argument.chars().next() {
+        Some('"') => ('"', '"'),
+        Some('<') => ('<', '>'),
+        _ => return Some(Err(String::from("expected `#include \"name\"`"))),
+    };
+
+    let argument = &argument[open.len_utf8()..];
+    match argument.find(close) {
+        Some(end) if end > 0 => Some(Ok(&argument[..end])),
+        _ => Some(Err(format!("unterminated include, expected a closing `{}`", close))),
+    }
+}
+
+/// The names a source switches on with `#ifdef`, `#ifndef` or `defined`,
+/// in the order they first appear, so they can be offered as toggles.
+///
+/// Names the source `#define`s itself, like include guards, and the `GL_`
+/// names the compiler defines are left out.
+pub fn toggles(source: &str) -> Vec<String> {
+    let mut tested: Vec<String> = Vec::new();
+    let mut defined: Vec<String> = Vec::new();
+
+    for line in source.lines() {
+        let directive = line.trim_start();
+        if !directive.starts_with('#') {
+            continue;
+        }
+
+        let directive = directive[1..].trim_start();
+        let keyword_end = directive.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(directive.len());
+        let (keyword, rest) = directive.split_at(keyword_end);
+        match keyword {
+            "ifdef" | "ifndef" => tested.extend(identifier(rest.trim_start())),
+            "if" | "elif" => {
+                let mut rest = rest;
+                while let Some(start) = rest.find("defined") {
+                    rest = rest[start + "defined".len()..].trim_start();
+                    tested.extend(identifier(rest.trim_start_matches('(').trim_start()));
+                }
+            }
+            "define" => defined.extend(identifier(rest.trim_start())),
+            _ => (),
+        }
+    }
+
+    let mut toggles: Vec<String> = Vec::new();
+    for name in tested {
+        if !name.starts_with("GL_") && !defined.contains(&name) && !toggles.contains(&name) {
+            toggles.push(name);
+        }
+    }
+    toggles
+}
+
+/// The identifier at the start of a string, if there is one.
+fn identifier(text: &str) -> Option<String> {
+    let end = text.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(text.len());
+    match text[..end].chars().next() {
+        Some(first) if !first.is_ascii_digit() => Some(text[..end].to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(snippets: &[(&str, &str)]) -> SnippetLibrary {
+        let mut library = SnippetLibrary { snippets: BTreeMap::new() };
+        for (name, source) in snippets {
+            library.insert(name, source);
+        }
+        library
+    }
+
+    #[test]
+    fn includes_each_snippet_once() {
+        let library = library(&[("a.glsl", "#include \"b.glsl\"\nfloat a;"), ("b.glsl", "float b;")]);
+        let output = Preprocessor::new(&library)
+            .process("fragment", "#include \"b.glsl\"\n#include \"a.glsl\"\nvoid main() {}")
+            .unwrap();
+
+        assert_eq!(output.source.matches("float b;").count(), 1);
+        assert_eq!(output.source, "float b;\n\nfloat a;\nvoid main() {}");
+    }
+
+    #[test]
+    fn built_in_snippets_resolve_their_includes() {
+        let library = SnippetLibrary::new();
+        let output = Preprocessor::new(&library).process("fragment", "#include \"noise.glsl\"").unwrap();
+
+        assert!(output.source.contains("float hash12("));
+        assert!(output.source.contains("float valueNoise("));
+    }
+
+    #[test]
+    fn detects_include_cycles() {
+        let library = library(&[("a.glsl", "#include \"b.glsl\""), ("b.glsl", "\n#include \"a.glsl\"")]);
+        let error = Preprocessor::new(&library).process("fragment", "#include \"a.glsl\"").unwrap_err();
+
+        assert_eq!(error, "b.glsl:2: `a.glsl` includes itself through fragment -> a.glsl -> b.glsl");
+    }
+
+    #[test]
+    fn reports_unknown_and_malformed_includes() {
+        let library = library(&[]);
+        let preprocessor = Preprocessor::new(&library);
+
+        assert_eq!(
+            preprocessor.process("vertex", "\n#include \"missing.glsl\"").unwrap_err(),
+            "vertex:2: unknown include `missing.glsl`"
+        );
+        assert!(preprocessor.process("vertex", "#include missing.glsl").is_err());
+        assert!(preprocessor.process("vertex", "#include \"missing.glsl").is_err());
+    }
+
+    #[test]
+    fn injects_defines_after_the_version() {
+        let library = library(&[]);
+        let output = Preprocessor::new(&library)
+            .define("SHADOWS", "1")
+            .process("fragment", "#version 300 es\nvoid main() {}")
+            .unwrap();
+
+        assert_eq!(output.source, "#version 300 es\n#define SHADOWS 1\nvoid main() {}");
+        assert_eq!(output.source_map.lookup(2).unwrap().file, "<defines>");
+        assert_eq!(output.source_map.lookup(3), Some(&SourceLocation { file: "fragment".to_string(), line: 2 }));
+    }
+
+    #[test]
+    fn remaps_log_lines_to_the_original_sources() {
+        let library = library(&[("a.glsl", "float a;\nfloat oops = x;")]);
+        let output = Preprocessor::new(&library)
+            .process("fragment", "precision mediump float;\n#include \"a.glsl\"\nvoid main() {}")
+            .unwrap();
+
+        let log = "ERROR: 0:3: 'x' : undeclared identifier\nERROR: 0:4: 'main' : bad\nERROR: 2 compilation errors";
+        assert_eq!(
+            output.source_map.remap_log(log),
+            "ERROR: a.glsl:2: 'x' : undeclared identifier\nERROR: fragment:3: 'main' : bad\nERROR: 2 compilation errors"
+        );
+    }
+
+    #[test]
+    fn leaves_lines_past_the_end_alone() {
+        let library = library(&[]);
+        let output = Preprocessor::new(&library).process("fragment", "void main() {}").unwrap();
+
+        assert_eq!(output.source_map.remap_log("ERROR: 0:7: oops"), "ERROR: 0:7: oops");
+        assert_eq!(output.source_map.lookup(0), None);
+    }
+
+    #[test]
+    fn finds_toggles() {
+        let source = "\
+#ifndef HELPERS
+#define HELPERS
+#endif
+#ifdef SHADOWS
+#if defined(FOG) && !defined REFLECTIONS || defined(GL_ES)
+#elif defined( SHADOWS )
+#endif
+#endif";
+
+        assert_eq!(toggles(source), vec!["SHADOWS", "FOG", "REFLECTIONS"]);
+    }
+}
@@ -0,0 +1,48 @@
+#include "hash.glsl"
+
+// Value noise in [0, 1].
+float valueNoise(vec2 p) {
+    vec2 i = floor(p);
+    vec2 f = fract(p);
+    vec2 u = f * f * (3.0 - 2.0 * f);
+
+    float a = hash12(i);
+    float b = hash12(i + vec2(1.0, 0.0));
+    float c = hash12(i + vec2(0.0, 1.0));
+    float d = hash12(i + vec2(1.0, 1.0));
+
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+// Gradient noise in [-1, 1].
+float gradientNoise(vec2 p) {
+    vec2 i = floor(p);
+    vec2 f = fract(p);
+    vec2 u = f * f * (3.0 - 2.0 * f);
+
+    vec2 ga = hash22(i) * 2.0 - 1.0;
+    vec2 gb = hash22(i + vec2(1.0, 0.0)) * 2.0 - 1.0;
+    vec2 gc = hash22(i + vec2(0.0, 1.0)) * 2.0 - 1.0;
+    vec2 gd = hash22(i + vec2(1.0, 1.0)) * 2.0 - 1.0;
+
+    float a = dot(ga, f);
+    float b = dot(gb, f - vec2(1.0, 0.0));
+    float c = dot(gc, f - vec2(0.0, 1.0));
+    float d = dot(gd, f - vec2(1.0, 1.0));
+
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+// Fractal brownian motion over gradient noise, five octaves.
+float fbm(vec2 p) {
+    float value = 0.0;
+    float amplitude = 0.5;
+
+    for (int i = 0; i < 5; i++) {
+        value += amplitude * gradientNoise(p);
+        p *= 2.0;
+        amplitude *= 0.5;
+    }
+
+    return value;
+}
@@ -0,0 +1,44 @@
+// Signed distance primitives and operators.
+// https://iquilezles.org/articles/distfunctions/
+
+float sdSphere(vec3 p, float radius) {
+    return length(p) - radius;
+}
+
+float sdBox(vec3 p, vec3 size) {
+    vec3 q = abs(p) - size;
+    return length(max(q, 0.0)) + min(max(q.x, max(q.y, q.z)), 0.0);
+}
+
+float sdTorus(vec3 p, vec2 radii) {
+    vec2 q = vec2(length(p.xz) - radii.x, p.y);
+    return length(q) - radii.y;
+}
+
+float sdCapsule(vec3 p, vec3 a, vec3 b, float radius) {
+    vec3 pa = p - a;
+    vec3 ba = b - a;
+    float h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    return length(pa - ba * h) - radius;
+}
+
+float sdPlane(vec3 p, vec3 normal, float height) {
+    return dot(p, normal) + height;
+}
+
+float opUnion(float a, float b) {
+    return min(a, b);
+}
+
+float opSubtraction(float a, float b) {
+    return max(-a, b);
+}
+
+float opIntersection(float a, float b) {
+    return max(a, b);
+}
+
+float opSmoothUnion(float a, float b, float k) {
+    float h = clamp(0.5 + 0.5 * (b - a) / k, 0.0, 1.0);
+    return mix(b, a, h) - k * h * (1.0 - h);
+}
@@ -0,0 +1,648 @@
+use super::diagnostic::Stage;
+use super::lexer::{tokenize, TokenKind};
+use super::parser::{match_close, Shader, Variable, Version};
+
+/// GLSL ES 1.00 extensions the translator knows about, by their GLSL name and
+/// the name WebGL enables them under.
+pub const EXTENSIONS: &[(&str, &str)] = &[
+    ("GL_OES_standard_derivatives", "OES_standard_derivatives"),
+    ("GL_EXT_shader_texture_lod", "EXT_shader_texture_lod"),
+    ("GL_EXT_frag_depth", "EXT_frag_depth"),
+    ("GL_EXT_draw_buffers", "WEBGL_draw_buffers"),
+];
+
+const DERIVATIVES: &[&str] = &["dFdx", "dFdy", "fwidth"];
+
+/// Names that are free to use in GLSL ES 1.00 but are keywords or the names
+/// of texture functions in GLSL ES 3.00, so they are renamed on the way up.
+///
+/// Uniforms, attributes and varyings keep their names, they're looked up by
+/// name from outside the shader.
+const RESERVED_ES300: &[&str] = &[
+    "layout", "centroid", "flat", "smooth", "uint", "uvec2", "uvec3", "uvec4",
+    "mat2x2", "mat2x3", "mat2x4", "mat3x2", "mat3x3", "mat3x4", "mat4x2", "mat4x3", "mat4x4",
+    "sampler3D", "sampler2DShadow", "samplerCubeShadow", "sampler2DArray", "sampler2DArrayShadow",
+    "isampler2D", "isampler3D", "isamplerCube", "isampler2DArray",
+    "usampler2D", "usampler3D", "usamplerCube", "usampler2DArray",
+    "texture", "textureProj", "textureLod", "textureProjLod", "textureGrad", "textureProjGrad",
+    "textureSize", "texelFetch",
+];
+
+/// GLSL ES 3.00 sampler types with nothing to sample them through in GLSL ES
+/// 1.00, so shaders using them can't be translated down.
+const SAMPLERS_ES300: &[&str] = &[
+    "sampler3D", "sampler2DShadow", "samplerCubeShadow", "sampler2DArray", "sampler2DArrayShadow",
+    "isampler2D", "isampler3D", "isamplerCube", "isampler2DArray",
+    "usampler2D", "usampler3D", "usamplerCube", "usampler2DArray",
+];
+
+/// GLSL ES 1.00 texture functions and the GLSL ES 3.00 function replacing them.
+const TEXTURE_FUNCTIONS_ES100: &[(&str, &str)] = &[
+    ("texture2D", "texture"),
+    ("textureCube", "texture"),
+    ("texture2DProj", "textureProj"),
+    ("texture2DLod", "textureLod"),
+    ("textureCubeLod", "textureLod"),
+    ("texture2DProjLod", "textureProjLod"),
+    ("texture2DLodEXT", "textureLod"),
+    ("textureCubeLodEXT", "textureLod"),
+    ("texture2DProjLodEXT", "textureProjLod"),
+    ("texture2DGradEXT", "textureGrad"),
+    ("textureCubeGradEXT", "textureGrad"),
+    ("texture2DProjGradEXT", "textureProjGrad"),
+];
+
+const FRAG_COLOR: &str = "netshader_FragColor";
+const FRAG_DATA: &str = "netshader_FragData";
+
+/// A shader rewritten for a GLSL ES version.
+#[derive(Debug, Clone)]
+pub struct Translation {
+    pub source: String,
+    /// WebGL extensions that have to be enabled before the source compiles,
+    /// e.g. `OES_standard_derivatives`.
+    pub extensions: Vec<&'static str>,
+    /// What keeps the source from compiling on the target that the
+    /// translation can't fix, by line in the source.
+    pub errors: Vec<(u32, String)>,
+}
+
+impl Translation {
+    /// `errors` worded like a driver's log, so they're located the same way,
+    /// or `None` if there are none.
+    pub fn error_log(&self) -> Option<String> {
+        if self.errors.is_empty() {
+            return None;
+        }
+
+        let log: Vec<String> = self.errors
+            .iter()
+            .map(|(line, message)| format!("ERROR: 0:{}: {}", line, message))
+            .collect();
+        Some(log.join("\n"))
+    }
+}
+
+/// Rewrites a shader for the GLSL ES version a context compiles, so the same
+/// source works on WebGL1 and WebGL2.
+///
+/// Going up to 3.00, `attribute` and `varying` become `in` and `out`,
+/// `gl_FragColor` and `gl_FragData[n]` become declared outputs and the
+/// `texture2D` family becomes `texture`. Going down to 1.00 it's the reverse,
+/// with the sampler's type picking between `texture2D` and `textureCube` and
+/// extension directives added for whatever used to be core.
+///
+/// Line numbers are kept stable, with `#line` where lines have to be added,
+/// so driver errors still point at the original source. Sources already
+/// written for the target are returned unchanged.
+///
+/// Names GLSL ES 3.00 reserves are renamed on the way up, except for
+/// uniforms, attributes and varyings, which are reported in `errors`. On the
+/// way down, so are samplers like `sampler3D` that GLSL ES 1.00 lacks.
+///
+/// ## Arguments
+///
+/// * `source` - Shader source code.
+/// * `stage` - Which stage the source is for.
+/// * `target` - The version the context compiles, see `shader::shading_language`.
+pub fn translate(source: &str, stage: Stage, target: Version) -> Translation {
+    let shader = Shader::parse(source);
+
+    let (source, errors) = match (shader.version, target) {
+        (Version::Es100, Version::Es300) => (Rewriter::new(&shader).upgrade(stage), reserved_interface(&shader)),
+        (Version::Es300, Version::Es100) => (Rewriter::new(&shader).downgrade(stage), unsupported_samplers(&shader)),
+        _ => (source.to_string(), Vec::new()),
+    };
+
+    let extensions = tokenize(&source)
+        .iter()
+        .filter(|token| token.kind == TokenKind::Directive)
+        .filter_map(|token| match token.text[1..].split_whitespace().collect::<Vec<&str>>().as_slice() {
+            ["extension", name, ..] => EXTENSIONS.iter().find(|(glsl, _)| glsl == name).map(|(_, webgl)| *webgl),
+            _ => None,
+        })
+        .collect();
+
+    Translation { source, extensions, errors }
+}
+
+/// `uniform`, `attribute` or `varying` for the variables of a GLSL ES 1.00
+/// shader's interface.
+fn interface_qualifier(variable: &Variable) -> Option<&'static str> {
+    ["uniform", "attribute", "varying"].iter().find(|qualifier| variable.has_qualifier(qualifier)).cloned()
+}
+
+/// Uniforms, attributes and varyings named after something GLSL ES 3.00
+/// reserves.
+fn reserved_interface(shader: &Shader) -> Vec<(u32, String)> {
+    shader.globals
+        .iter()
+        .filter(|variable| RESERVED_ES300.contains(&variable.name.as_str()))
+        .filter_map(|variable| {
+            let message = format!(
+                "`{}` is reserved in GLSL ES 3.00, rename the {} so the shader also compiles on WebGL2",
+                variable.name,
+                interface_qualifier(variable)?
+            );
+            Some((variable.line, message))
+        })
+        .collect()
+}
+
+/// Declarations using a sampler type GLSL ES 1.00 doesn't have, once per
+/// line and type.
+fn unsupported_samplers(shader: &Shader) -> Vec<(u32, String)> {
+    let mut errors: Vec<(u32, String)> = Vec::new();
+
+    for token in shader.tokens.iter().filter(|token| SAMPLERS_ES300.contains(&token.text)) {
+        let error = (token.line, format!("`{}` needs WebGL2, GLSL ES 1.00 has no way to sample it", token.text));
+        if !errors.contains(&error) {
+            errors.push(error);
+        }
+    }
+
+    errors
+}
+
+/// A global declaration, as a range of positions in `Shader::significant`.
+struct Statement {
+    start: usize,
+    end: usize,
+}
+
+/// Replaces the text of individual tokens while keeping everything else,
+/// comments and layout included, as it was.
+struct Rewriter<'s, 'a> {
+    shader: &'s Shader<'a>,
+    significant: Vec<usize>,
+    output: Vec<String>,
+}
+
+impl<'s, 'a> Rewriter<'s, 'a> {
+    fn new(shader: &'s Shader<'a>) -> Self {
+        Self {
+            shader,
+            significant: shader.significant(),
+            output: shader.tokens.iter().map(|token| token.text.to_string()).collect(),
+        }
+    }
+
+    fn text(&self, position: usize) -> &'a str {
+        self.significant.get(position).map_or("", |&index| self.shader.tokens[index].text)
+    }
+
+    fn replace(&mut self, position: usize, text: &str) {
+        self.output[self.significant[position]] = text.to_string();
+    }
+
+    /// Positions of the identifiers naming a global variable, leaving out its
+    /// declaration.
+    fn references(&self, name: &str) -> Vec<usize> {
+        let tokens = self.shader.references(name);
+        self.significant
+            .iter()
+            .enumerate()
+            .filter(|&(_, index)| tokens.contains(index))
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    /// Global declarations ending in `;`, skipping over function bodies.
+    fn statements(&self) -> Vec<Statement> {
+        let mut statements = Vec::new();
+        let mut start = 0;
+        let mut depth = 0;
+
+        for position in 0..self.significant.len() {
+            match self.text(position) {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" => depth -= 1,
+                "}" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        start = position + 1;
+                    }
+                }
+                ";" if depth == 0 => {
+                    statements.push(Statement { start, end: position });
+                    start = position + 1;
+                }
+                _ => {}
+            }
+        }
+
+        statements
+    }
+
+    /// Position of the first token of the first function definition, where
+    /// new global declarations can go without moving any lines.
+    fn first_function(&self) -> usize {
+        let name = self.shader.functions
+            .iter()
+            .filter(|function| function.body.is_some())
+            .filter_map(|function| self.significant.iter().position(|&index| index == function.token))
+            .min()
+            .unwrap_or(0);
+
+        // Walk back over the return type and qualifiers to the end of the
+        // previous declaration or function.
+        let mut start = name;
+        while start > 0 && self.text(start - 1) != ";" && self.text(start - 1) != "}" {
+            start -= 1;
+        }
+        start
+    }
+
+    fn finish(self) -> String {
+        self.output.concat()
+    }
+
+    /// GLSL ES 1.00 to 3.00.
+    fn upgrade(mut self, stage: Stage) -> String {
+        let mut outputs: Vec<Option<String>> = Vec::new();
+        let mut has_version = false;
+
+        for index in 0..self.shader.tokens.len() {
+            let token = &self.shader.tokens[index];
+            if token.kind != TokenKind::Directive {
+                continue;
+            }
+
+            match token.text[1..].split_whitespace().collect::<Vec<&str>>().as_slice() {
+                // `#version` has to be on the very first line in GLSL ES 3.00.
+                ["version", ..] if token.line == 1 => {
+                    self.output[index] = String::from("#version 300 es");
+                    has_version = true;
+                }
+                ["version", ..] => self.output[index] = String::new(),
+                ["extension", name, ..] if EXTENSIONS.iter().any(|(glsl, _)| glsl == name) => {
+                    // Core in GLSL ES 3.00, where the directive would be an error.
+                    self.output[index] = String::new();
+                }
+                _ => {}
+            }
+        }
+
+        let mut position = 0;
+        while position < self.significant.len() {
+            let text = self.text(position);
+
+            match text {
+                "attribute" => self.replace(position, "in"),
+                "varying" => self.replace(position, if stage == Stage::Vertex { "out" } else { "in" }),
+                "gl_FragDepthEXT" => self.replace(position, "gl_FragDepth"),
+                "gl_FragColor" if stage == Stage::Fragment => {
+                    self.replace(position, FRAG_COLOR);
+                    if !outputs.iter().any(|output| output.is_none()) {
+                        outputs.push(None);
+                    }
+                }
+                "gl_FragData" if stage == Stage::Fragment && self.text(position + 1) == "[" && self.text(position + 3) == "]" => {
+                    let location = self.text(position + 2);
+                    if location.parse::<u32>().is_ok() {
+                        self.replace(position, &format!("{}{}", FRAG_DATA, location));
+                        for offset in 1..4 {
+                            self.replace(position + offset, "");
+                        }
+                        if !outputs.iter().any(|output| output.as_ref().map(String::as_str) == Some(location)) {
+                            outputs.push(Some(location.to_string()));
+                        }
+                        position += 3;
+                    }
+                }
+                _ => {
+                    if let Some((_, replacement)) = TEXTURE_FUNCTIONS_ES100.iter().find(|(name, _)| *name == text) {
+                        self.replace(position, replacement);
+                    } else if RESERVED_ES300.contains(&text) && self.shader.global(text).and_then(interface_qualifier).is_none() {
+                        self.replace(position, &format!("{}_", text));
+                    }
+                }
+            }
+
+            position += 1;
+        }
+
+        if !outputs.is_empty() {
+            let declarations: String = outputs
+                .iter()
+                .map(|output| match output {
+                    None => format!("out highp vec4 {}; ", FRAG_COLOR),
+                    Some(location) => format!("layout(location = {}) out highp vec4 {}{}; ", location, FRAG_DATA, location),
+                })
+                .collect();
+
+            let index = self.significant[self.first_function()];
+            self.output[index] = format!("{}{}", declarations, self.output[index]);
+        }
+
+        let source = self.finish();
+        if has_version {
+            source
+        } else {
+            // In GLSL ES 3.00 `#line 1` numbers the line after it 1.
+            format!("#version 300 es\n#line 1\n{}", source)
+        }
+    }
+
+    /// GLSL ES 3.00 to 1.00.
+    fn downgrade(mut self, stage: Stage) -> String {
+        let mut extensions: Vec<&str> = Vec::new();
+        let mut outputs: Vec<(String, String)> = Vec::new();
+
+        for statement in self.statements() {
+            let mut position = statement.start;
+            let mut location = String::from("0");
+            let mut output = false;
+
+            loop {
+                match self.text(position) {
+                    "layout" if self.text(position + 1) == "(" => {
+                        let close = match_close(&self.shader.tokens, &self.significant, position + 1);
+                        for inner in position + 1..close {
+                            if self.text(inner) == "location" && self.text(inner + 1) == "=" {
+                                location = self.text(inner + 2).to_string();
+                            }
+                        }
+                        for blanked in position..=close {
+                            self.replace(blanked, "");
+                        }
+                        position = close + 1;
+                    }
+                    "flat" | "smooth" | "centroid" => {
+                        self.replace(position, "");
+                        position += 1;
+                    }
+                    "in" => {
+                        self.replace(position, if stage == Stage::Vertex { "attribute" } else { "varying" });
+                        position += 1;
+                    }
+                    "out" if stage == Stage::Vertex => {
+                        self.replace(position, "varying");
+                        position += 1;
+                    }
+                    "out" => {
+                        output = true;
+                        position += 1;
+                    }
+                    "invariant" | "highp" | "mediump" | "lowp" => position += 1,
+                    _ => break,
+                }
+            }
+
+            if output {
+                let name = self.text(statement.end - 1).to_string();
+                for blanked in statement.start..=statement.end {
+                    self.replace(blanked, "");
+                }
+                outputs.push((name, location));
+            }
+        }
+
+        let single = outputs.len() == 1 && outputs[0].1 == "0";
+        for (name, location) in &outputs {
+            let replacement = if single {
+                String::from("gl_FragColor")
+            } else {
+                format!("gl_FragData[{}]", location)
+            };
+            for position in self.references(name) {
+                self.replace(position, &replacement);
+            }
+        }
+        if !outputs.is_empty() && !single {
+            extensions.push("GL_EXT_draw_buffers");
+        }
+
+        for position in 0..self.significant.len() {
+            let text = self.text(position);
+            let fragment = stage == Stage::Fragment;
+
+            let cube = text.starts_with("texture") && self.text(position + 1) == "(" && {
+                let sampler = self.text(position + 2);
+                self.shader
+                    .variable_at(sampler, self.significant[position])
+                    .map_or(false, |variable| variable.type_name == "samplerCube")
+            };
+            let sampler = if cube { "Cube" } else { "2D" };
+            let lod_suffix = if fragment { "EXT" } else { "" };
+
+            let replacement = match text {
+                "texture" => format!("texture{}", sampler),
+                "textureProj" => String::from("texture2DProj"),
+                "textureLod" => format!("texture{}Lod{}", sampler, lod_suffix),
+                "textureProjLod" => format!("texture2DProjLod{}", lod_suffix),
+                "textureGrad" => format!("texture{}GradEXT", sampler),
+                "textureProjGrad" => String::from("texture2DProjGradEXT"),
+                "gl_FragDepth" => {
+                    extensions.push("GL_EXT_frag_depth");
+                    String::from("gl_FragDepthEXT")
+                }
+                _ => {
+                    if fragment && DERIVATIVES.contains(&text) {
+                        extensions.push("GL_OES_standard_derivatives");
+                    }
+                    continue;
+                }
+            };
+
+            if replacement.ends_with("EXT") && replacement.starts_with("texture") {
+                extensions.push("GL_EXT_shader_texture_lod");
+            }
+            self.replace(position, &replacement);
+        }
+
+        extensions.sort();
+        extensions.dedup();
+
+        let version = self.shader.tokens
+            .iter()
+            .position(|token| token.kind == TokenKind::Directive && token.text[1..].trim_start().starts_with("version"));
+
+        if let Some(index) = version {
+            let line = self.shader.tokens[index].line;
+            self.output[index] = if extensions.is_empty() {
+                String::new()
+            } else {
+                // In GLSL ES 1.00 `#line n` numbers the line after it n + 1.
+                let directives: String = extensions
+                    .iter()
+                    .map(|name| format!("#extension {} : enable\n", name))
+                    .collect();
+                format!("{}#line {}", directives, line)
+            };
+        }
+
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERTEX_ES100: &str = "\
+attribute vec4 aPosition;
+varying vec2 vUv;
+void main() {
+    vUv = aPosition.xy;
+    gl_Position = aPosition;
+}";
+
+    const FRAGMENT_ES100: &str = "\
+#extension GL_OES_standard_derivatives : enable
+precision mediump float;
+uniform sampler2D uImage;
+varying vec2 vUv;
+void main() {
+    float flat = fwidth(vUv.x);
+    gl_FragColor = texture2D(uImage, vUv) * flat;
+}";
+
+    const FRAGMENT_ES300: &str = "\
+#version 300 es
+precision mediump float;
+uniform samplerCube uSky;
+uniform sampler2D uImage;
+in vec2 vUv;
+layout(location = 0) out vec4 color;
+void main() {
+    color = texture(uSky, vec3(vUv, 1.0)) + textureLod(uImage, vUv, 2.0) * dFdx(vUv.x);
+}";
+
+    #[test]
+    fn upgrades_the_vertex_interface() {
+        let translation = translate(VERTEX_ES100, Stage::Vertex, Version::Es300);
+
+        assert_eq!(
+            translation.source,
+            "#version 300 es\n#line 1\nin vec4 aPosition;\nout vec2 vUv;\nvoid main() {\n    vUv = aPosition.xy;\n    gl_Position = aPosition;\n}"
+        );
+        assert!(translation.extensions.is_empty());
+        assert!(translation.errors.is_empty());
+    }
+
+    #[test]
+    fn upgrades_fragment_outputs_texture_calls_and_reserved_names() {
+        let translation = translate(FRAGMENT_ES100, Stage::Fragment, Version::Es300);
+
+        assert_eq!(
+            translation.source,
+            "#version 300 es\n#line 1\n\nprecision mediump float;\nuniform sampler2D uImage;\nin vec2 vUv;\n\
+             out highp vec4 netshader_FragColor; void main() {\n    float flat_ = fwidth(vUv.x);\n    \
+             netshader_FragColor = texture(uImage, vUv) * flat_;\n}"
+        );
+        // Derivatives are core in GLSL ES 3.00.
+        assert!(translation.extensions.is_empty());
+    }
+
+    #[test]
+    fn upgrades_frag_data_to_located_outputs() {
+        let source = "precision mediump float;\nvoid main() {\n    gl_FragData[0] = vec4(1.0);\n    gl_FragData[1] = vec4(0.0);\n}";
+        let translation = translate(source, Stage::Fragment, Version::Es300);
+
+        assert!(translation.source.contains(
+            "layout(location = 0) out highp vec4 netshader_FragData0; layout(location = 1) out highp vec4 netshader_FragData1; void main()"
+        ));
+        assert!(translation.source.contains("    netshader_FragData1 = vec4(0.0);"));
+    }
+
+    #[test]
+    fn reports_reserved_interface_names_instead_of_renaming_them() {
+        let source = "precision mediump float;\nuniform sampler2D texture;\nvarying vec2 sampler3D;\nvoid main() {\n    gl_FragColor = texture2D(texture, sampler3D);\n}";
+        let translation = translate(source, Stage::Fragment, Version::Es300);
+
+        assert_eq!(
+            translation.errors,
+            vec![
+                (2, String::from("`texture` is reserved in GLSL ES 3.00, rename the uniform so the shader also compiles on WebGL2")),
+                (3, String::from("`sampler3D` is reserved in GLSL ES 3.00, rename the varying so the shader also compiles on WebGL2")),
+            ]
+        );
+        assert!(translation.source.contains("uniform sampler2D texture;"));
+        assert!(!translation.source.contains("texture_"));
+    }
+
+    #[test]
+    fn reserved_names_are_fine_in_es100() {
+        let source = "precision mediump float;\nuniform sampler2D texture;\nvoid main() {\n    gl_FragColor = texture2D(texture, vec2(0.5));\n}";
+        let translation = translate(source, Stage::Fragment, Version::Es100);
+
+        assert_eq!(translation.source, source);
+        assert!(translation.errors.is_empty());
+    }
+
+    #[test]
+    fn downgrades_the_vertex_interface() {
+        let source = "#version 300 es\nin vec4 aPosition;\nflat out vec2 vUv;\nvoid main() {\n    vUv = aPosition.xy;\n    gl_Position = aPosition;\n}";
+        let translation = translate(source, Stage::Vertex, Version::Es100);
+
+        assert_eq!(
+            translation.source,
+            "\nattribute vec4 aPosition;\n varying vec2 vUv;\nvoid main() {\n    vUv = aPosition.xy;\n    gl_Position = aPosition;\n}"
+        );
+    }
+
+    #[test]
+    fn downgrades_outputs_samplers_and_extensions() {
+        // The output's declaration is blanked, leaving the spaces between its tokens.
+        let translation = translate(FRAGMENT_ES300, Stage::Fragment, Version::Es100);
+
+        assert_eq!(
+            translation.source,
+            "#extension GL_EXT_shader_texture_lod : enable\n#extension GL_OES_standard_derivatives : enable\n#line 1\n\
+             precision mediump float;\nuniform samplerCube uSky;\nuniform sampler2D uImage;\nvarying vec2 vUv;\n     \n\
+             void main() {\n    gl_FragColor = textureCube(uSky, vec3(vUv, 1.0)) + texture2DLodEXT(uImage, vUv, 2.0) * dFdx(vUv.x);\n}"
+        );
+        assert_eq!(translation.extensions, vec!["EXT_shader_texture_lod", "OES_standard_derivatives"]);
+    }
+
+    #[test]
+    fn reports_samplers_es100_lacks() {
+        let source = "#version 300 es\nprecision mediump float;\nprecision mediump sampler3D;\nuniform sampler3D uVolume;\nin vec3 vUvw;\nout vec4 color;\n\
+                      void main() {\n    color = texture(uVolume, vUvw);\n}";
+        let translation = translate(source, Stage::Fragment, Version::Es100);
+
+        assert_eq!(
+            translation.errors,
+            vec![
+                (3, String::from("`sampler3D` needs WebGL2, GLSL ES 1.00 has no way to sample it")),
+                (4, String::from("`sampler3D` needs WebGL2, GLSL ES 1.00 has no way to sample it")),
+            ]
+        );
+        assert_eq!(
+            translation.error_log().unwrap().lines().last(),
+            Some("ERROR: 0:4: `sampler3D` needs WebGL2, GLSL ES 1.00 has no way to sample it")
+        );
+    }
+
+    #[test]
+    fn downgrades_several_outputs_to_frag_data() {
+        let source = "#version 300 es\nprecision mediump float;\nlayout(location = 0) out vec4 color;\nlayout(location = 1) out vec4 normal;\nvoid main() {\n    color = vec4(1.0);\n    normal = vec4(0.0);\n}";
+        let translation = translate(source, Stage::Fragment, Version::Es100);
+
+        assert!(translation.source.contains("    gl_FragData[0] = vec4(1.0);\n    gl_FragData[1] = vec4(0.0);"));
+        assert_eq!(translation.extensions, vec!["WEBGL_draw_buffers"]);
+    }
+
+    #[test]
+    fn keeps_line_numbers() {
+        for (source, stage) in &[(VERTEX_ES100, Stage::Vertex), (FRAGMENT_ES100, Stage::Fragment)] {
+            let translation = translate(source, *stage, Version::Es300);
+            // Everything after `#version 300 es` and `#line 1`.
+            assert_eq!(translation.source.lines().count() - 2, source.lines().count());
+        }
+
+        let translation = translate(FRAGMENT_ES300, Stage::Fragment, Version::Es100);
+        let main = translation.source.lines().position(|line| line.starts_with("void main")).unwrap();
+        // `#line 1` makes the line after it 2, like `void main` is 7 in the original.
+        let line_directive = translation.source.lines().position(|line| line == "#line 1").unwrap();
+        assert_eq!(main - line_directive + 1, 7);
+    }
+
+    #[test]
+    fn leaves_sources_written_for_the_target_alone() {
+        assert_eq!(translate(VERTEX_ES100, Stage::Vertex, Version::Es100).source, VERTEX_ES100);
+        assert_eq!(translate(FRAGMENT_ES300, Stage::Fragment, Version::Es300).source, FRAGMENT_ES300);
+        assert_eq!(translate(FRAGMENT_ES100, Stage::Fragment, Version::Es100).extensions, vec!["OES_standard_derivatives"]);
+    }
+}
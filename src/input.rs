@@ -0,0 +1,271 @@
+use nalgebra_glm::{self as glm, Vec4};
+
+use crate::resources::Description;
+use crate::webgl_rendering_context::WebGLRenderingContext;
+
+/// Key codes the keyboard texture has a column for, as in
+/// `KeyboardEvent.keyCode`.
+pub const KEYS: usize = 256;
+
+/// Rows of the keyboard texture.
+const DOWN: usize = 0;
+const PRESSED: usize = 1;
+const TOGGLED: usize = 2;
+
+/// The mouse as Shadertoy's `iMouse` describes it, in canvas pixels with the
+/// origin at the bottom left.
+#[derive(Debug, Clone, Default)]
+pub struct Mouse {
+    /// Where the pointer was the last time it moved with a button held.
+    position: (f32, f32),
+    /// Where the button was last pressed.
+    click: (f32, f32),
+    down: bool,
+    /// Whether the button was pressed since the last frame.
+    clicked: bool,
+}
+
+impl Mouse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn press(&mut self, x: f32, y: f32) {
+        self.position = (x, y);
+        self.click = (x, y);
+        self.down = true;
+        self.clicked = true;
+    }
+
+    /// Follows the pointer, only while a button is held like Shadertoy does.
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        if self.down {
+            self.position = (x, y);
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.down = false;
+    }
+
+    /// The value of `iMouse`: xy is the position, zw where the button was
+    /// pressed. z is negative once the button is released and w only
+    /// positive in the frame right after the press, so shaders can tell a
+    /// click from a drag.
+    ///
+    /// ## Arguments
+    ///
+    /// * `scale` - Size of the rendered image relative to the canvas.
+    pub fn uniform(&self, scale: f32) -> Vec4 {
+        let (x, y) = (self.position.0 * scale, self.position.1 * scale);
+        let (click_x, click_y) = (self.click.0 * scale, self.click.1 * scale);
+        glm::vec4(
+            x,
+            y,
+            if self.down { click_x } else { -click_x },
+            if self.clicked { click_y } else { -click_y },
+        )
+    }
+
+    /// Marks the press as seen, call after every frame.
+    pub fn end_frame(&mut self) {
+        self.clicked = false;
+    }
+}
+
+/// State of every key, laid out as Shadertoy's keyboard texture: 256 texels
+/// wide, one per key code, with the row at y = 0 set while a key is down,
+/// y = 1 only in the frame right after it was pressed and y = 2 flipping with
+/// every press.
+#[derive(Debug, Clone)]
+pub struct Keyboard {
+    /// One byte per texel, 0 or 255, row by row.
+    state: Vec<u8>,
+    /// Whether `state` changed since the texture was last updated.
+    changed: bool,
+}
+
+impl Keyboard {
+    pub fn new() -> Self {
+        Self { state: vec![0; KEYS * 3], changed: true }
+    }
+
+    pub fn press(&mut self, key: u8) {
+        // Held keys repeat, that's not a new press.
+        if self.get(DOWN, key) {
+            return;
+        }
+
+        let toggled = self.get(TOGGLED, key);
+        self.set(DOWN, key, true);
+        self.set(PRESSED, key, true);
+        self.set(TOGGLED, key, !toggled);
+    }
+
+    pub fn release(&mut self, key: u8) {
+        self.set(DOWN, key, false);
+    }
+
+    /// Releases every key, e.g. when the canvas loses focus and would never
+    /// hear about the keys going up.
+    pub fn release_all(&mut self) {
+        for key in 0..KEYS {
+            self.set(DOWN, key as u8, false);
+        }
+    }
+
+    /// Marks presses as seen, call after every frame.
+    pub fn end_frame(&mut self) {
+        for key in 0..KEYS {
+            self.set(PRESSED, key as u8, false);
+        }
+    }
+
+    /// Whether the state changed since the last call, and the texture needs
+    /// updating.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.changed, false)
+    }
+
+    /// The keyboard texture. Each texel is white where the state is set, so
+    /// any channel can be read, and transparent where it isn't.
+    pub fn description(&self) -> Description {
+        Description::Texture {
+            width: KEYS as i32,
+            height: 3,
+            filter: WebGLRenderingContext::NEAREST,
+            wrap: WebGLRenderingContext::CLAMP_TO_EDGE,
+            pixels: Some(self.state.iter().flat_map(|&value| vec![value; 4]).collect()),
+        }
+    }
+
+    fn get(&self, row: usize, key: u8) -> bool {
+        self.state[row * KEYS + key as usize] != 0
+    }
+
+    fn set(&mut self, row: usize, key: u8, value: bool) {
+        let texel = &mut self.state[row * KEYS + key as usize];
+        let value = if value { 255 } else { 0 };
+        if *texel != value {
+            *texel = value;
+            self.changed = true;
+        }
+    }
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: u8 = 65;
+
+    /// The sign of each component of `iMouse`.
+    fn signs(mouse: &Mouse) -> [f32; 4] {
+        let uniform = mouse.uniform(1.0);
+        [uniform.x.signum(), uniform.y.signum(), uniform.z.signum(), uniform.w.signum()]
+    }
+
+    #[test]
+    fn a_press_sets_every_row() {
+        let mut keyboard = Keyboard::new();
+        keyboard.press(KEY);
+
+        assert!(keyboard.get(DOWN, KEY));
+        assert!(keyboard.get(PRESSED, KEY));
+        assert!(keyboard.get(TOGGLED, KEY));
+        assert!(!keyboard.get(DOWN, KEY + 1));
+    }
+
+    #[test]
+    fn repeats_of_a_held_key_are_not_presses() {
+        let mut keyboard = Keyboard::new();
+        keyboard.press(KEY);
+        keyboard.end_frame();
+        keyboard.take_changed();
+
+        keyboard.press(KEY);
+
+        assert!(!keyboard.get(PRESSED, KEY));
+        assert!(keyboard.get(TOGGLED, KEY));
+        assert!(!keyboard.take_changed());
+    }
+
+    #[test]
+    fn end_frame_only_clears_presses() {
+        let mut keyboard = Keyboard::new();
+        keyboard.press(KEY);
+        keyboard.end_frame();
+
+        assert!(keyboard.get(DOWN, KEY));
+        assert!(!keyboard.get(PRESSED, KEY));
+        assert!(keyboard.get(TOGGLED, KEY));
+    }
+
+    #[test]
+    fn every_new_press_flips_toggled() {
+        let mut keyboard = Keyboard::new();
+        keyboard.press(KEY);
+        keyboard.release(KEY);
+        keyboard.press(KEY);
+
+        assert!(keyboard.get(PRESSED, KEY));
+        assert!(!keyboard.get(TOGGLED, KEY));
+    }
+
+    #[test]
+    fn release_all_keeps_toggles() {
+        let mut keyboard = Keyboard::new();
+        keyboard.press(KEY);
+        keyboard.press(KEY + 1);
+        keyboard.release_all();
+
+        assert!(!keyboard.get(DOWN, KEY));
+        assert!(!keyboard.get(DOWN, KEY + 1));
+        assert!(keyboard.get(TOGGLED, KEY));
+    }
+
+    #[test]
+    fn mouse_signs_tell_clicks_from_drags() {
+        let mut mouse = Mouse::new();
+        mouse.press(10.0, 20.0);
+        assert_eq!(signs(&mouse), [1.0, 1.0, 1.0, 1.0]);
+
+        // Still held, but no longer the frame of the press.
+        mouse.end_frame();
+        assert_eq!(signs(&mouse), [1.0, 1.0, 1.0, -1.0]);
+
+        mouse.release();
+        assert_eq!(signs(&mouse), [1.0, 1.0, -1.0, -1.0]);
+    }
+
+    #[test]
+    fn a_press_released_within_the_frame_still_counts() {
+        let mut mouse = Mouse::new();
+        mouse.press(10.0, 20.0);
+        mouse.release();
+        assert_eq!(signs(&mouse), [1.0, 1.0, -1.0, 1.0]);
+
+        mouse.end_frame();
+        assert_eq!(signs(&mouse), [1.0, 1.0, -1.0, -1.0]);
+    }
+
+    #[test]
+    fn mouse_only_follows_the_pointer_while_held() {
+        let mut mouse = Mouse::new();
+        mouse.move_to(5.0, 5.0);
+        assert_eq!(mouse.uniform(1.0), glm::vec4(0.0, 0.0, 0.0, 0.0));
+
+        mouse.press(10.0, 20.0);
+        mouse.move_to(30.0, 40.0);
+        mouse.release();
+        mouse.move_to(50.0, 60.0);
+
+        assert_eq!(mouse.uniform(0.5), glm::vec4(15.0, 20.0, -5.0, 10.0));
+    }
+}
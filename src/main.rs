@@ -1,3 +1,5 @@
+#![recursion_limit = "1024"]
+
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
@@ -6,20 +8,66 @@ extern crate stdweb;
 #[macro_use]
 extern crate stdweb_derive;
 
+mod attributes;
+mod audio;
+mod channel;
+mod commands;
+mod export;
+mod geometry;
+mod gl_state;
+mod glsl;
+mod input;
 mod models;
+mod owned;
+mod post;
+mod project;
+mod render_target;
+mod resolution;
+mod resources;
 mod shader;
+mod sound;
+mod timing;
+mod uniform;
+#[cfg(not(feature = "web-sys-backend"))]
+mod webgl_rendering_context;
+#[cfg(feature = "web-sys-backend")]
+#[path = "webgl_web_sys.rs"]
 mod webgl_rendering_context;
 
 use serde::{Deserialize, Serialize};
-use stdweb::js;
+use stdweb::{js, Value};
+use stdweb::web::{window, File, IHtmlElement, TypedArray};
 use stdweb::web::html_element::CanvasElement;
 use webgl_rendering_context::WebGLRenderingContext;
 use yew::{html, Component, ComponentLink, Html, NodeRef, Properties, ShouldRender};
-use crate::webgl_rendering_context::{WebGLProgram, WebGLUniformLocation};
-use crate::models::{Model, Cube};
+use yew::events::{ChangeData, InputData};
+use yew::services::render::{RenderService, RenderTask};
+use yew::services::resize::{ResizeService, ResizeTask};
+use crate::attributes::AttributeBindings;
+use crate::audio::{AudioChannel, AudioSource};
+use crate::channel::{Channel, Image, CHANNELS};
+use crate::gl_state::{CallCounts, Gl};
+use crate::glsl::{analyzer, builtins, format, preprocessor, Diagnostic, Preprocessor, Severity, SnippetLibrary};
+use crate::input::{Keyboard, Mouse};
+use crate::models::{Model, Mesh, Cube};
+use crate::post::PostStack;
+use crate::project::{Project, UniformValue};
+use crate::render_target::{Pass, RenderTarget};
+use crate::resolution::Resolution;
+use crate::resources::{Registry, Resource};
+use crate::sound::{Player, SoundShader};
+use crate::timing::GpuTimer;
+use crate::uniform::{TextureUnit, Uniform, UniformType};
 
+use nalgebra_glm::{Mat4, Vec3, Vec4};
 use std::f32::consts::PI;
 
+/// Id of the canvas, the web-sys backend finds it by it.
+const CANVAS_ID: &str = "canvas";
+
+/// Texture unit of `iChannel0`, after the keyboard's and the audio's.
+const FIRST_CHANNEL_UNIT: i32 = 2;
+
 pub const DEFAULT_VERTEX: &str = r#"
     attribute vec4 aVertexPosition;
 
@@ -38,20 +86,114 @@ void main() {
 }
 "#;
 
+/// A plucked A every half second, slowly panning from side to side.
+pub const DEFAULT_SOUND: &str = r#"vec2 mainSound(float time) {
+    float beat = mod(time, 0.5);
+    float note = sin(6.2831853 * 440.0 * beat) * exp(-8.0 * beat);
+    float pan = 0.5 + 0.5 * sin(time);
+    return vec2(1.0 - pan, pan) * note * 0.5;
+}
+"#;
+
 struct State {
+    link: ComponentLink<Self>,
+    project: Project,
     canvas_ref: NodeRef,
     canvas: Option<CanvasElement>,
-    gl: Option<WebGLRenderingContext>,
-    shader_program: Option<WebGLProgram>,
-    vertex_position: Option<u32>,
-    projection_matrix: Option<WebGLUniformLocation>,
-    model_view_matrix: Option<WebGLUniformLocation>,
+    gl: Option<Gl>,
+    /// State changes of the last frame, sent and skipped.
+    gl_calls: CallCounts,
+    shader_program: Option<Resource>,
+    attributes: Option<AttributeBindings>,
+    /// Shader attribute name and the layout entry it's about to be made an
+    /// alias of.
+    alias_draft: (String, String),
+    projection_matrix: Option<Uniform<Mat4>>,
+    model_view_matrix: Option<Uniform<Mat4>>,
+    resolution_uniform: Option<Uniform<Vec3>>,
+    mouse_uniform: Option<Uniform<Vec4>>,
+    keyboard_uniform: Option<Uniform<TextureUnit>>,
+    audio_uniform: Option<Uniform<TextureUnit>>,
+    audio_level_uniform: Option<Uniform<f32>>,
+    audio_bands_uniform: Option<Uniform<Vec3>>,
+    channel_uniforms: Vec<Option<Uniform<TextureUnit>>>,
+    mouse: Mouse,
+    keyboard: Keyboard,
+    keyboard_texture: Option<Resource>,
+    audio: Option<AudioChannel>,
+    sound: Option<SoundShader>,
+    channels: Vec<Channel>,
     model: Box<dyn Model>,
+    mesh: Option<Mesh>,
+    snippets: SnippetLibrary,
+    /// Name and source of the snippet being edited, saved into the library
+    /// on demand.
+    snippet_draft: (String, String),
+    /// Names the sources and the snippets they include test with `#ifdef`
+    /// or `defined`, offered as toggles.
+    toggles: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    render_service: RenderService,
+    render_task: Option<RenderTask>,
+    gpu_timer: Option<GpuTimer>,
+    resolution: Resolution,
+    render_target: Option<RenderTarget>,
+    blitter: Option<Pass>,
+    post: PostStack,
+    last_frame: Option<f64>,
+    resize_task: Option<ResizeTask>,
+    resize_observer: Option<Value>,
+    registry: Registry,
+    /// Set between the browser taking the context away and giving it back,
+    /// with the reason it gave, if any.
+    context_lost: Option<String>,
+    /// Set when the sources changed while the context was lost, so the
+    /// program is rebuilt from them instead of restored.
+    rebuild_on_restore: bool,
 }
 
 impl State {
-    fn render(&self) {
+    fn render(&mut self) {
         let gl = self.gl.as_ref().unwrap();
+
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.poll(gl);
+            timer.begin_frame();
+            timer.begin_pass("main");
+        }
+
+        // Below or above a scale of 1 the scene is drawn offscreen and
+        // stretched over the canvas afterwards, post effects also need it
+        // offscreen to read from. The canvas is only asked for its size here,
+        // going through the context later would submit mid-frame.
+        let (canvas_width, canvas_height) = (gl.canvas().width() as i32, gl.canvas().height() as i32);
+        let (width, height) = self.resolution.render_size(canvas_width, canvas_height);
+        let offscreen = ((width, height) != (canvas_width, canvas_height) || self.post.is_active()) && self.blitter.is_some();
+
+        if offscreen {
+            match self.render_target.as_mut() {
+                Some(target) => target.resize(gl, width, height),
+                None => match RenderTarget::new(gl, &mut self.registry, width, height) {
+                    Ok(target) => self.render_target = Some(target),
+                    Err(error) => {
+                        // Keep rendering at full size rather than not at all.
+                        self.diagnostics.push(Diagnostic::warning(None, error));
+                        self.blitter = None;
+                    }
+                },
+            }
+        }
+
+        let offscreen = offscreen && self.blitter.is_some();
+        let (width, height) = if offscreen { (width, height) } else { (canvas_width, canvas_height) };
+        match self.render_target.as_ref() {
+            Some(target) if offscreen => target.bind(gl),
+            _ => {
+                gl.bind_framebuffer(WebGLRenderingContext::FRAMEBUFFER, None);
+                gl.viewport(0, 0, canvas_width, canvas_height);
+            }
+        }
+
         gl.clear_color(0.0, 0.0, 0.0, 1.0);
         gl.clear_depth(1.0);
         gl.enable(WebGLRenderingContext::DEPTH_TEST);
@@ -67,11 +209,8 @@ impl State {
         // and we only want to see objects between 0.1 units
         // and 100 units away from the camera.
 
-        let canvas_height = gl.canvas().height() as f32;
-        let canvas_width = gl.canvas().width() as f32;
-
         let fov = 45f32 * PI / 180f32;   // in radians
-        let aspect = canvas_width / canvas_height;
+        let aspect = canvas_width as f32 / canvas_height as f32;
         let z_near = 0.1;
         let z_far = 100.0;
 
@@ -95,96 +234,1387 @@ impl State {
         let translation = nalgebra_glm::vec3(0.0, 0.0, -6.0);
         model_view_matrix = nalgebra_glm::translate(&model_view_matrix, &translation);
 
-        // Tell WebGL how to pull out the positions from the position
-        // buffer into the vertexPosition attribute.
-        {
-            let num_components = 2;  // pull out 2 values per iteration
-            let normalize = false;   // don't normalize
-            let stride = 0;          // how many bytes to get from one set of values to the next
+        // Tell WebGL how to pull each attribute the shader uses out of
+        // the mesh's vertex buffer.
+        let mesh = self.mesh.as_ref().unwrap();
+        self.attributes.as_ref().unwrap().apply(gl, mesh);
 
-            // 0 = use type and numComponents above
-            let offset = 0;          // how many bytes inside the buffer to start from
+        // Tell WebGL to use our program when drawing
 
-//            gl.bind_buffer(WebGLRenderingContext::ARRAY_BUFFER, self.model.positions().as_ref());
+        gl.use_program(self.shader_program.as_ref().and_then(Resource::program).as_ref());
 
-            gl.vertex_attrib_pointer(
-                self.vertex_position.unwrap(),
-                num_components,
-                WebGLRenderingContext::FLOAT,
-                normalize,
-                stride,
-                offset
-            );
+        // Set the shader uniforms
 
-            gl.enable_vertex_attrib_array(self.vertex_position.unwrap());
+        if let Some(uniform) = self.projection_matrix.as_mut() {
+            uniform.set(gl, projection_matrix);
+        }
+        if let Some(uniform) = self.model_view_matrix.as_mut() {
+            uniform.set(gl, model_view_matrix);
         }
 
-        // Tell WebGL to use our program when drawing
+        // The size of the image being rendered, which is only the canvas'
+        // when it isn't scaled.
+        if let Some(uniform) = self.resolution_uniform.as_mut() {
+            uniform.set(gl, nalgebra_glm::vec3(width as f32, height as f32, 1.0));
+        }
+        if let Some(uniform) = self.mouse_uniform.as_mut() {
+            uniform.set(gl, self.mouse.uniform(width as f32 / canvas_width));
+        }
 
-        gl.use_program(self.shader_program.as_ref());
+        // Uploads first, then the textures are bound for sampling.
+        if let Some(texture) = self.keyboard_texture.as_ref() {
+            if self.keyboard.take_changed() {
+                // Always a texture, which `update` accepts.
+                texture.update(gl, self.keyboard.description()).unwrap();
+            }
+        }
+        if let Some(audio) = self.audio.as_mut() {
+            audio.update(gl);
+        }
 
-        // Set the shader uniforms
+        if let (Some(uniform), Some(texture)) = (self.keyboard_uniform.as_mut(), self.keyboard_texture.as_ref()) {
+            gl.active_texture(WebGLRenderingContext::TEXTURE0);
+            gl.bind_texture(WebGLRenderingContext::TEXTURE_2D, texture.texture().as_ref());
+            uniform.set(gl, TextureUnit(0));
+        }
+
+        if let Some(audio) = self.audio.as_ref() {
+            if let (Some(uniform), Some(texture)) = (self.audio_uniform.as_mut(), audio.texture()) {
+                gl.active_texture(WebGLRenderingContext::TEXTURE1);
+                gl.bind_texture(WebGLRenderingContext::TEXTURE_2D, Some(&texture));
+                uniform.set(gl, TextureUnit(1));
+            }
+            if let Some(uniform) = self.audio_level_uniform.as_mut() {
+                uniform.set(gl, audio.analysis().level());
+            }
+            if let Some(uniform) = self.audio_bands_uniform.as_mut() {
+                let [bass, mids, highs] = audio.analysis().bands();
+                uniform.set(gl, nalgebra_glm::vec3(bass, mids, highs));
+            }
+        }
+
+        for (index, (channel, uniform)) in self.channels.iter().zip(self.channel_uniforms.iter_mut()).enumerate() {
+            if let Some(uniform) = uniform.as_mut() {
+                // Empty channels read from a unit nothing is bound to, black.
+                let unit = FIRST_CHANNEL_UNIT + index as i32;
+                if let Some((target, texture)) = channel.texture() {
+                    gl.active_texture(WebGLRenderingContext::TEXTURE0 + unit as u32);
+                    gl.bind_texture(target, Some(&texture));
+                }
+                uniform.set(gl, TextureUnit(unit));
+            }
+        }
+
+        mesh.draw(gl);
+
+        if let (true, Some(target), Some(blitter)) = (offscreen, self.render_target.as_ref(), self.blitter.as_mut()) {
+            // Queries go straight to the extension, the draws before them
+            // have to be made first.
+            let post = self.post.is_active();
+            if let Some(timer) = self.gpu_timer.as_mut() {
+                gl.submit();
+                timer.begin_pass(if post { "post" } else { "upscale" });
+            }
+
+            if let Some(texture) = target.texture() {
+                let time = self.last_frame.unwrap_or(0.0) as f32 / 1000.0;
+                let drawn = post && match self.post.render(gl, &mut self.registry, &texture, (width, height), (canvas_width, canvas_height), time) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        // Show the scene without effects rather than not at all.
+                        self.diagnostics.push(Diagnostic::warning(None, error));
+                        self.post.disable();
+                        false
+                    }
+                };
+
+                if !drawn {
+                    gl.bind_framebuffer(WebGLRenderingContext::FRAMEBUFFER, None);
+                    gl.viewport(0, 0, canvas_width, canvas_height);
+                    blitter.draw(gl, &[&texture], |_| {});
+                }
+            }
+        }
+
+        gl.submit();
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.end_frame();
+        }
+
+        // Sound is rendered a block per frame, after the frame's own passes.
+        if let Some(sound) = self.sound.as_mut() {
+            sound.render_block(gl);
+        }
+
+        // Presses show up in exactly one frame.
+        self.mouse.end_frame();
+        self.keyboard.end_frame();
+
+        self.gl_calls = gl.take_counts();
+    }
+
+    /// Feeds the adaptive resolution the GPU time of the latest frame when
+    /// it's measured, or else the time since the previous frame.
+    ///
+    /// Returns whether the render scale changed.
+    fn adapt_resolution(&mut self, timestamp: f64) -> bool {
+        let interval = self.last_frame.map(|last| timestamp - last);
+        self.last_frame = Some(timestamp);
+
+        let frame_time = match self.gpu_timer.as_ref().and_then(GpuTimer::latest) {
+            Some(frame) => frame.total,
+            None => match interval {
+                Some(interval) => interval,
+                None => return false,
+            },
+        };
+
+        self.resolution.update(frame_time)
+    }
+
+    /// Sizes the canvas' drawing buffer to its displayed size in device
+    /// pixels, so a resolution scale of 1 is sharp on high DPI screens.
+    ///
+    /// Returns whether the size changed. Changing it clears the canvas, so
+    /// it's left alone otherwise.
+    fn fit_canvas(&self) -> bool {
+        let canvas = self.canvas.as_ref().unwrap();
+        let ratio = window().device_pixel_ratio();
+        let width = ((canvas.offset_width() as f64 * ratio).round() as u32).max(1);
+        let height = ((canvas.offset_height() as f64 * ratio).round() as u32).max(1);
+
+        if (width, height) == (canvas.width(), canvas.height()) {
+            return false;
+        }
+
+        canvas.set_width(width);
+        canvas.set_height(height);
+        true
+    }
+
+    /// Resizes the drawing buffer and the render targets that depend on it
+    /// after the canvas' displayed size changed.
+    ///
+    /// Returns whether anything changed.
+    fn resize(&mut self) -> bool {
+        if !self.fit_canvas() {
+            return false;
+        }
+
+        if let (Some(gl), Some(target)) = (self.gl.as_ref(), self.render_target.as_mut()) {
+            let (width, height) = self.resolution.render_size(gl.canvas().width() as i32, gl.canvas().height() as i32);
+            target.resize(gl, width, height);
+        }
+        true
+    }
+
+    /// Follows both the window, which also catches zooming and moving to a
+    /// screen with another pixel ratio, and the canvas' own box, which the
+    /// layout can resize without the window changing.
+    fn watch_size(&mut self) {
+        self.resize_task = Some(ResizeService::new().register(self.link.callback(|_| Message::Resize)));
+
+        let callback = self.link.callback(|_: ()| Message::Resize);
+        let resized = move || callback.emit(());
+        let canvas = self.canvas.as_ref().unwrap();
+
+        self.resize_observer = Some(js! {
+            if (typeof ResizeObserver === "undefined") {
+                return null;
+            }
+            var resized = @{resized};
+            var observer = new ResizeObserver(function () {
+                resized();
+            });
+            observer.observe(@{canvas});
+            return observer;
+        });
+    }
+
+    /// Looks up a uniform of the shader program. Declaring it with another
+    /// type than the app sets is reported as a warning.
+    fn uniform<T: UniformType>(&mut self, name: &str) -> Option<Uniform<T>> {
+        let program = self.shader_program.clone()?;
+
+        match Uniform::new(self.gl.as_ref().unwrap(), &mut self.registry, &program, name) {
+            Ok(uniform) => uniform,
+            Err(error) => {
+                self.diagnostics.push(Diagnostic::warning(None, error));
+                None
+            }
+        }
+    }
+
+    /// Gets the canvas' WebGL2 context, or its WebGL1 context where there's
+    /// none. Both take the same calls, 3D textures need WebGL2.
+    #[cfg(not(feature = "web-sys-backend"))]
+    fn context(&self) -> WebGLRenderingContext {
+        let canvas = self.canvas.as_ref().unwrap();
+        let context = js! {
+            var canvas = @{canvas};
+            return canvas.getContext("webgl2") || canvas.getContext("webgl");
+        };
+        // A WebGL2 context isn't an instance of `WebGLRenderingContext`,
+        // which the checked conversion insists on.
+        unsafe { context.into_reference_unchecked() }.unwrap()
+    }
+
+    #[cfg(feature = "web-sys-backend")]
+    fn context(&self) -> WebGLRenderingContext {
+        WebGLRenderingContext::from_canvas_id(CANVAS_ID).unwrap()
+    }
+
+    /// Listens for the browser taking the context away, e.g. after a driver
+    /// reset or when too many contexts are open, and giving it back.
+    ///
+    /// https://developer.mozilla.org/en-US/docs/Web/API/HTMLCanvasElement/webglcontextlost_event
+    fn watch_context(&mut self) {
+        let lost = self.link.callback(Message::ContextLost);
+        let lost = move |reason: String| lost.emit(reason);
+        let restored = self.link.callback(|_: ()| Message::ContextRestored);
+        let restored = move || restored.emit(());
+        let canvas = self.canvas.as_ref().unwrap();
+
+        js! { @(no_return)
+            var lost = @{lost};
+            var restored = @{restored};
+            var canvas = @{canvas};
+            canvas.addEventListener("webglcontextlost", function (event) {
+                // Without this the browser never gives the context back.
+                event.preventDefault();
+                lost(event.statusMessage);
+            });
+            canvas.addEventListener("webglcontextrestored", function () {
+                restored();
+            });
+        };
+    }
+
+    /// Follows the mouse over the canvas and the keyboard while the canvas
+    /// has focus, which clicking it gives it. Positions are converted to
+    /// canvas pixels from the bottom left, like `gl_FragCoord`.
+    ///
+    /// Buttons released outside the canvas still count, and keys are
+    /// released when it loses focus since it won't hear about them anymore.
+    fn watch_input(&mut self) {
+        let down = self.link.callback(|(x, y): (f64, f64)| Message::MouseDown(x as f32, y as f32));
+        let down = move |x: f64, y: f64| down.emit((x, y));
+        let moved = self.link.callback(|(x, y): (f64, f64)| Message::MouseMove(x as f32, y as f32));
+        let moved = move |x: f64, y: f64| moved.emit((x, y));
+        let up = self.link.callback(|_: ()| Message::MouseUp);
+        let up = move || up.emit(());
+        let key_down = self.link.callback(|key: u32| Message::KeyDown(key as u8));
+        let key_down = move |key: u32| key_down.emit(key);
+        let key_up = self.link.callback(|key: u32| Message::KeyUp(key as u8));
+        let key_up = move |key: u32| key_up.emit(key);
+        let blur = self.link.callback(|_: ()| Message::ReleaseKeys);
+        let blur = move || blur.emit(());
+        let canvas = self.canvas.as_ref().unwrap();
+
+        js! { @(no_return)
+            var down = @{down};
+            var moved = @{moved};
+            var up = @{up};
+            var keyDown = @{key_down};
+            var keyUp = @{key_up};
+            var blur = @{blur};
+            var canvas = @{canvas};
+            var pressed = false;
+
+            function position(event, callback) {
+                var rect = canvas.getBoundingClientRect();
+                var scale = canvas.width / rect.width;
+                callback((event.clientX - rect.left) * scale, (rect.bottom - event.clientY) * scale);
+            }
+            canvas.addEventListener("mousedown", function (event) {
+                pressed = true;
+                canvas.focus();
+                position(event, down);
+            });
+            canvas.addEventListener("mousemove", function (event) {
+                if (pressed) {
+                    position(event, moved);
+                }
+            });
+            window.addEventListener("mouseup", function () {
+                if (pressed) {
+                    pressed = false;
+                    up();
+                }
+            });
+
+            // Tab still moves the focus on, everything else is the shader's.
+            canvas.addEventListener("keydown", function (event) {
+                if (event.keyCode === 9 || event.keyCode > 255) {
+                    return;
+                }
+                event.preventDefault();
+                keyDown(event.keyCode);
+            });
+            canvas.addEventListener("keyup", function (event) {
+                if (event.keyCode <= 255) {
+                    keyUp(event.keyCode);
+                }
+            });
+            canvas.addEventListener("blur", function () {
+                blur();
+            });
+        };
+    }
+
+    /// Stops rendering until the context comes back. Every WebGL object is
+    /// gone, only their descriptions in the registry remain.
+    fn lose_context(&mut self, reason: String) {
+        self.registry.lose();
+        self.render_task = None;
+        self.gpu_timer = None;
+        self.last_frame = None;
+        self.context_lost = Some(reason);
+    }
+
+    /// Recreates every GPU resource from the registry, then what's derived
+    /// from them, and starts rendering again.
+    fn restore_context(&mut self) {
+        let gl = self.gl.as_ref().unwrap();
+        self.context_lost = None;
+
+        // A restored context starts from the defaults.
+        gl.reset();
+
+        for error in self.registry.restore(gl) {
+            self.diagnostics.push(Diagnostic::error(None, format!("Unable to restore a GPU resource: {}", error)));
+        }
+
+        if let Some(mesh) = self.mesh.as_ref() {
+            if let Err(error) = mesh.restore(gl) {
+                self.diagnostics.push(Diagnostic::error(None, error));
+            }
+        }
+
+        if self.rebuild_on_restore {
+            self.rebuild_on_restore = false;
+            self.rebuild_program();
+        } else {
+            // Attribute locations belong to the program object, which is new.
+            self.bind_attributes();
+        }
+
+        self.gpu_timer = GpuTimer::new(gl);
+        if self.shader_program.is_some() && self.mesh.is_some() {
+            self.request_frame();
+        }
+    }
+
+    /// Renders a frame and schedules the next one.
+    fn request_frame(&mut self) {
+        self.render_task = Some(self.render_service.request_animation_frame(self.link.callback(Message::Render)));
+    }
+
+    /// Preprocesses, analyzes and compiles the shader sources, collecting our
+    /// own findings and the driver's errors for the info panel.
+    ///
+    /// Returns whether they compiled, the program before is kept if not.
+    fn compile(&mut self, vsrc: &str, fsrc: &str) -> bool {
+        let gl = self.gl.as_ref().unwrap();
+        let preprocessor = self.project.preprocessor(&self.snippets);
+        self.diagnostics.clear();
+
+        if let (Ok(vertex), Ok(fragment)) = (preprocessor.process("vertex", vsrc), preprocessor.process("fragment", fsrc)) {
+            self.diagnostics.extend(analyzer::analyze(&vertex, &fragment));
+        }
+
+        match shader::init(gl, &mut self.registry, &preprocessor, vsrc, fsrc) {
+            Ok(linked) => {
+                self.diagnostics.extend(linked.warnings);
+                self.shader_program = Some(linked.program);
+                true
+            }
+            Err(diagnostics) => {
+                self.diagnostics.extend(diagnostics);
+                false
+            }
+        }
+    }
+
+    /// Compiles the project's shaders with the current snippets and toggles
+    /// and switches to the new program, looking up what's derived from it.
+    fn rebuild_program(&mut self) {
+        let (vertex, fragment) = (self.project.vertex.clone(), self.project.fragment.clone());
+
+        // Toggles already switched on would count as defined by the sources.
+        let plain = Preprocessor::new(&self.snippets);
+        self.toggles.clear();
+        for source in &[&vertex, &fragment] {
+            let expanded = plain.process("", source).map(|expanded| expanded.source).unwrap_or_default();
+            for name in preprocessor::toggles(&expanded) {
+                if !self.toggles.contains(&name) {
+                    self.toggles.push(name);
+                }
+            }
+        }
+
+        // Nothing compiles on a lost context, the sources are picked up again
+        // once it's back.
+        if self.context_lost.is_some() {
+            self.rebuild_on_restore = true;
+            return;
+        }
+
+        if !self.compile(&vertex, &fragment) {
+            return;
+        }
+
+        self.projection_matrix = self.uniform("uProjectionMatrix");
+        self.model_view_matrix = self.uniform("uModelViewMatrix");
+        self.resolution_uniform = self.uniform("iResolution");
+        self.mouse_uniform = self.uniform("iMouse");
+        self.keyboard_uniform = self.uniform("iKeyboard");
+        self.audio_uniform = self.uniform("iAudio");
+        self.audio_level_uniform = self.uniform("iAudioLevel");
+        self.audio_bands_uniform = self.uniform("iAudioBands");
+        self.channel_uniforms = (0..CHANNELS).map(|index| self.uniform(&format!("iChannel{}", index))).collect();
+        for (channel, uniform) in self.channels.iter_mut().zip(&self.channel_uniforms) {
+            if let Some(uniform) = uniform {
+                channel.follow_sampler(uniform.type_());
+            }
+        }
+        for index in 0..CHANNELS {
+            self.check_channel(index);
+        }
+
+        self.bind_attributes();
+
+        // The first program to compile starts rendering.
+        if self.render_task.is_none() && self.context_lost.is_none() && self.mesh.is_some() {
+            self.request_frame();
+        }
+    }
+
+    /// Matches the program's attributes to the mesh, through the project's
+    /// aliases.
+    fn bind_attributes(&mut self) {
+        let program = self.shader_program.as_ref().and_then(Resource::program);
+        if let (Some(program), Some(mesh)) = (program, self.mesh.as_ref()) {
+            let aliases = self.project.aliases();
+            self.attributes = Some(AttributeBindings::new(self.gl.as_ref().unwrap(), &program, mesh, &aliases));
+        }
+    }
+
+    /// Warns when what a channel holds isn't what the shader declares its
+    /// sampler as, e.g. an image for a `samplerCube`.
+    fn check_channel(&mut self, index: usize) {
+        let uniform = match self.channel_uniforms.get(index) {
+            Some(Some(uniform)) => uniform,
+            _ => return,
+        };
+
+        if let Some(mismatch) = self.channels[index].mismatch(uniform.name(), uniform.type_()) {
+            self.diagnostics.push(Diagnostic::warning(None, mismatch));
+        }
+    }
+
+    /// Compiles the project's sound shader, reporting errors with the other
+    /// diagnostics.
+    fn compile_sound(&mut self, sample_rate: u32) -> Option<SoundShader> {
+        let source = self.project.sound.clone()?;
+        let gl = self.gl.as_ref()?;
+
+        let sound = self.project
+            .preprocessor(&self.snippets)
+            .process("sound", &source)
+            .and_then(|sound| SoundShader::new(gl, &mut self.registry, &sound.source, sample_rate));
+
+        match sound {
+            Ok(sound) => Some(sound),
+            Err(error) => {
+                self.diagnostics.push(Diagnostic::error(None, format!("Unable to compile the sound shader: {}", error)));
+                None
+            }
+        }
+    }
+
+    /// Renders what's left of the sound shader, compiling it first if it
+    /// isn't playing, and hands it to the browser as a WAV download.
+    fn export_sound(&mut self) {
+        if self.sound.is_none() {
+            self.sound = self.compile_sound(sound::EXPORT_SAMPLE_RATE);
+        }
+        let (gl, sound) = match (self.gl.as_ref(), self.sound.as_mut()) {
+            (Some(gl), Some(sound)) => (gl, sound),
+            _ => return,
+        };
+
+        sound.render_all(gl);
+        let wav = TypedArray::<u8>::from(sound.wav().as_slice());
+        let file_name = format!("{}.wav", self.project.name);
+
+        js! {
+            var link = document.createElement("a");
+            link.href = URL.createObjectURL(new Blob([@{wav}], { type: "audio/wav" }));
+            link.download = @{file_name};
+            document.body.appendChild(link);
+            link.click();
+            document.body.removeChild(link);
+            setTimeout(() => URL.revokeObjectURL(link.href), 0);
+        };
+    }
+
+    /// Copies the camera matrices last uploaded and the channel images into
+    /// the project, exports start from what the editor shows.
+    fn record_project(&mut self) {
+        let matrices = [("uProjectionMatrix", &self.projection_matrix), ("uModelViewMatrix", &self.model_view_matrix)];
+        for (name, uniform) in matrices.iter() {
+            if let Some(matrix) = uniform.as_ref().and_then(Uniform::value) {
+                let mut values = [0.0; 16];
+                values.copy_from_slice(matrix.as_slice());
+                self.project.set_uniform(name, UniformValue::Mat4(values));
+            }
+        }
+
+        self.project.textures.clear();
+        for (index, channel) in self.channels.iter().enumerate() {
+            let uniform = format!("iChannel{}", index);
+            match (channel.data_url(), channel.summary()) {
+                (Some(url), _) => self.project.set_texture(&uniform, &url),
+                (None, Some(summary)) => self.diagnostics.push(Diagnostic::warning(
+                    None,
+                    format!("`{}` holds a {}, exports only include images", uniform, summary),
+                )),
+                (None, None) => {}
+            }
+        }
+    }
+
+    /// Exports the project as a standalone page and hands it to the browser
+    /// as a download.
+    fn export(&mut self) {
+        self.record_project();
+        let html = match export::standalone_html(&self.project, self.model.as_ref(), &self.snippets) {
+            Ok(html) => html,
+            Err(error) => {
+                self.diagnostics.push(Diagnostic::error(None, format!("Export failed: {}", error)));
+                return;
+            }
+        };
+        let file_name = format!("{}.html", self.project.name);
+
+        js! {
+            var link = document.createElement("a");
+            link.href = URL.createObjectURL(new Blob([@{html}], { type: "text/html" }));
+            link.download = @{file_name};
+            document.body.appendChild(link);
+            link.click();
+            document.body.removeChild(link);
+            setTimeout(() => URL.revokeObjectURL(link.href), 0);
+        };
+    }
+
+    /// Render scale picker and adaptive toggle, with the scale and size
+    /// frames are actually rendered at.
+    fn view_resolution(&self) -> Html {
+        let scale = self.resolution.scale();
+        let (width, height) = match &self.canvas {
+            Some(canvas) => self.resolution.render_size(canvas.width() as i32, canvas.height() as i32),
+            None => (0, 0),
+        };
+
+        let on_change = self.link.callback(|change: ChangeData| {
+            let index = match change {
+                ChangeData::Select(select) => select.selected_index(),
+                _ => None,
+            };
+            let scale = index.and_then(|index| resolution::SCALES.get(index as usize)).cloned().unwrap_or(1.0);
+            Message::SetResolutionScale(scale)
+        });
 
-//        gl.uniformMatrix4fv(
-//            programInfo.uniformLocations.projectionMatrix,
-//            false,
-//            projectionMatrix);
-//        gl.uniformMatrix4fv(
-//            programInfo.uniformLocations.modelViewMatrix,
-//            false,
-//            modelViewMatrix);
-//
-//        {
-//            const offset = 0;
-//            const vertexCount = 4;
-//            gl.drawArrays(gl.TRIANGLE_STRIP, offset, vertexCount);
-//        }
+        html! {
+            <div class="form-inline mb-2 small">
+                <label class="mr-1" for="resolution-scale">{ "Resolution" }</label>
+                <select id="resolution-scale" class="custom-select custom-select-sm mr-3" onchange=on_change>
+                    { for resolution::SCALES.iter().map(|&option| html! {
+                        <option selected={ option == scale }>{ format!("{}×", option) }</option>
+                    }) }
+                </select>
+                <div class="custom-control custom-checkbox mr-3">
+                    <input
+                        type="checkbox"
+                        class="custom-control-input"
+                        id="adaptive-resolution"
+                        checked=self.resolution.adaptive()
+                        onclick=self.link.callback(|_| Message::ToggleAdaptiveResolution) />
+                    <label
+                        class="custom-control-label"
+                        for="adaptive-resolution"
+                        title={ format!("Lower the resolution while frames take longer than {:.1} ms", self.resolution.budget()) }>
+                        { "Adaptive" }
+                    </label>
+                </div>
+                <span class="text-muted text-monospace">
+                    { format!("{:.2}×, {}×{}", self.resolution.effective_scale(), width, height) }
+                </span>
+            </div>
+        }
+    }
+
+    /// The post effects in the order they're applied, each with a toggle, a
+    /// button to move it earlier and sliders for its parameters while it's
+    /// enabled.
+    fn view_post_effects(&self) -> Html {
+        let view_parameter = |index: usize, effect: &post::Effect, (number, parameter): (usize, &post::Parameter)| {
+            let default = parameter.default;
+            let on_input = self.link.callback(move |input: InputData| {
+                Message::SetPostParameter(index, number, input.value.parse().unwrap_or(default))
+            });
+            let value = effect.values()[number];
+
+            html! {
+                <div class="form-inline ml-4">
+                    <label class="mr-1" style="width: 6rem;" for={ format!("post-{}-{}", index, number) }>{ parameter.name }</label>
+                    <input
+                        type="range"
+                        class="custom-range mr-2"
+                        style="width: 10rem;"
+                        id={ format!("post-{}-{}", index, number) }
+                        min={ parameter.min.to_string() }
+                        max={ parameter.max.to_string() }
+                        step={ parameter.step.to_string() }
+                        value={ value.to_string() }
+                        oninput=on_input />
+                    <span class="text-muted text-monospace">{ format!("{:.3}", value) }</span>
+                </div>
+            }
+        };
+
+        let view_effect = |(index, effect): (usize, &post::Effect)| {
+            let parameters = if effect.enabled() { effect.kind().parameters() } else { &[] };
+
+            html! {
+                <div>
+                    <div class="d-flex align-items-center">
+                        <div class="custom-control custom-checkbox mr-2">
+                            <input
+                                type="checkbox"
+                                class="custom-control-input"
+                                id={ format!("post-{}", index) }
+                                checked=effect.enabled()
+                                onclick=self.link.callback(move |_| Message::TogglePostEffect(index)) />
+                            <label class="custom-control-label" for={ format!("post-{}", index) }>{ effect.kind().name() }</label>
+                        </div>
+                        <button
+                            class="btn btn-sm btn-link p-0"
+                            title="Apply this effect before the one above"
+                            disabled={ index == 0 }
+                            onclick=self.link.callback(move |_| Message::MovePostEffect(index))>
+                            { "▲" }
+                        </button>
+                    </div>
+                    { for parameters.iter().enumerate().map(|parameter| view_parameter(index, effect, parameter)) }
+                </div>
+            }
+        };
+
+        html! {
+            <details class="mb-2 small">
+                <summary>{ "Post effects" }</summary>
+                { for self.post.effects().iter().enumerate().map(view_effect) }
+            </details>
+        }
+    }
+
+    /// Picking the audio shaders react to, a file or the microphone, and its
+    /// level while it plays.
+    fn view_audio(&self) -> Html {
+        let audio = match &self.audio {
+            Some(audio) => audio,
+            None => return html! {},
+        };
+
+        let on_change = self.link.callback(|change: ChangeData| match change {
+            ChangeData::Files(files) => match files.iter().next() {
+                Some(file) => Message::PlayAudioFile(file),
+                None => Message::StopAudio,
+            },
+            _ => Message::StopAudio,
+        });
+
+        html! {
+            <div class="form-inline mb-2 small">
+                <label class="mr-1">{ "Audio" }</label>
+                <div class="custom-file mr-2" style="width: 12rem;">
+                    <input type="file" class="custom-file-input" id="audio-file" accept="audio/*" onchange=on_change />
+                    <label class="custom-file-label text-truncate" for="audio-file">{ "Play a file" }</label>
+                </div>
+                <button
+                    class="btn btn-sm btn-outline-secondary mr-2"
+                    title="React to the microphone"
+                    onclick=self.link.callback(|_| Message::ListenToMicrophone)>
+                    { "Microphone" }
+                </button>
+                { if audio.is_playing() {
+                    let [bass, mids, highs] = audio.analysis().bands();
+                    html! {
+                        <>
+                            <button
+                                class="btn btn-sm btn-outline-secondary mr-3"
+                                onclick=self.link.callback(|_| Message::StopAudio)>
+                                { "Stop" }
+                            </button>
+                            <span class="text-muted text-monospace">
+                                { format!("level {:.2}, bands {:.2} {:.2} {:.2}", audio.analysis().level(), bass, mids, highs) }
+                            </span>
+                        </>
+                    }
+                } else {
+                    html! {}
+                } }
+            </div>
+        }
+    }
+
+    /// A row per channel: what it makes of the images picked for it, which
+    /// follows the shader's declaration, and what it holds.
+    fn view_channels(&self) -> Html {
+        html! {
+            <>
+                { for self.channels.iter().enumerate().map(|(index, channel)| self.view_channel(index, channel)) }
+            </>
+        }
+    }
+
+    fn view_channel(&self, index: usize, channel: &Channel) -> Html {
+        let kind = channel.kind();
+        let on_kind = self.link.callback(move |change: ChangeData| {
+            let selected = match change {
+                ChangeData::Select(select) => select.selected_index(),
+                _ => None,
+            };
+            let kind = selected.and_then(|selected| channel::Kind::ALL.get(selected as usize)).cloned().unwrap_or(kind);
+            Message::SetChannelKind(index, kind)
+        });
+        let on_files = self.link.callback(move |change: ChangeData| match change {
+            ChangeData::Files(files) => Message::LoadChannel(index, files.iter().collect()),
+            _ => Message::LoadChannel(index, Vec::new()),
+        });
+        let id = format!("channel-{}", index);
+
+        html! {
+            <div class="form-inline mb-2 small">
+                <label class="mr-1" for=id.clone()>{ format!("iChannel{}", index) }</label>
+                <select class="custom-select custom-select-sm mr-2" onchange=on_kind>
+                    { for channel::Kind::ALL.iter().map(|&option| html! {
+                        <option selected={ option == kind }>{ option.name() }</option>
+                    }) }
+                </select>
+                <div class="custom-file mr-2" style="width: 10rem;">
+                    <input
+                        type="file"
+                        class="custom-file-input"
+                        id=id.clone()
+                        accept="image/*"
+                        multiple="multiple"
+                        onchange=on_files />
+                    <label class="custom-file-label text-truncate" for=id>
+                        { if kind.is_multiple() { "Pick six faces" } else { "Pick an image" } }
+                    </label>
+                </div>
+                { match channel.summary() {
+                    Some(summary) => html! {
+                        <>
+                            <span class="text-muted text-monospace mr-2">{ summary }</span>
+                            <button
+                                class="btn btn-sm btn-outline-secondary"
+                                onclick=self.link.callback(move |_| Message::ClearChannel(index))>
+                                { "Clear" }
+                            </button>
+                        </>
+                    },
+                    None => html! {},
+                } }
+            </div>
+        }
+    }
+
+    /// A checkbox for each toggle the sources test, switched on ones are
+    /// defined as 1.
+    fn view_toggles(&self) -> Html {
+        if self.toggles.is_empty() {
+            return html! {};
+        }
+
+        let view_toggle = |name: &String| {
+            let on = self.project.toggles.contains(name);
+            let id = format!("toggle-{}", name);
+            let toggled = name.clone();
+
+            html! {
+                <div class="custom-control custom-checkbox custom-control-inline">
+                    <input
+                        type="checkbox"
+                        class="custom-control-input"
+                        id=id.clone()
+                        checked=on
+                        onclick=self.link.callback(move |_| Message::SetToggle(toggled.clone(), !on)) />
+                    <label class="custom-control-label text-monospace" for=id>{ name }</label>
+                </div>
+            }
+        };
+
+        html! {
+            <div class="mb-2 small">
+                <span class="mr-2">{ "Toggles" }</span>
+                { for self.toggles.iter().map(view_toggle) }
+            </div>
+        }
+    }
+
+    /// The snippet library, with an editor to add snippets or change the
+    /// built-in ones.
+    fn view_snippets(&self) -> Html {
+        let (name, source) = &self.snippet_draft;
+        let names: Vec<String> = self.snippets.names().map(str::to_string).collect();
+        let saved = names.contains(name);
+
+        // The first option starts a new snippet.
+        let options = names.clone();
+        let on_select = self.link.callback(move |change: ChangeData| {
+            let index = match change {
+                ChangeData::Select(select) => select.selected_index(),
+                _ => None,
+            };
+            let name = index.and_then(|index| (index as usize).checked_sub(1)).and_then(|index| options.get(index)).cloned();
+            Message::EditSnippet(name.unwrap_or_default())
+        });
+
+        html! {
+            <details class="mb-2 small">
+                <summary>{ "Snippets" }</summary>
+                <div class="form-inline my-1">
+                    <select class="custom-select custom-select-sm mr-2" onchange=on_select>
+                        <option selected={ !saved }>{ "New snippet" }</option>
+                        { for names.iter().map(|option| html! {
+                            <option selected={ option == name }>{ option }</option>
+                        }) }
+                    </select>
+                    <input
+                        type="text"
+                        class="form-control form-control-sm mr-2"
+                        placeholder="name.glsl"
+                        value=name.clone()
+                        oninput=self.link.callback(|input: InputData| Message::SetSnippetName(input.value)) />
+                    <button
+                        class="btn btn-sm btn-outline-secondary mr-2"
+                        title="Save the snippet to the library and recompile"
+                        onclick=self.link.callback(|_| Message::SaveSnippet)>
+                        { "Save" }
+                    </button>
+                    <button
+                        class="btn btn-sm btn-outline-secondary"
+                        disabled={ !saved }
+                        onclick=self.link.callback(|_| Message::RemoveSnippet)>
+                        { "Remove" }
+                    </button>
+                </div>
+                <textarea
+                    class="form-control form-control-sm text-monospace"
+                    rows="8"
+                    value=source.clone()
+                    oninput=self.link.callback(|input: InputData| Message::SetSnippetSource(input.value)) />
+                <span class="text-muted">{ format!("Include it with #include \"{}\"", if name.is_empty() { "name.glsl" } else { name.as_str() }) }</span>
+            </details>
+        }
+    }
+
+    /// The project's attribute aliases, with a form to make a shader
+    /// attribute read an entry of the mesh's vertex layout.
+    fn view_aliases(&self) -> Html {
+        let layout_names: Vec<String> = self.mesh
+            .as_ref()
+            .map(|mesh| mesh.layout.attributes.iter().map(|attribute| attribute.name.clone()).collect())
+            .unwrap_or_default();
+        let (name, layout) = &self.alias_draft;
+
+        let options = layout_names.clone();
+        let on_select = self.link.callback(move |change: ChangeData| {
+            let index = match change {
+                ChangeData::Select(select) => select.selected_index(),
+                _ => None,
+            };
+            let layout = index.and_then(|index| options.get(index as usize)).cloned();
+            Message::SetAliasLayout(layout.unwrap_or_default())
+        });
+
+        let view_alias = |(shader_name, layout_name): &(String, String)| {
+            let removed = shader_name.clone();
+            html! {
+                <li>
+                    <span class="text-monospace mr-2">{ format!("{} reads {}", shader_name, layout_name) }</span>
+                    <button
+                        class="btn btn-sm btn-link p-0"
+                        onclick=self.link.callback(move |_| Message::RemoveAlias(removed.clone()))>
+                        { "Remove" }
+                    </button>
+                </li>
+            }
+        };
+
+        html! {
+            <details class="mb-2 small">
+                <summary>{ "Attribute aliases" }</summary>
+                <ul class="list-unstyled my-1">
+                    { for self.project.attribute_aliases.iter().map(view_alias) }
+                </ul>
+                <div class="form-inline">
+                    <input
+                        type="text"
+                        class="form-control form-control-sm mr-2"
+                        placeholder="aShaderName"
+                        value=name.clone()
+                        oninput=self.link.callback(|input: InputData| Message::SetAliasName(input.value)) />
+                    <select class="custom-select custom-select-sm mr-2" onchange=on_select>
+                        { for layout_names.iter().enumerate().map(|(index, option)| html! {
+                            <option selected={ option == layout || (layout.is_empty() && index == 0) }>{ option }</option>
+                        }) }
+                    </select>
+                    <button
+                        class="btn btn-sm btn-outline-secondary"
+                        title="Make the shader attribute read this mesh data"
+                        disabled={ layout_names.is_empty() }
+                        onclick=self.link.callback(|_| Message::SaveAlias)>
+                        { "Add" }
+                    </button>
+                </div>
+            </details>
+        }
+    }
+
+    /// Playing and exporting the sound shader, with how much of it has been
+    /// rendered.
+    fn view_sound(&self) -> Html {
+        if self.project.sound.is_none() {
+            return html! {};
+        }
+
+        let playing = self.sound.as_ref().map_or(false, SoundShader::is_playing);
+        let (toggle, label) = if playing {
+            (self.link.callback(|_| Message::StopSound), "Stop")
+        } else {
+            (self.link.callback(|_| Message::PlaySound), "Play")
+        };
+
+        html! {
+            <div class="form-inline mb-2 small">
+                <label class="mr-2">{ "Sound" }</label>
+                <button class="btn btn-sm btn-outline-secondary mr-2" onclick=toggle>{ label }</button>
+                <button
+                    class="btn btn-sm btn-outline-secondary mr-3"
+                    title="Download the sound shader's output as a WAV file"
+                    onclick=self.link.callback(|_| Message::ExportSound)>
+                    { "Export WAV" }
+                </button>
+                { for self.sound.iter().map(|sound| html! {
+                    <span class="text-muted text-monospace">
+                        { format!("{:.1} of {} s rendered", sound.rendered(), sound::DURATION) }
+                    </span>
+                }) }
+            </div>
+        }
+    }
+
+    /// GPU frame time as a rolling graph, with min, average and max over the
+    /// graph and the average of each pass.
+    fn view_timings(&self) -> Html {
+        let timer = match &self.gpu_timer {
+            Some(timer) => timer,
+            None => return html! {},
+        };
+
+        let stats = match timer.stats() {
+            Some(stats) => stats,
+            None => return html! { <p class="small text-muted mb-2">{ "Waiting for GPU timings" }</p> },
+        };
+
+        let bar = |milliseconds: f64| {
+            let height = (milliseconds / stats.max.max(0.001) * 100.0).min(100.0);
+            html! {
+                <div class="bg-info" style={ format!("width: 2px; margin-right: 1px; height: {:.1}%;", height) }></div>
+            }
+        };
+
+        html! {
+            <div class="mb-2 small text-monospace">
+                <div class="d-flex align-items-end border-bottom" style="height: 40px;">
+                    { for timer.history().map(|frame| bar(frame.total)) }
+                </div>
+                <div>
+                    { format!("GPU min {:.2} ms, avg {:.2} ms, max {:.2} ms", stats.min, stats.avg, stats.max) }
+                </div>
+                { for timer.pass_averages().iter().map(|(name, milliseconds)| html! {
+                    <div class="text-muted">{ format!("{}: {:.2} ms", name, milliseconds) }</div>
+                }) }
+            </div>
+        }
+    }
+
+    /// Live WebGL objects by kind, to spot recompiles that leak.
+    fn view_objects(&self) -> Html {
+        let objects = owned::live_objects();
+        if objects.is_empty() {
+            return html! {};
+        }
+
+        let counts: Vec<String> = objects.iter().map(|(kind, count)| format!("{} {}", count, kind)).collect();
+        html! {
+            <p class="small text-muted text-monospace mb-2">{ format!("GPU objects: {}", counts.join(", ")) }</p>
+        }
+    }
+
+    fn view_gl_calls(&self) -> Html {
+        let calls = self.gl_calls;
+        if calls.issued + calls.skipped == 0 {
+            return html! {};
+        }
+
+        html! {
+            <p class="small text-muted text-monospace mb-2">
+                { format!("State changes per frame: {} made, {} skipped, in {} batches", calls.issued, calls.skipped, calls.batches) }
+            </p>
+        }
+    }
+
+    fn view_diagnostics(&self) -> Html {
+        let warnings = self.attributes
+            .as_ref()
+            .map(AttributeBindings::warnings)
+            .unwrap_or_default();
+
+        let view_diagnostic = |diagnostic: &Diagnostic| {
+            let class = match diagnostic.severity {
+                Severity::Error => "text-danger",
+                Severity::Warning => "text-warning",
+            };
+
+            html! { <li class=class>{ diagnostic.to_string() }</li> }
+        };
+
+        html! {
+            <ul class="list-unstyled mb-0 small text-monospace">
+                { for self.context_lost.iter().map(|reason| html! {
+                    <li class="text-warning">
+                        { "The WebGL context was lost, waiting for the browser to restore it" }
+                        { if reason.is_empty() { String::new() } else { format!(": {}", reason) } }
+                    </li>
+                }) }
+                { for self.diagnostics.iter().map(view_diagnostic) }
+                { for warnings.iter().map(|warning| html! { <li class="text-warning">{ warning }</li> }) }
+            </ul>
+        }
     }
 }
 
+enum Message {
+    Export,
+    Render(f64),
+    Resize,
+    ContextLost(String),
+    ContextRestored,
+    SetResolutionScale(f32),
+    ToggleAdaptiveResolution,
+    /// A mouse button went down at a position in canvas pixels.
+    MouseDown(f32, f32),
+    MouseMove(f32, f32),
+    MouseUp,
+    /// A key went down, by its key code.
+    KeyDown(u8),
+    KeyUp(u8),
+    ReleaseKeys,
+    PlayAudioFile(File),
+    ListenToMicrophone,
+    StopAudio,
+    AudioError(String),
+    /// Decodes image files into the channel at the index.
+    LoadChannel(usize, Vec<File>),
+    ChannelLoaded(usize, Vec<Image>),
+    ChannelError(String),
+    SetChannelKind(usize, channel::Kind),
+    ClearChannel(usize),
+    PlaySound,
+    StopSound,
+    ExportSound,
+    TogglePostEffect(usize),
+    /// Applies the effect at the index before the one above it.
+    MovePostEffect(usize),
+    /// Sets a parameter of the effect at the index.
+    SetPostParameter(usize, usize, f32),
+    /// Loads a snippet of the library into the snippet editor, or starts a
+    /// new one for an empty name.
+    EditSnippet(String),
+    SetSnippetName(String),
+    SetSnippetSource(String),
+    SaveSnippet,
+    RemoveSnippet,
+    SetToggle(String, bool),
+    SetAliasName(String),
+    SetAliasLayout(String),
+    /// Adds the drafted alias to the project and binds the attributes again.
+    SaveAlias,
+    RemoveAlias(String),
+}
+
 impl Component for State {
-    type Message = ();
+    type Message = Message;
     type Properties = ();
 
-    fn create(_: Self::Properties, _: ComponentLink<Self>) -> Self {
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
         Self {
+            link,
+            project: Project {
+                sound: Some(DEFAULT_SOUND.to_string()),
+                ..Project::new("netshader", DEFAULT_VERTEX, DEFAULT_FRAGMENT)
+            },
             canvas_ref: Default::default(),
             canvas: None,
             gl: None,
+            gl_calls: CallCounts::default(),
             shader_program: None,
-            vertex_position: None,
+            attributes: None,
+            alias_draft: (String::new(), String::new()),
             model_view_matrix: None,
             projection_matrix: None,
-            model: Box::new(Cube::new())
+            resolution_uniform: None,
+            mouse_uniform: None,
+            keyboard_uniform: None,
+            mouse: Mouse::new(),
+            keyboard: Keyboard::new(),
+            keyboard_texture: None,
+            audio: None,
+            sound: None,
+            audio_uniform: None,
+            audio_level_uniform: None,
+            audio_bands_uniform: None,
+            channel_uniforms: Vec::new(),
+            channels: (0..CHANNELS).map(|_| Channel::new()).collect(),
+            model: Box::new(Cube::new()),
+            mesh: None,
+            snippets: SnippetLibrary::new(),
+            snippet_draft: (String::new(), String::new()),
+            toggles: Vec::new(),
+            diagnostics: Vec::new(),
+            render_service: RenderService::new(),
+            render_task: None,
+            gpu_timer: None,
+            resolution: Resolution::new(),
+            render_target: None,
+            blitter: None,
+            post: PostStack::new(),
+            last_frame: None,
+            resize_task: None,
+            resize_observer: None,
+            registry: Registry::new(),
+            context_lost: None,
+            rebuild_on_restore: false,
         }
     }
 
     fn mounted(&mut self) -> ShouldRender {
         self.canvas = Some(self.canvas_ref.cast::<CanvasElement>().unwrap());
-        self.gl = Some(self.canvas.as_ref().unwrap().get_context().unwrap());
+        self.fit_canvas();
+        self.watch_size();
+        self.gl = Some(Gl::new(self.context()));
+        self.watch_context();
+        self.watch_input();
         self.gl.as_ref().unwrap().clear_color(0.0, 0.0, 0.0, 1.0);
         self.gl.as_ref().unwrap().clear(WebGLRenderingContext::COLOR_BUFFER_BIT);
 
-        self.shader_program = Some(shader::init(self.gl.as_ref().unwrap(), DEFAULT_VERTEX, DEFAULT_FRAGMENT).unwrap());
+        self.mesh = Some(Mesh::new(self.gl.as_ref().unwrap(), &mut self.registry, self.model.as_ref()).unwrap());
 
-        self.projection_matrix = Some(self.gl
-            .as_ref()
-            .unwrap()
-            .get_uniform_location(self.shader_program.as_ref().unwrap(), "uProjectionMatrix")
-            .unwrap());
+        // Compiling clears the diagnostics, so it goes before what adds to them.
+        self.rebuild_program();
 
-        self.model_view_matrix = Some(self.gl
-            .as_ref()
-            .unwrap()
-            .get_uniform_location(self.shader_program.as_ref().unwrap(), "uModelViewMatrix")
-            .unwrap());
+        match self.registry.create(self.gl.as_ref().unwrap(), self.keyboard.description()) {
+            Ok(texture) => self.keyboard_texture = Some(texture),
+            Err(error) => self.diagnostics.push(Diagnostic::warning(None, error)),
+        }
+        match AudioChannel::new(self.gl.as_ref().unwrap(), &mut self.registry) {
+            Ok(audio) => self.audio = Some(audio),
+            Err(error) => self.diagnostics.push(Diagnostic::warning(None, error)),
+        }
+
+        match Pass::blit(self.gl.as_ref().unwrap(), &mut self.registry) {
+            Ok(blitter) => self.blitter = Some(blitter),
+            Err(error) => self.diagnostics.push(Diagnostic::warning(None, error)),
+        }
 
-        self.render();
+        self.gpu_timer = GpuTimer::new(self.gl.as_ref().unwrap());
 
         true
     }
 
-    fn update(&mut self, _: Self::Message) -> ShouldRender {
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            Message::Export => self.export(),
+            Message::Render(timestamp) => {
+                let rescaled = self.adapt_resolution(timestamp);
+                let calls = self.gl_calls;
+                let rendering_sound = self.sound.as_ref().map_or(false, |sound| !sound.is_finished());
+                self.render();
+                self.request_frame();
+                let timings = self.gpu_timer.as_mut().map_or(false, GpuTimer::take_refresh);
+                // Only the timings, the render scale, the state changes and
+                // the sound's progress change between frames.
+                return timings || rescaled || self.gl_calls != calls || rendering_sound;
+            }
+            Message::Resize => return self.resize(),
+            Message::ContextLost(reason) => self.lose_context(reason),
+            Message::ContextRestored => self.restore_context(),
+            Message::SetResolutionScale(scale) => self.resolution.set_scale(scale),
+            Message::ToggleAdaptiveResolution => {
+                let adaptive = !self.resolution.adaptive();
+                self.resolution.set_adaptive(adaptive);
+            }
+            Message::TogglePostEffect(index) => {
+                if let Some(gl) = self.gl.as_ref() {
+                    if let Err(error) = self.post.toggle(gl, &mut self.registry, index) {
+                        self.diagnostics.push(Diagnostic::error(None, error));
+                    }
+                }
+            }
+            Message::MovePostEffect(index) => self.post.move_up(index),
+            // Read by the next frame, nothing to show until then.
+            Message::MouseDown(x, y) => {
+                self.mouse.press(x, y);
+                return false;
+            }
+            Message::MouseMove(x, y) => {
+                self.mouse.move_to(x, y);
+                return false;
+            }
+            Message::MouseUp => {
+                self.mouse.release();
+                return false;
+            }
+            Message::KeyDown(key) => {
+                self.keyboard.press(key);
+                return false;
+            }
+            Message::KeyUp(key) => {
+                self.keyboard.release(key);
+                return false;
+            }
+            Message::ReleaseKeys => {
+                self.keyboard.release_all();
+                return false;
+            }
+            Message::PlayAudioFile(file) => {
+                let error = self.link.callback(Message::AudioError);
+                if let Some(audio) = self.audio.as_mut() {
+                    audio.set_source(Some(AudioSource::file(&file, move |message| error.emit(message))));
+                }
+            }
+            Message::ListenToMicrophone => {
+                let error = self.link.callback(Message::AudioError);
+                if let Some(audio) = self.audio.as_mut() {
+                    audio.set_source(Some(AudioSource::microphone(move |message| error.emit(message))));
+                }
+            }
+            Message::StopAudio => {
+                if let Some(audio) = self.audio.as_mut() {
+                    audio.set_source(None);
+                }
+            }
+            Message::LoadChannel(_, files) if files.is_empty() => return false,
+            Message::LoadChannel(index, files) => {
+                let loaded = self.link.callback(move |images: Vec<Image>| Message::ChannelLoaded(index, images));
+                let error = self.link.callback(Message::ChannelError);
+                channel::decode(files, move |images| loaded.emit(images), move |message| error.emit(message));
+                return false;
+            }
+            Message::ChannelLoaded(index, images) => {
+                let gl = self.gl.as_ref().unwrap();
+                match self.channels[index].load(gl, &mut self.registry, images) {
+                    Ok(()) => self.check_channel(index),
+                    Err(error) => self.diagnostics.push(Diagnostic::error(None, error)),
+                }
+            }
+            Message::ChannelError(error) => self.diagnostics.push(Diagnostic::error(None, error)),
+            Message::SetChannelKind(index, kind) => self.channels[index].set_kind(kind),
+            Message::ClearChannel(index) => self.channels[index].clear(),
+            Message::PlaySound => {
+                // Rendered at the device's rate, so it plays as is.
+                let player = Player::new();
+                self.sound = self.compile_sound(player.sample_rate());
+                if let Some(sound) = self.sound.as_mut() {
+                    sound.set_player(Some(player));
+                }
+            }
+            Message::StopSound => self.sound = None,
+            Message::ExportSound => self.export_sound(),
+            Message::AudioError(error) => {
+                self.diagnostics.push(Diagnostic::error(None, error));
+                if let Some(audio) = self.audio.as_mut() {
+                    audio.set_source(None);
+                }
+            }
+            Message::SetPostParameter(index, parameter, value) => self.post.set(index, parameter, value),
+            Message::EditSnippet(name) => {
+                let source = self.snippets.get(&name).unwrap_or_default().to_string();
+                self.snippet_draft = (name, source);
+            }
+            // Kept by the inputs until the snippet is saved.
+            Message::SetSnippetName(name) => {
+                self.snippet_draft.0 = name;
+                return false;
+            }
+            Message::SetSnippetSource(source) => {
+                self.snippet_draft.1 = source;
+                return false;
+            }
+            Message::SaveSnippet => {
+                let name = self.snippet_draft.0.trim().to_string();
+                if name.is_empty() {
+                    return false;
+                }
+                self.snippets.insert(&name, &self.snippet_draft.1);
+                self.snippet_draft.0 = name;
+                self.rebuild_program();
+            }
+            Message::RemoveSnippet => {
+                self.snippets.remove(&self.snippet_draft.0);
+                self.snippet_draft = (String::new(), String::new());
+                self.rebuild_program();
+            }
+            Message::SetToggle(name, on) => {
+                self.project.set_toggle(&name, on);
+                self.rebuild_program();
+            }
+            // Kept by the inputs until the alias is saved.
+            Message::SetAliasName(name) => {
+                self.alias_draft.0 = name;
+                return false;
+            }
+            Message::SetAliasLayout(layout) => {
+                self.alias_draft.1 = layout;
+                return false;
+            }
+            Message::SaveAlias => {
+                let name = self.alias_draft.0.trim().to_string();
+                // An untouched select shows its first option.
+                let layout = match self.alias_draft.1.as_str() {
+                    "" => self.mesh.as_ref().and_then(|mesh| mesh.layout.attributes.first()).map(|attribute| attribute.name.clone()),
+                    layout => Some(layout.to_string()),
+                };
+                let layout = match (name.is_empty(), layout) {
+                    (false, Some(layout)) => layout,
+                    _ => return false,
+                };
+                self.project.set_attribute_alias(&name, &layout);
+                self.alias_draft = (String::new(), String::new());
+                self.bind_attributes();
+            }
+            Message::RemoveAlias(name) => {
+                self.project.remove_attribute_alias(&name);
+                self.bind_attributes();
+            }
+        }
         true
     }
 
@@ -200,10 +1630,29 @@ impl Component for State {
                     </div>
                     <div class="col-sm py-4 pl-0 pr-4">
                         <div class="h-25 pb-4">
-                            <div class="h-100 border rounded"></div>
+                            <div class="h-100 border rounded p-2 overflow-auto">
+                                <button
+                                    class="btn btn-sm btn-outline-secondary float-right ml-2"
+                                    title="Download the project as a standalone HTML page"
+                                    onclick=self.link.callback(|_| Message::Export)>
+                                    { "Export" }
+                                </button>
+                                { self.view_resolution() }
+                                { self.view_post_effects() }
+                                { self.view_toggles() }
+                                { self.view_snippets() }
+                                { self.view_aliases() }
+                                { self.view_audio() }
+                                { self.view_channels() }
+                                { self.view_sound() }
+                                { self.view_timings() }
+                                { self.view_objects() }
+                                { self.view_gl_calls() }
+                                { self.view_diagnostics() }
+                            </div>
                         </div>
                         <div class="h-75 border rounded">
-                            <canvas ref=self.canvas_ref.clone() class="h-100 w-100"></canvas>
+                            <canvas ref=self.canvas_ref.clone() id=CANVAS_ID tabindex="0" class="d-block h-100 w-100"></canvas>
                         </div>
                     </div>
                 </div>
@@ -222,6 +1671,62 @@ struct Response {
     value: String,
 }
 
+/// The GLSL vocabulary handed to the Ace completer and highlighter.
+#[derive(Debug, Serialize)]
+struct GlslVocabulary {
+    types: Vec<&'static str>,
+    qualifiers: Vec<&'static str>,
+    keywords: Vec<&'static str>,
+    variables: Vec<GlslVariable>,
+    uniforms: Vec<GlslUniform>,
+    functions: Vec<GlslFunction>,
+}
+
+#[derive(Debug, Serialize)]
+struct GlslVariable {
+    #[serde(rename = "type")]
+    type_: &'static str,
+    name: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct GlslUniform {
+    #[serde(rename = "type")]
+    type_: &'static str,
+    name: &'static str,
+    doc: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct GlslFunction {
+    name: &'static str,
+    signatures: Vec<&'static str>,
+}
+
+js_serializable!(GlslVocabulary);
+
+impl GlslVocabulary {
+    fn new() -> Self {
+        Self {
+            types: builtins::TYPES.to_vec(),
+            qualifiers: builtins::QUALIFIERS.to_vec(),
+            keywords: builtins::KEYWORDS.to_vec(),
+            variables: builtins::VARIABLES
+                .iter()
+                .map(|&(type_, name)| GlslVariable { type_, name })
+                .collect(),
+            uniforms: builtins::INJECTED_UNIFORMS
+                .iter()
+                .map(|&(type_, name, doc)| GlslUniform { type_, name, doc })
+                .collect(),
+            functions: builtins::FUNCTIONS
+                .iter()
+                .map(|&(name, signatures)| GlslFunction { name, signatures: signatures.to_vec() })
+                .collect(),
+        }
+    }
+}
+
 struct AceService;
 
 impl AceService {
@@ -230,24 +1735,50 @@ impl AceService {
     }
 
     pub fn edit(&mut self, id: &str) {
+        let vocabulary = GlslVocabulary::new();
+        let format = |source: String| format::format(&source);
+
         js! {
+            var format = @{format};
             Promise.resolve().then(() => {
                 window.ace = ace;
                 window.editor = window.ace.edit(@{id});
                 window.editor.setTheme("ace/theme/tomorrow_night");
-                window.editor.getSession().setMode("ace/mode/javascript");
+                window.netshaderGlsl.install(window.editor, @{vocabulary});
+                window.editor.commands.addCommand({
+                    name: "format",
+                    bindKey: { win: "Ctrl-Shift-F", mac: "Command-Shift-F" },
+                    exec: function (editor) {
+                        var cursor = editor.getCursorPosition();
+                        editor.setValue(format(editor.getValue()), -1);
+                        editor.moveCursorToPosition(cursor);
+                    }
+                });
                 window.editor.setValue("Hello World");
                 window.editor.clearSelection();
             });
         };
     }
+
+    /// Pretty-prints the source in an editor, as an undoable edit.
+    pub fn format(&mut self, id: &str) {
+        js! {
+            window.ace.edit(@{id}).execCommand("format");
+        };
+    }
 }
 
 struct EditorComponent {
+    link: ComponentLink<Self>,
+    ace: AceService,
     name: String,
     class: String,
 }
 
+enum EditorMessage {
+    Format,
+}
+
 #[derive(Clone, Properties)]
 struct EditorComponentProperties {
     name: String,
@@ -255,26 +1786,40 @@ struct EditorComponentProperties {
 }
 
 impl Component for EditorComponent {
-    type Message = ();
+    type Message = EditorMessage;
     type Properties = EditorComponentProperties;
 
-    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let mut ace = AceService::new();
         ace.edit(&props.name);
 
         Self {
+            link,
+            ace,
             name: props.name,
             class: props.class,
         }
     }
 
-    fn update(&mut self, _: Self::Message) -> ShouldRender {
-        true
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            EditorMessage::Format => self.ace.format(&self.name),
+        }
+        false
     }
 
     fn view(&self) -> Html {
         html! {
-            <div id={ &self.name } class={ &self.class }></div>
+            <div class={ format!("position-relative {}", self.class) }>
+                <div id={ &self.name } class="h-100 w-100"></div>
+                <button
+                    class="btn btn-sm btn-outline-secondary position-absolute"
+                    style="top: 0.5rem; right: 1.5rem; z-index: 10;"
+                    title="Format (Ctrl-Shift-F)"
+                    onclick=self.link.callback(|_| EditorMessage::Format)>
+                    { "Format" }
+                </button>
+            </div>
         }
     }
 }
@@ -1,16 +1,188 @@
-use crate::webgl_rendering_context::WebGLRenderingContext;
+use crate::gl_state::Gl;
+use crate::resources::{is_webgl2, Description, Registry, Resource};
+use crate::webgl_rendering_context::{GLenum, GLint, GLintptr, GLsizei, OESElementIndexUint, WebGLRenderingContext};
+
+/// Describes a single named attribute inside a vertex buffer, mirroring the
+/// arguments of `vertexAttribPointer`.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/vertexAttribPointer
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexAttribute {
+    /// Name of the shader attribute this data feeds, e.g. `aVertexPosition`.
+    pub name: String,
+    /// Number of components per vertex, 1 to 4.
+    pub size: GLint,
+    /// Component type, e.g. `FLOAT` or `UNSIGNED_BYTE`.
+    pub type_: GLenum,
+    /// Whether integer components are mapped to [0, 1] or [-1, 1].
+    pub normalized: bool,
+    /// Bytes between the start of consecutive vertices, 0 for tightly packed.
+    pub stride: GLsizei,
+    /// Bytes from the start of the buffer to the first component.
+    pub offset: GLintptr,
+}
+
+impl VertexAttribute {
+    /// Size in bytes of a single component of this attribute.
+    pub fn component_size(&self) -> usize {
+        match self.type_ {
+            WebGLRenderingContext::BYTE | WebGLRenderingContext::UNSIGNED_BYTE => 1,
+            WebGLRenderingContext::SHORT | WebGLRenderingContext::UNSIGNED_SHORT => 2,
+            _ => 4,
+        }
+    }
+
+    /// Distance in bytes between two consecutive vertices, resolving a zero
+    /// stride to the tightly packed size.
+    pub fn effective_stride(&self) -> usize {
+        if self.stride == 0 {
+            self.size as usize * self.component_size()
+        } else {
+            self.stride as usize
+        }
+    }
+}
+
+/// The set of attributes making up a model's vertex buffer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VertexLayout {
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self { attributes: Vec::new() }
+    }
+
+    /// Builds a layout where each float attribute occupies its own tightly
+    /// packed block of the buffer, one block after another.
+    ///
+    /// ## Arguments
+    ///
+    /// * `attributes` - Attribute names and their component counts, in buffer order.
+    /// * `vertex_count` - Number of vertices stored for every attribute.
+    pub fn planar(attributes: &[(&str, GLint)], vertex_count: usize) -> Self {
+        let mut layout = Self::new();
+        let mut offset = 0;
+
+        for (name, size) in attributes {
+            layout.attributes.push(VertexAttribute {
+                name: name.to_string(),
+                size: *size,
+                type_: WebGLRenderingContext::FLOAT,
+                normalized: false,
+                stride: 0,
+                offset,
+            });
+            offset += (*size as usize * 4 * vertex_count) as GLintptr;
+        }
+
+        layout
+    }
+
+    pub fn get(&self, name: &str) -> Option<&VertexAttribute> {
+        self.attributes.iter().find(|attribute| attribute.name == name)
+    }
+
+    /// Reads every vertex of a float attribute out of a raw vertex buffer.
+    ///
+    /// ## Arguments
+    ///
+    /// * `vertices` - Raw vertex data laid out as described by this layout.
+    /// * `name` - Name of the attribute to read.
+    /// * `vertex_count` - Number of vertices in the buffer.
+    pub fn read(&self, vertices: &[u8], name: &str, vertex_count: usize) -> Option<Vec<f32>> {
+        let attribute = self.get(name)?;
+        if attribute.type_ != WebGLRenderingContext::FLOAT {
+            return None;
+        }
+
+        let stride = attribute.effective_stride();
+        let mut values = Vec::with_capacity(vertex_count * attribute.size as usize);
+
+        for vertex in 0..vertex_count {
+            for component in 0..attribute.size as usize {
+                let start = attribute.offset as usize + vertex * stride + component * 4;
+                let bytes = vertices.get(start..start + 4)?;
+                values.push(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+            }
+        }
+
+        Some(values)
+    }
+}
+
+/// Index data for a model, stored at the narrowest width WebGL can draw.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    /// Picks `U16` whenever every index fits, falling back to `U32`.
+    pub fn from_u32(indices: Vec<u32>) -> Self {
+        if indices.iter().all(|&index| index <= u16::max_value() as u32) {
+            Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `type` argument to pass to `drawElements`.
+    pub fn type_(&self) -> GLenum {
+        match self {
+            Indices::U16(_) => WebGLRenderingContext::UNSIGNED_SHORT,
+            Indices::U32(_) => WebGLRenderingContext::UNSIGNED_INT,
+        }
+    }
+
+    /// The indices in the little endian byte order WebGL expects.
+    pub fn bytes(&self) -> Vec<u8> {
+        match self {
+            Indices::U16(indices) => indices.iter().flat_map(|index| index.to_le_bytes().to_vec()).collect(),
+            Indices::U32(indices) => indices.iter().flat_map(|index| index.to_le_bytes().to_vec()).collect(),
+        }
+    }
+
+    pub fn to_u32(&self) -> Vec<u32> {
+        match self {
+            Indices::U16(indices) => indices.iter().map(|&index| index as u32).collect(),
+            Indices::U32(indices) => indices.clone(),
+        }
+    }
+}
 
 pub trait Model {
-    fn positions(&self) -> Vec<f32>;
-    fn colors(&self) -> Vec<f32>;
-    fn indices(&self) -> Vec<u8>;
-    fn render(&self, gl: &WebGLRenderingContext);
+    /// Describes where each attribute lives inside `vertices`.
+    fn layout(&self) -> VertexLayout;
+    /// Raw vertex data, laid out as described by `layout`.
+    fn vertices(&self) -> Vec<u8>;
+    fn vertex_count(&self) -> usize;
+    fn indices(&self) -> Indices;
+}
+
+/// Serializes floats into the little endian byte order WebGL expects.
+pub fn f32_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect()
 }
 
 pub struct Cube {
     positions: [f32; 72],
     colors: [f32; 24],
-    indices: [u8; 36],
+    texture_coords: [f32; 48],
+    indices: [u16; 36],
 }
 
 impl Cube {
@@ -61,6 +233,38 @@ impl Cube {
                 1.0,  1.0,  0.0,  1.0,    // Right face: yellow
                 1.0,  0.0,  1.0,  1.0,    // Left face: purple
             ],
+            texture_coords: [
+                // Front
+                0.0,  0.0,
+                1.0,  0.0,
+                1.0,  1.0,
+                0.0,  1.0,
+                // Back
+                0.0,  0.0,
+                1.0,  0.0,
+                1.0,  1.0,
+                0.0,  1.0,
+                // Top
+                0.0,  0.0,
+                1.0,  0.0,
+                1.0,  1.0,
+                0.0,  1.0,
+                // Bottom
+                0.0,  0.0,
+                1.0,  0.0,
+                1.0,  1.0,
+                0.0,  1.0,
+                // Right
+                0.0,  0.0,
+                1.0,  0.0,
+                1.0,  1.0,
+                0.0,  1.0,
+                // Left
+                0.0,  0.0,
+                1.0,  0.0,
+                1.0,  1.0,
+                0.0,  1.0,
+            ],
             indices: [
                 0,  1,  2,      0,  2,  3,    // front
                 4,  5,  6,      4,  6,  7,    // back
@@ -74,20 +278,126 @@ impl Cube {
 }
 
 impl Model for Cube {
-    fn positions(&self) -> Vec<f32> {
-        self.positions.to_vec()
+    fn layout(&self) -> VertexLayout {
+        VertexLayout::planar(
+            &[("aVertexPosition", 3), ("aVertexColor", 4), ("aTextureCoord", 2)],
+            self.vertex_count()
+        )
     }
 
-    fn colors(&self) -> Vec<f32> {
-        self.colors.to_vec()
+    fn vertices(&self) -> Vec<u8> {
+        // Colors are stored per face, every vertex of a face shares its color.
+        let colors: Vec<f32> = self.colors
+            .chunks(4)
+            .flat_map(|color| color.iter().cycle().take(16).cloned().collect::<Vec<f32>>())
+            .collect();
+
+        let mut vertices = f32_bytes(&self.positions);
+        vertices.extend(f32_bytes(&colors));
+        vertices.extend(f32_bytes(&self.texture_coords));
+        vertices
     }
 
-    fn indices(&self) -> Vec<u8> {
-        self.indices.to_vec()
+    fn vertex_count(&self) -> usize {
+        self.positions.len() / 3
     }
 
-    fn render(&self, _gl: &WebGLRenderingContext) {
-        let _num_components = 3;  // todo(heyzoos): Should this be a struct property?
+    fn indices(&self) -> Indices {
+        Indices::U16(self.indices.to_vec())
     }
 }
 
+/// A model uploaded to the GPU, ready to be drawn.
+pub struct Mesh {
+    pub layout: VertexLayout,
+    pub vertex_buffer: Resource,
+    pub index_buffer: Resource,
+    pub index_count: GLsizei,
+    pub index_type: GLenum,
+}
+
+impl Mesh {
+    /// Uploads the vertex and index data of a model into new buffers.
+    ///
+    /// 32 bit indices need the `OES_element_index_uint` extension on WebGL1,
+    /// models that require it fail to upload when it is unavailable.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `registry` - Tracks the buffers so they can be restored with the context.
+    /// * `model` - Model to upload.
+    pub fn new(gl: &Gl, registry: &mut Registry, model: &dyn Model) -> Result<Self, String> {
+        let indices = model.indices();
+        enable_index_type(gl, indices.type_())?;
+
+        let vertex_buffer = registry.create(gl, Description::Buffer {
+            target: WebGLRenderingContext::ARRAY_BUFFER,
+            data: model.vertices(),
+            usage: WebGLRenderingContext::STATIC_DRAW,
+        })?;
+
+        let index_buffer = registry.create(gl, Description::Buffer {
+            target: WebGLRenderingContext::ELEMENT_ARRAY_BUFFER,
+            data: indices.bytes(),
+            usage: WebGLRenderingContext::STATIC_DRAW,
+        })?;
+
+        Ok(Self {
+            layout: model.layout(),
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as GLsizei,
+            index_type: indices.type_(),
+        })
+    }
+
+    /// Points a shader attribute at this mesh's data for the named layout entry.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `name` - Name of the attribute in the vertex layout.
+    /// * `location` - Attribute location in the linked program.
+    pub fn bind_attribute(&self, gl: &Gl, name: &str, location: u32) -> bool {
+        match self.layout.get(name) {
+            Some(attribute) => {
+                gl.bind_buffer(WebGLRenderingContext::ARRAY_BUFFER, self.vertex_buffer.buffer().as_ref());
+                gl.vertex_attrib_pointer(
+                    location,
+                    attribute.size,
+                    attribute.type_,
+                    attribute.normalized,
+                    attribute.stride,
+                    attribute.offset
+                );
+                gl.enable_vertex_attrib_array(location);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Enables the extensions the mesh needs again, extensions are off in a
+    /// restored context until asked for.
+    pub fn restore(&self, gl: &Gl) -> Result<(), String> {
+        enable_index_type(gl, self.index_type)
+    }
+
+    pub fn draw(&self, gl: &Gl) {
+        gl.bind_buffer(WebGLRenderingContext::ELEMENT_ARRAY_BUFFER, self.index_buffer.buffer().as_ref());
+        gl.draw_elements(WebGLRenderingContext::TRIANGLES, self.index_count, self.index_type, 0);
+    }
+}
+
+/// 32 bit indices need the `OES_element_index_uint` extension on WebGL1.
+fn enable_index_type(gl: &Gl, index_type: GLenum) -> Result<(), String> {
+    // WebGL2 draws them without the extension, and doesn't have it.
+    if index_type == WebGLRenderingContext::UNSIGNED_INT
+        && !is_webgl2(gl)
+        && gl.get_extension::<OESElementIndexUint>().is_none()
+    {
+        return Err(String::from("32 bit indices require the OES_element_index_uint extension"));
+    }
+    Ok(())
+}
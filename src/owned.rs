@@ -0,0 +1,103 @@
+use std::cell::RefCell;
+use std::ops::Deref;
+
+use crate::webgl_rendering_context::{
+    WebGLBuffer, WebGLFramebuffer, WebGLProgram, WebGLRenderbuffer, WebGLRenderingContext, WebGLShader, WebGLTexture,
+};
+
+thread_local! {
+    /// Number of objects of each kind currently alive, in the order kinds
+    /// were first created.
+    static LIVE: RefCell<Vec<(&'static str, usize)>> = RefCell::new(Vec::new());
+}
+
+/// A kind of WebGL object that has to be deleted explicitly.
+pub trait Deletable {
+    /// Name of the kind, as shown by `live_objects`.
+    const KIND: &'static str;
+
+    fn delete(&self, gl: &WebGLRenderingContext);
+}
+
+macro_rules! deletable {
+    ($type_:ty, $kind:expr, $delete:ident) => {
+        impl Deletable for $type_ {
+            const KIND: &'static str = $kind;
+
+            fn delete(&self, gl: &WebGLRenderingContext) {
+                gl.$delete(Some(self));
+            }
+        }
+    };
+}
+
+deletable!(WebGLShader, "shaders", delete_shader);
+deletable!(WebGLProgram, "programs", delete_program);
+deletable!(WebGLBuffer, "buffers", delete_buffer);
+deletable!(WebGLTexture, "textures", delete_texture);
+deletable!(WebGLRenderbuffer, "renderbuffers", delete_renderbuffer);
+deletable!(WebGLFramebuffer, "framebuffers", delete_framebuffer);
+
+/// A WebGL object that is deleted when dropped.
+///
+/// The bindings' types are plain references to JavaScript objects, which
+/// the garbage collector may free much later or, for programs still in use,
+/// never. Owning them makes the GPU memory go away with the Rust value.
+#[derive(Debug)]
+pub struct Owned<T: Deletable> {
+    gl: WebGLRenderingContext,
+    object: T,
+}
+
+impl<T: Deletable> Owned<T> {
+    /// Takes ownership of a newly created object.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context the object was created with.
+    /// * `object` - The object, which nothing else deletes.
+    pub fn new(gl: &WebGLRenderingContext, object: T) -> Self {
+        count(T::KIND, true);
+        Self { gl: gl.clone(), object }
+    }
+}
+
+impl<T: Deletable> Deref for Owned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.object
+    }
+}
+
+impl<T: Deletable> Drop for Owned<T> {
+    fn drop(&mut self) {
+        self.object.delete(&self.gl);
+        count(T::KIND, false);
+    }
+}
+
+fn count(kind: &'static str, created: bool) {
+    LIVE.with(|live| {
+        let mut live = live.borrow_mut();
+        let index = match live.iter().position(|(existing, _)| *existing == kind) {
+            Some(index) => index,
+            None => {
+                live.push((kind, 0));
+                live.len() - 1
+            }
+        };
+
+        if created {
+            live[index].1 += 1;
+        } else {
+            live[index].1 -= 1;
+        }
+    });
+}
+
+/// How many objects of each kind are alive. Numbers that keep growing as
+/// shaders are recompiled point at a leak.
+pub fn live_objects() -> Vec<(&'static str, usize)> {
+    LIVE.with(|live| live.borrow().clone())
+}
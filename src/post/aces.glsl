@@ -0,0 +1,14 @@
+// ACES filmic tonemapping, Krzysztof Narkowicz's fit of the reference curve.
+precision mediump float;
+uniform sampler2D uSource;
+uniform float uExposure;
+varying vec2 vTexCoord;
+
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec4 color = texture2D(uSource, vTexCoord);
+    gl_FragColor = vec4(aces(color.rgb * uExposure), color.a);
+}
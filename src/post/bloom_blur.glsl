@@ -0,0 +1,17 @@
+// One direction of a separable 9 tap gaussian blur, using bilinear filtering
+// to read two texels per tap.
+precision mediump float;
+uniform sampler2D uSource;
+uniform vec2 uResolution;
+uniform vec2 uDirection;
+varying vec2 vTexCoord;
+
+void main() {
+    vec2 texel = uDirection / uResolution;
+    vec3 color = texture2D(uSource, vTexCoord).rgb * 0.2270270270;
+    color += texture2D(uSource, vTexCoord + texel * 1.3846153846).rgb * 0.3162162162;
+    color += texture2D(uSource, vTexCoord - texel * 1.3846153846).rgb * 0.3162162162;
+    color += texture2D(uSource, vTexCoord + texel * 3.2307692308).rgb * 0.0702702703;
+    color += texture2D(uSource, vTexCoord - texel * 3.2307692308).rgb * 0.0702702703;
+    gl_FragColor = vec4(color, 1.0);
+}
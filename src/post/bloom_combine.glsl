@@ -0,0 +1,12 @@
+// Adds the blurred highlights back onto the image.
+precision mediump float;
+uniform sampler2D uSource;
+uniform sampler2D uBloom;
+uniform float uIntensity;
+varying vec2 vTexCoord;
+
+void main() {
+    vec4 color = texture2D(uSource, vTexCoord);
+    vec3 bloom = texture2D(uBloom, vTexCoord).rgb;
+    gl_FragColor = vec4(color.rgb + bloom * uIntensity, color.a);
+}
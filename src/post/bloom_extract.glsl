@@ -0,0 +1,16 @@
+// Keeps what's brighter than the threshold, with a soft knee, at the lower
+// resolution of the target it's drawn into.
+precision mediump float;
+uniform sampler2D uSource;
+uniform float uThreshold;
+varying vec2 vTexCoord;
+
+void main() {
+    vec3 color = texture2D(uSource, vTexCoord).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float knee = uThreshold * 0.5;
+    float soft = clamp(brightness - uThreshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.0001);
+    float contribution = max(soft, brightness - uThreshold) / max(brightness, 0.0001);
+    gl_FragColor = vec4(color * contribution, 1.0);
+}
@@ -0,0 +1,15 @@
+// Samples red and blue further from and closer to the center than green,
+// like a lens that doesn't focus all wavelengths at once.
+precision mediump float;
+uniform sampler2D uSource;
+uniform vec2 uResolution;
+uniform float uStrength;
+varying vec2 vTexCoord;
+
+void main() {
+    vec2 offset = (vTexCoord - 0.5) * uStrength / uResolution.x * 2.0;
+    vec4 color = texture2D(uSource, vTexCoord);
+    float red = texture2D(uSource, vTexCoord + offset).r;
+    float blue = texture2D(uSource, vTexCoord - offset).b;
+    gl_FragColor = vec4(red, color.g, blue, color.a);
+}
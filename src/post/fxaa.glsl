@@ -0,0 +1,42 @@
+// FXAA, the reduced quality version of Timothy Lottes' algorithm that only
+// searches along the edge once. Meant for gamma encoded colors, so it goes
+// after tonemapping and gamma.
+precision mediump float;
+uniform sampler2D uSource;
+uniform vec2 uResolution;
+uniform float uSpan;
+uniform float uReduce;
+varying vec2 vTexCoord;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+
+void main() {
+    vec2 texel = 1.0 / uResolution;
+    vec3 luma = vec3(0.299, 0.587, 0.114);
+
+    float nw = dot(texture2D(uSource, vTexCoord + vec2(-1.0, -1.0) * texel).rgb, luma);
+    float ne = dot(texture2D(uSource, vTexCoord + vec2(1.0, -1.0) * texel).rgb, luma);
+    float sw = dot(texture2D(uSource, vTexCoord + vec2(-1.0, 1.0) * texel).rgb, luma);
+    float se = dot(texture2D(uSource, vTexCoord + vec2(1.0, 1.0) * texel).rgb, luma);
+    vec4 center = texture2D(uSource, vTexCoord);
+    float m = dot(center.rgb, luma);
+
+    float lumaMin = min(m, min(min(nw, ne), min(sw, se)));
+    float lumaMax = max(m, max(max(nw, ne), max(sw, se)));
+
+    vec2 direction = vec2(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+    float reduce = max((nw + ne + sw + se) * 0.25 * uReduce, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2(-uSpan), vec2(uSpan)) * texel;
+
+    vec3 a = 0.5 * (
+        texture2D(uSource, vTexCoord + direction * (1.0 / 3.0 - 0.5)).rgb +
+        texture2D(uSource, vTexCoord + direction * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 b = a * 0.5 + 0.25 * (
+        texture2D(uSource, vTexCoord + direction * -0.5).rgb +
+        texture2D(uSource, vTexCoord + direction * 0.5).rgb);
+
+    float lumaB = dot(b, luma);
+    vec3 color = (lumaB < lumaMin || lumaB > lumaMax) ? a : b;
+    gl_FragColor = vec4(color, center.a);
+}
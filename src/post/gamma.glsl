@@ -0,0 +1,10 @@
+// Encodes linear colors with a plain power curve.
+precision mediump float;
+uniform sampler2D uSource;
+uniform float uGamma;
+varying vec2 vTexCoord;
+
+void main() {
+    vec4 color = texture2D(uSource, vTexCoord);
+    gl_FragColor = vec4(pow(max(color.rgb, 0.0), vec3(1.0 / uGamma)), color.a);
+}
@@ -0,0 +1,21 @@
+// Film grain, noise that changes every frame and shows most in the midtones.
+precision mediump float;
+uniform sampler2D uSource;
+uniform vec2 uResolution;
+uniform float uTime;
+uniform float uIntensity;
+varying vec2 vTexCoord;
+
+float hash(vec3 p) {
+    p = fract(p * 0.1031);
+    p += dot(p, p.zyx + 31.32);
+    return fract((p.x + p.y) * p.z);
+}
+
+void main() {
+    vec4 color = texture2D(uSource, vTexCoord);
+    float noise = hash(vec3(floor(vTexCoord * uResolution), floor(uTime * 24.0))) - 0.5;
+    float luma = dot(color.rgb, vec3(0.299, 0.587, 0.114));
+    float amount = uIntensity * (1.0 - abs(luma * 2.0 - 1.0) * 0.5);
+    gl_FragColor = vec4(color.rgb + noise * amount, color.a);
+}
@@ -0,0 +1,449 @@
+use nalgebra_glm::{self as glm, Vec2};
+
+use crate::gl_state::Gl;
+use crate::render_target::{Pass, RenderTarget};
+use crate::resources::Registry;
+use crate::uniform::Uniform;
+use crate::webgl_rendering_context::{WebGLRenderingContext, WebGLTexture};
+
+const ACES: &str = include_str!("aces.glsl");
+const REINHARD: &str = include_str!("reinhard.glsl");
+const GAMMA: &str = include_str!("gamma.glsl");
+const SRGB: &str = include_str!("srgb.glsl");
+const BLOOM_EXTRACT: &str = include_str!("bloom_extract.glsl");
+const BLOOM_BLUR: &str = include_str!("bloom_blur.glsl");
+const BLOOM_COMBINE: &str = include_str!("bloom_combine.glsl");
+const FXAA: &str = include_str!("fxaa.glsl");
+const CHROMATIC_ABERRATION: &str = include_str!("chromatic_aberration.glsl");
+const VIGNETTE: &str = include_str!("vignette.glsl");
+const GRAIN: &str = include_str!("grain.glsl");
+
+/// Samplers every effect program may declare, the image being processed and
+/// for bloom's last pass the blurred highlights.
+const SAMPLERS: &[&str] = &["uSource", "uBloom"];
+
+/// A built-in full-screen effect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Bloom,
+    Aces,
+    Reinhard,
+    ChromaticAberration,
+    Gamma,
+    Srgb,
+    Fxaa,
+    Vignette,
+    Grain,
+}
+
+/// A value of an effect the UI offers a slider for, uploaded to the float
+/// uniform `uniform`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub uniform: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    pub default: f32,
+}
+
+const fn parameter(name: &'static str, uniform: &'static str, min: f32, max: f32, step: f32, default: f32) -> Parameter {
+    Parameter { name, uniform, min, max, step, default }
+}
+
+const EXPOSURE: Parameter = parameter("Exposure", "uExposure", 0.0, 4.0, 0.05, 1.0);
+
+const BLOOM_PARAMETERS: &[Parameter] = &[
+    parameter("Threshold", "uThreshold", 0.0, 1.0, 0.01, 0.8),
+    parameter("Intensity", "uIntensity", 0.0, 3.0, 0.05, 1.0),
+];
+
+const REINHARD_PARAMETERS: &[Parameter] = &[EXPOSURE, parameter("White point", "uWhite", 0.5, 8.0, 0.1, 2.0)];
+
+/// The strength is how many pixels the colors are apart at the sides.
+const CHROMATIC_ABERRATION_PARAMETERS: &[Parameter] = &[parameter("Strength", "uStrength", 0.0, 10.0, 0.1, 2.0)];
+
+const GAMMA_PARAMETERS: &[Parameter] = &[parameter("Gamma", "uGamma", 1.0, 3.0, 0.05, 2.2)];
+
+const FXAA_PARAMETERS: &[Parameter] = &[
+    parameter("Span", "uSpan", 1.0, 16.0, 1.0, 8.0),
+    parameter("Reduce", "uReduce", 0.0, 0.5, 0.005, 0.125),
+];
+
+const VIGNETTE_PARAMETERS: &[Parameter] = &[
+    parameter("Strength", "uStrength", 0.0, 1.0, 0.01, 0.5),
+    parameter("Radius", "uRadius", 0.2, 1.0, 0.01, 0.75),
+    parameter("Softness", "uSoftness", 0.05, 1.0, 0.01, 0.45),
+];
+
+const GRAIN_PARAMETERS: &[Parameter] = &[parameter("Intensity", "uIntensity", 0.0, 0.5, 0.005, 0.08)];
+
+impl Kind {
+    /// Every effect, in the order a new stack applies them: bloom and
+    /// tonemapping on linear colors, then encoding them, then what should
+    /// happen to the final image.
+    pub const ALL: &'static [Kind] = &[
+        Kind::Bloom,
+        Kind::Aces,
+        Kind::Reinhard,
+        Kind::ChromaticAberration,
+        Kind::Gamma,
+        Kind::Srgb,
+        Kind::Fxaa,
+        Kind::Vignette,
+        Kind::Grain,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Bloom => "Bloom",
+            Kind::Aces => "ACES tonemapping",
+            Kind::Reinhard => "Reinhard tonemapping",
+            Kind::ChromaticAberration => "Chromatic aberration",
+            Kind::Gamma => "Gamma",
+            Kind::Srgb => "sRGB",
+            Kind::Fxaa => "FXAA",
+            Kind::Vignette => "Vignette",
+            Kind::Grain => "Film grain",
+        }
+    }
+
+    pub fn parameters(self) -> &'static [Parameter] {
+        match self {
+            Kind::Bloom => BLOOM_PARAMETERS,
+            Kind::Aces => &[EXPOSURE],
+            Kind::Reinhard => REINHARD_PARAMETERS,
+            Kind::ChromaticAberration => CHROMATIC_ABERRATION_PARAMETERS,
+            Kind::Gamma => GAMMA_PARAMETERS,
+            Kind::Srgb => &[],
+            Kind::Fxaa => FXAA_PARAMETERS,
+            Kind::Vignette => VIGNETTE_PARAMETERS,
+            Kind::Grain => GRAIN_PARAMETERS,
+        }
+    }
+
+    /// Fragment shaders of the effect's passes. The last one draws the
+    /// result, bloom's others draw into its own smaller targets.
+    fn fragments(self) -> &'static [&'static str] {
+        match self {
+            Kind::Bloom => &[BLOOM_EXTRACT, BLOOM_BLUR, BLOOM_COMBINE],
+            Kind::Aces => &[ACES],
+            Kind::Reinhard => &[REINHARD],
+            Kind::ChromaticAberration => &[CHROMATIC_ABERRATION],
+            Kind::Gamma => &[GAMMA],
+            Kind::Srgb => &[SRGB],
+            Kind::Fxaa => &[FXAA],
+            Kind::Vignette => &[VIGNETTE],
+            Kind::Grain => &[GRAIN],
+        }
+    }
+}
+
+/// One of an effect's passes with the uniforms the stack sets, `None` where
+/// the shader doesn't use them.
+struct Program {
+    pass: Pass,
+    resolution: Option<Uniform<Vec2>>,
+    direction: Option<Uniform<Vec2>>,
+    time: Option<Uniform<f32>>,
+    parameters: Vec<Option<Uniform<f32>>>,
+}
+
+impl Program {
+    fn new(gl: &Gl, registry: &mut Registry, fragment: &str, parameters: &[Parameter]) -> Result<Self, String> {
+        let pass = Pass::new(gl, registry, fragment, SAMPLERS)?;
+        let program = pass.program().clone();
+
+        Ok(Self {
+            resolution: Uniform::new(gl, registry, &program, "uResolution")?,
+            direction: Uniform::new(gl, registry, &program, "uDirection")?,
+            time: Uniform::new(gl, registry, &program, "uTime")?,
+            parameters: parameters
+                .iter()
+                .map(|parameter| Uniform::new(gl, registry, &program, parameter.uniform))
+                .collect::<Result<_, _>>()?,
+            pass,
+        })
+    }
+
+    /// Draws over the current viewport.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `textures` - The image being processed and, for bloom, the highlights.
+    /// * `size` - Size of the first texture in pixels.
+    /// * `direction` - Direction bloom's blur runs in.
+    /// * `time` - Seconds since the page loaded.
+    /// * `values` - Values of the effect's parameters.
+    fn draw(&mut self, gl: &Gl, textures: &[&WebGLTexture], size: (i32, i32), direction: Vec2, time: f32, values: &[f32]) {
+        let Self { pass, resolution, direction: direction_uniform, time: time_uniform, parameters } = self;
+
+        pass.draw(gl, textures, |gl| {
+            if let Some(uniform) = resolution.as_mut() {
+                uniform.set(gl, glm::vec2(size.0 as f32, size.1 as f32));
+            }
+            if let Some(uniform) = direction_uniform.as_mut() {
+                uniform.set(gl, direction);
+            }
+            if let Some(uniform) = time_uniform.as_mut() {
+                uniform.set(gl, time);
+            }
+            for (uniform, &value) in parameters.iter_mut().zip(values) {
+                if let Some(uniform) = uniform.as_mut() {
+                    uniform.set(gl, value);
+                }
+            }
+        });
+    }
+}
+
+/// An effect of the stack and its settings.
+pub struct Effect {
+    kind: Kind,
+    enabled: bool,
+    values: Vec<f32>,
+    /// Compiled the first time the effect is enabled.
+    programs: Vec<Program>,
+}
+
+impl Effect {
+    fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            enabled: false,
+            values: kind.parameters().iter().map(|parameter| parameter.default).collect(),
+            programs: Vec::new(),
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Values of the parameters, in the order of `Kind::parameters`.
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+}
+
+/// Full-screen effects applied in order to the rendered scene, the last one
+/// drawing onto the canvas.
+///
+/// Effects read the previous one's result and draw into one of two render
+/// targets of the scene's size, taking turns. Bloom blurs at half that size
+/// in targets of its own. The targets hold 8 bits per channel, so
+/// tonemapping works on colors already clamped to 1, which the exposure can
+/// make up for.
+pub struct PostStack {
+    effects: Vec<Effect>,
+    targets: Vec<RenderTarget>,
+    bloom_targets: Vec<RenderTarget>,
+}
+
+impl PostStack {
+    /// A stack of every effect, all disabled.
+    pub fn new() -> Self {
+        Self {
+            effects: Kind::ALL.iter().map(|&kind| Effect::new(kind)).collect(),
+            targets: Vec::new(),
+            bloom_targets: Vec::new(),
+        }
+    }
+
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    /// Whether any effect is enabled, the scene has to be rendered offscreen
+    /// for them.
+    pub fn is_active(&self) -> bool {
+        self.effects.iter().any(|effect| effect.enabled)
+    }
+
+    /// Enables or disables an effect, compiling its programs the first time
+    /// it's enabled. An effect that doesn't compile stays disabled.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `registry` - Tracks the effect's resources so they can be restored with the context.
+    /// * `index` - Position of the effect in the stack.
+    pub fn toggle(&mut self, gl: &Gl, registry: &mut Registry, index: usize) -> Result<(), String> {
+        let effect = match self.effects.get_mut(index) {
+            Some(effect) => effect,
+            None => return Ok(()),
+        };
+
+        if !effect.enabled && effect.programs.is_empty() {
+            let parameters = effect.kind.parameters();
+            effect.programs = effect
+                .kind
+                .fragments()
+                .iter()
+                .map(|fragment| Program::new(gl, registry, fragment, parameters))
+                .collect::<Result<_, _>>()
+                .map_err(|error| format!("Unable to compile the {} effect: {}", effect.kind.name(), error))?;
+        }
+
+        effect.enabled = !effect.enabled;
+        Ok(())
+    }
+
+    /// Disables every effect, after they failed to render.
+    pub fn disable(&mut self) {
+        for effect in self.effects.iter_mut() {
+            effect.enabled = false;
+        }
+    }
+
+    /// Moves an effect one step earlier in the stack.
+    pub fn move_up(&mut self, index: usize) {
+        if index > 0 && index < self.effects.len() {
+            self.effects.swap(index - 1, index);
+        }
+    }
+
+    /// Sets one of an effect's parameters, clamped to its range.
+    pub fn set(&mut self, index: usize, parameter: usize, value: f32) {
+        if let Some(effect) = self.effects.get_mut(index) {
+            if let (Some(range), Some(stored)) = (effect.kind.parameters().get(parameter), effect.values.get_mut(parameter)) {
+                *stored = value.max(range.min).min(range.max);
+            }
+        }
+    }
+
+    /// Applies the enabled effects to an image, drawing the result onto the
+    /// canvas. Fails when the targets can't be created, leaving the canvas
+    /// in whatever state the effects before got it to.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `registry` - Tracks the targets so they can be restored with the context.
+    /// * `source` - The rendered scene.
+    /// * `size` - Width and height of the scene in pixels.
+    /// * `canvas` - Width and height of the canvas' drawing buffer, which the last effect draws onto.
+    /// * `time` - Seconds since the page loaded, for the effects that change over time.
+    pub fn render(
+        &mut self,
+        gl: &Gl,
+        registry: &mut Registry,
+        source: &WebGLTexture,
+        size: (i32, i32),
+        canvas: (i32, i32),
+        time: f32,
+    ) -> Result<(), String> {
+        let (width, height) = size;
+        let Self { effects, targets, bloom_targets } = self;
+        let enabled: Vec<&mut Effect> = effects.iter_mut().filter(|effect| effect.enabled).collect();
+        if enabled.is_empty() {
+            return Ok(());
+        }
+
+        // Every effect but the last needs somewhere to draw, two are enough
+        // to take turns.
+        fit_targets(targets, gl, registry, (enabled.len() - 1).min(2), width, height)?;
+        if enabled.iter().any(|effect| effect.kind == Kind::Bloom) {
+            fit_targets(bloom_targets, gl, registry, 2, (width / 2).max(1), (height / 2).max(1))?;
+        }
+
+        let count = enabled.len();
+        let mut source = source.clone();
+        for (position, effect) in enabled.into_iter().enumerate() {
+            // `None` while the context is lost, there's nothing to draw then.
+            let bloom = match effect.kind {
+                Kind::Bloom => match bloom(gl, effect, bloom_targets, &source, (width, height), time) {
+                    Some(bloom) => Some(bloom),
+                    None => return Ok(()),
+                },
+                _ => None,
+            };
+
+            let output = if position + 1 == count {
+                gl.bind_framebuffer(WebGLRenderingContext::FRAMEBUFFER, None);
+                gl.viewport(0, 0, canvas.0, canvas.1);
+                None
+            } else {
+                let target = &targets[position % 2];
+                target.bind(gl);
+                Some(target)
+            };
+
+            let mut textures = vec![&source];
+            textures.extend(bloom.as_ref());
+            let direction = glm::vec2(0.0, 0.0);
+            effect.programs.last_mut().unwrap().draw(gl, &textures, (width, height), direction, time, &effect.values);
+
+            if let Some(target) = output {
+                source = match target.texture() {
+                    Some(texture) => texture,
+                    None => return Ok(()),
+                };
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for PostStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Makes sure there are `count` targets of the given size.
+fn fit_targets(
+    targets: &mut Vec<RenderTarget>,
+    gl: &Gl,
+    registry: &mut Registry,
+    count: usize,
+    width: i32,
+    height: i32,
+) -> Result<(), String> {
+    for target in targets.iter_mut() {
+        target.resize(gl, width, height);
+    }
+    while targets.len() < count {
+        targets.push(RenderTarget::new(gl, registry, width, height)?);
+    }
+    Ok(())
+}
+
+/// Draws bloom's highlights, extracted from `source` at half the size and
+/// blurred horizontally then vertically, and returns the texture they end up
+/// in.
+fn bloom(
+    gl: &Gl,
+    effect: &mut Effect,
+    targets: &[RenderTarget],
+    source: &WebGLTexture,
+    size: (i32, i32),
+    time: f32,
+) -> Option<WebGLTexture> {
+    let (first, second) = (&targets[0], &targets[1]);
+    let half = (first.width(), first.height());
+    let (extract, blur) = match &mut effect.programs[..] {
+        [extract, blur, _] => (extract, blur),
+        _ => return None,
+    };
+
+    first.bind(gl);
+    extract.draw(gl, &[source], size, glm::vec2(0.0, 0.0), time, &effect.values);
+
+    let highlights = first.texture()?;
+    second.bind(gl);
+    blur.draw(gl, &[&highlights], half, glm::vec2(1.0, 0.0), time, &effect.values);
+
+    let blurred = second.texture()?;
+    first.bind(gl);
+    blur.draw(gl, &[&blurred], half, glm::vec2(0.0, 1.0), time, &effect.values);
+
+    Some(highlights)
+}
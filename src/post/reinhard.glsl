@@ -0,0 +1,13 @@
+// Reinhard tonemapping, with a white point that maps to 1.
+precision mediump float;
+uniform sampler2D uSource;
+uniform float uExposure;
+uniform float uWhite;
+varying vec2 vTexCoord;
+
+void main() {
+    vec4 color = texture2D(uSource, vTexCoord);
+    vec3 x = color.rgb * uExposure;
+    vec3 mapped = x * (1.0 + x / (uWhite * uWhite)) / (1.0 + x);
+    gl_FragColor = vec4(mapped, color.a);
+}
@@ -0,0 +1,16 @@
+// Encodes linear colors with the exact sRGB transfer function.
+precision mediump float;
+uniform sampler2D uSource;
+varying vec2 vTexCoord;
+
+vec3 linearToSrgb(vec3 c) {
+    c = clamp(c, 0.0, 1.0);
+    vec3 low = c * 12.92;
+    vec3 high = 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055;
+    return mix(low, high, step(vec3(0.0031308), c));
+}
+
+void main() {
+    vec4 color = texture2D(uSource, vTexCoord);
+    gl_FragColor = vec4(linearToSrgb(color.rgb), color.a);
+}
@@ -0,0 +1,15 @@
+// Darkens the corners, round regardless of the aspect ratio.
+precision mediump float;
+uniform sampler2D uSource;
+uniform vec2 uResolution;
+uniform float uStrength;
+uniform float uRadius;
+uniform float uSoftness;
+varying vec2 vTexCoord;
+
+void main() {
+    vec4 color = texture2D(uSource, vTexCoord);
+    vec2 position = (vTexCoord - 0.5) * vec2(uResolution.x / uResolution.y, 1.0);
+    float vignette = 1.0 - smoothstep(uRadius - uSoftness, uRadius, length(position));
+    gl_FragColor = vec4(color.rgb * mix(1.0, vignette, uStrength), color.a);
+}
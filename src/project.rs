@@ -0,0 +1,113 @@
+use crate::attributes::AttributeAliases;
+use crate::glsl::{Preprocessor, SnippetLibrary};
+
+/// A value for a uniform the app sets, kept with the project so exports
+/// render the same way the editor does.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Int(i32),
+    Bool(bool),
+    Mat3([f32; 9]),
+    Mat4([f32; 16]),
+}
+
+/// An image bound to a sampler uniform.
+///
+/// Images are kept as `data:` URLs, so a project never depends on files
+/// that only exist on the machine it was made on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    /// Name of the sampler uniform, e.g. `iChannel0`.
+    pub uniform: String,
+    pub data_url: String,
+}
+
+/// Everything needed to reproduce what the editor shows: the shader sources,
+/// the textures they sample and the values of their uniforms.
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub name: String,
+    pub vertex: String,
+    pub fragment: String,
+    /// Source of the sound shader, defining `vec2 mainSound(float time)`.
+    pub sound: Option<String>,
+    /// Toggles switched on, `#define`d as 1 before the sources.
+    pub toggles: Vec<String>,
+    pub textures: Vec<Texture>,
+    pub uniforms: Vec<(String, UniformValue)>,
+    /// Attribute names of the shaders and the vertex layout entries they
+    /// read, on top of the built-in ones of `AttributeAliases`.
+    pub attribute_aliases: Vec<(String, String)>,
+}
+
+impl Project {
+    pub fn new(name: &str, vertex: &str, fragment: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            vertex: vertex.to_string(),
+            fragment: fragment.to_string(),
+            sound: None,
+            toggles: Vec::new(),
+            textures: Vec::new(),
+            uniforms: Vec::new(),
+            attribute_aliases: Vec::new(),
+        }
+    }
+
+    /// Sets the value of a uniform, replacing any previous value.
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) {
+        match self.uniforms.iter_mut().find(|(existing, _)| existing == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.uniforms.push((name.to_string(), value)),
+        }
+    }
+
+    /// Switches a toggle on or off.
+    pub fn set_toggle(&mut self, name: &str, on: bool) {
+        self.toggles.retain(|toggle| toggle != name);
+        if on {
+            self.toggles.push(name.to_string());
+        }
+    }
+
+    /// A preprocessor for the project's sources, with its toggles defined.
+    pub fn preprocessor<'a>(&self, snippets: &'a SnippetLibrary) -> Preprocessor<'a> {
+        self.toggles
+            .iter()
+            .fold(Preprocessor::new(snippets), |preprocessor, toggle| preprocessor.define(toggle, "1"))
+    }
+
+    /// Makes a shader attribute read a vertex layout entry, replacing what
+    /// it read before.
+    pub fn set_attribute_alias(&mut self, shader_name: &str, layout_name: &str) {
+        match self.attribute_aliases.iter_mut().find(|(existing, _)| existing == shader_name) {
+            Some((_, existing)) => *existing = layout_name.to_string(),
+            None => self.attribute_aliases.push((shader_name.to_string(), layout_name.to_string())),
+        }
+    }
+
+    pub fn remove_attribute_alias(&mut self, shader_name: &str) {
+        self.attribute_aliases.retain(|(existing, _)| existing != shader_name);
+    }
+
+    /// The built-in aliases with the project's own on top.
+    pub fn aliases(&self) -> AttributeAliases {
+        let mut aliases = AttributeAliases::new();
+        for (shader_name, layout_name) in &self.attribute_aliases {
+            aliases.insert(shader_name, layout_name);
+        }
+        aliases
+    }
+
+    /// Binds an image to a sampler uniform, replacing any previous image.
+    pub fn set_texture(&mut self, uniform: &str, data_url: &str) {
+        match self.textures.iter_mut().find(|texture| texture.uniform == uniform) {
+            Some(texture) => texture.data_url = data_url.to_string(),
+            None => self.textures.push(Texture { uniform: uniform.to_string(), data_url: data_url.to_string() }),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use crate::gl_state::Gl;
+use crate::models::f32_bytes;
+use crate::resources::{Description, Registry, Resource};
+use crate::uniform::{TextureUnit, Uniform};
+use crate::webgl_rendering_context::{WebGLRenderingContext, WebGLTexture};
+
+const FULLSCREEN_VERTEX: &str = r#"attribute vec2 aPosition;
+varying vec2 vTexCoord;
+void main() {
+    vTexCoord = aPosition * 0.5 + 0.5;
+    gl_Position = vec4(aPosition, 0.0, 1.0);
+}
+"#;
+
+const BLIT_FRAGMENT: &str = r#"precision mediump float;
+uniform sampler2D uSource;
+varying vec2 vTexCoord;
+void main() {
+    gl_FragColor = texture2D(uSource, vTexCoord);
+}
+"#;
+
+/// A single triangle covering the whole viewport, cheaper than a quad since
+/// no pixels along the diagonal are shaded twice.
+const FULLSCREEN_TRIANGLE: [f32; 6] = [-1.0, -1.0, 3.0, -1.0, -1.0, 3.0];
+
+/// Where the only attribute of full-screen passes is bound.
+const POSITION_LOCATION: u32 = 0;
+
+/// An offscreen image to render into, a color texture with a depth buffer.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/WebGLFramebuffer
+pub struct RenderTarget {
+    framebuffer: Resource,
+    texture: Resource,
+    depth: Resource,
+    width: i32,
+    height: i32,
+}
+
+impl RenderTarget {
+    /// Creates a render target, failing if the browser can't render into
+    /// textures of this size.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `registry` - Tracks the target's resources so they can be restored with the context.
+    /// * `width` - Width in pixels.
+    /// * `height` - Height in pixels.
+    pub fn new(gl: &Gl, registry: &mut Registry, width: i32, height: i32) -> Result<Self, String> {
+        let texture = registry.create(gl, color_description(width, height))?;
+        let depth = registry.create(gl, depth_description(width, height))?;
+
+        let framebuffer = registry
+            .create(gl, Description::Framebuffer { color: Some(texture.clone()), depth: Some(depth.clone()) })
+            .map_err(|error| format!("Unable to render into a {}x{} texture: {}", width, height, error))?;
+
+        Ok(Self { framebuffer, texture, depth, width, height })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// The color texture, `None` while the context is lost.
+    pub fn texture(&self) -> Option<WebGLTexture> {
+        self.texture.texture()
+    }
+
+    /// Reallocates the color and depth storage if the size changed, the
+    /// previous contents are lost.
+    pub fn resize(&mut self, gl: &Gl, width: i32, height: i32) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+
+        // Both are always of the kind `update` accepts.
+        self.texture.update(gl, color_description(width, height)).unwrap();
+        self.depth.update(gl, depth_description(width, height)).unwrap();
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Directs drawing into this target and sets the viewport to cover it.
+    pub fn bind(&self, gl: &Gl) {
+        gl.bind_framebuffer(WebGLRenderingContext::FRAMEBUFFER, self.framebuffer.framebuffer().as_ref());
+        gl.viewport(0, 0, self.width, self.height);
+    }
+}
+
+fn color_description(width: i32, height: i32) -> Description {
+    // Textures of any size need clamping and no mipmaps on WebGL1.
+    Description::Texture {
+        width,
+        height,
+        filter: WebGLRenderingContext::LINEAR,
+        wrap: WebGLRenderingContext::CLAMP_TO_EDGE,
+        pixels: None,
+    }
+}
+
+fn depth_description(width: i32, height: i32) -> Description {
+    Description::Renderbuffer { format: WebGLRenderingContext::DEPTH_COMPONENT16, width, height }
+}
+
+/// A program drawn over the whole viewport, reading from textures. The
+/// vertex shader passes the texture coordinate `vTexCoord` to `fragment`.
+pub struct Pass {
+    program: Resource,
+    buffer: Resource,
+    samplers: Vec<Option<Uniform<TextureUnit>>>,
+}
+
+impl Pass {
+    /// Compiles a full-screen pass.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `registry` - Tracks the pass' resources so they can be restored with the context.
+    /// * `fragment` - Source of the fragment shader.
+    /// * `samplers` - Names of the sampler uniforms, read from texture units 0, 1 and so on.
+    pub fn new(gl: &Gl, registry: &mut Registry, fragment: &str, samplers: &[&str]) -> Result<Self, String> {
+        let program = registry.create(gl, Description::Program {
+            vertex: FULLSCREEN_VERTEX.to_string(),
+            fragment: fragment.to_string(),
+            attributes: vec![(POSITION_LOCATION, String::from("aPosition"))],
+        })?;
+
+        let buffer = registry.create(gl, Description::Buffer {
+            target: WebGLRenderingContext::ARRAY_BUFFER,
+            data: f32_bytes(&FULLSCREEN_TRIANGLE),
+            usage: WebGLRenderingContext::STATIC_DRAW,
+        })?;
+
+        let samplers = samplers
+            .iter()
+            .map(|name| Uniform::new(gl, registry, &program, name))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { program, buffer, samplers })
+    }
+
+    /// Copies a texture onto whatever framebuffer is bound, stretching it
+    /// over the viewport with bilinear filtering.
+    ///
+    /// Upscaling a smaller image this way is what makes rendering at a lower
+    /// resolution useful, and at twice the size each pixel averages four
+    /// texels, a cheap form of supersampling.
+    pub fn blit(gl: &Gl, registry: &mut Registry) -> Result<Self, String> {
+        Self::new(gl, registry, BLIT_FRAGMENT, &["uSource"])
+    }
+
+    /// The pass' program, to look up its uniforms.
+    pub fn program(&self) -> &Resource {
+        &self.program
+    }
+
+    /// Draws over the current viewport.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `textures` - Textures for the samplers, in the order they were named.
+    /// * `set_uniforms` - Sets the program's other uniforms, it's in use by then.
+    pub fn draw(&mut self, gl: &Gl, textures: &[&WebGLTexture], set_uniforms: impl FnOnce(&Gl)) {
+        gl.disable(WebGLRenderingContext::DEPTH_TEST);
+        gl.use_program(self.program.program().as_ref());
+
+        gl.bind_buffer(WebGLRenderingContext::ARRAY_BUFFER, self.buffer.buffer().as_ref());
+        gl.vertex_attrib_pointer(POSITION_LOCATION, 2, WebGLRenderingContext::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(POSITION_LOCATION);
+
+        for (unit, (texture, sampler)) in textures.iter().zip(self.samplers.iter_mut()).enumerate() {
+            gl.active_texture(WebGLRenderingContext::TEXTURE0 + unit as u32);
+            gl.bind_texture(WebGLRenderingContext::TEXTURE_2D, Some(*texture));
+            if let Some(sampler) = sampler.as_mut() {
+                sampler.set(gl, TextureUnit(unit as i32));
+            }
+        }
+        set_uniforms(gl);
+
+        gl.draw_arrays(WebGLRenderingContext::TRIANGLES, 0, 3);
+
+        // The scene's program may not use this location, leave it as found.
+        gl.disable_vertex_attrib_array(POSITION_LOCATION);
+    }
+}
@@ -0,0 +1,289 @@
+/// Scales offered in the UI, relative to the canvas' size in device pixels.
+pub const SCALES: &[f32] = &[0.25, 0.5, 0.75, 1.0, 1.5, 2.0];
+
+pub const MIN_SCALE: f32 = 0.25;
+pub const MAX_SCALE: f32 = 2.0;
+
+/// Frame time the adaptive mode aims for, in milliseconds.
+pub const DEFAULT_BUDGET: f64 = 1000.0 / 60.0;
+
+/// Frames to wait after a change before judging the new scale, so frames
+/// already in flight and the smoothing don't trigger another change.
+const SETTLE_FRAMES: u32 = 30;
+
+/// Frames within budget before trying a higher scale again. Doubles every
+/// time a higher scale turns out to be too slow, up to `MAX_PROBE_FRAMES`.
+const PROBE_FRAMES: u32 = 120;
+const MAX_PROBE_FRAMES: u32 = 1920;
+
+/// Frame times above this are pauses, e.g. a background tab, not slow frames.
+const MAX_FRAME_TIME: f64 = 250.0;
+
+/// Weight of the newest frame in the smoothed frame time.
+const SMOOTHING: f64 = 0.1;
+
+/// How many pixels the scene is rendered at, relative to the canvas.
+///
+/// A scale of 1 renders one pixel per device pixel, so high DPI screens get
+/// sharp output, below 1 renders fewer pixels and upscales the result and
+/// above 1 supersamples.
+///
+/// In adaptive mode the scale is lowered further whenever the smoothed frame
+/// time goes over budget. Cost grows with the number of pixels, so the scale
+/// is cut by the square root of how far over budget a frame is. Once frames
+/// are back within budget it creeps back up towards the chosen scale.
+#[derive(Debug, Clone)]
+pub struct Resolution {
+    scale: f32,
+    adaptive: bool,
+    budget: f64,
+    /// Fraction of `scale` the adaptive mode currently renders at, 1 when
+    /// it's off.
+    factor: f32,
+    average: Option<f64>,
+    frames: u32,
+    probe_frames: u32,
+    /// Factor before trying a higher one, to go back to if it's too slow.
+    probed_from: Option<f32>,
+}
+
+impl Resolution {
+    pub fn new() -> Self {
+        Self {
+            scale: 1.0,
+            adaptive: false,
+            budget: DEFAULT_BUDGET,
+            factor: 1.0,
+            average: None,
+            frames: 0,
+            probe_frames: PROBE_FRAMES,
+            probed_from: None,
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets the scale, clamped to `MIN_SCALE` and `MAX_SCALE`, and restarts
+    /// the adaptive mode from it.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+        self.reset();
+    }
+
+    pub fn adaptive(&self) -> bool {
+        self.adaptive
+    }
+
+    pub fn set_adaptive(&mut self, adaptive: bool) {
+        self.adaptive = adaptive;
+        self.reset();
+    }
+
+    pub fn budget(&self) -> f64 {
+        self.budget
+    }
+
+    /// Sets the frame time the adaptive mode aims for, in milliseconds.
+    pub fn set_budget(&mut self, budget: f64) {
+        self.budget = budget.max(1.0);
+        self.reset();
+    }
+
+    /// The scale frames are currently rendered at, including the adaptive
+    /// mode's reduction.
+    pub fn effective_scale(&self) -> f32 {
+        (self.scale * self.factor).max(MIN_SCALE)
+    }
+
+    /// Size of the image to render for a canvas of the given size.
+    ///
+    /// ## Arguments
+    ///
+    /// * `width` - Width of the canvas' drawing buffer, in pixels.
+    /// * `height` - Height of the canvas' drawing buffer, in pixels.
+    pub fn render_size(&self, width: i32, height: i32) -> (i32, i32) {
+        let scale = self.effective_scale();
+        let scaled = |size: i32| ((size as f32 * scale).round() as i32).max(1);
+        (scaled(width), scaled(height))
+    }
+
+    /// Feeds the time the last frame took to the adaptive mode.
+    ///
+    /// Returns whether the effective scale changed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `frame_time` - GPU time of the frame if it's known, otherwise the
+    ///   time between frames, in milliseconds.
+    pub fn update(&mut self, frame_time: f64) -> bool {
+        if !self.adaptive || !frame_time.is_finite() || frame_time <= 0.0 || frame_time > MAX_FRAME_TIME {
+            return false;
+        }
+
+        let average = match self.average {
+            Some(average) => average + (frame_time - average) * SMOOTHING,
+            None => frame_time,
+        };
+        self.average = Some(average);
+        self.frames += 1;
+
+        if self.frames < SETTLE_FRAMES {
+            return false;
+        }
+
+        let min_factor = MIN_SCALE / self.scale;
+        let probed_from = self.probed_from.take();
+        if probed_from.is_some() && average <= self.budget * 1.15 {
+            self.probe_frames = PROBE_FRAMES;
+        }
+        let factor = if average > self.budget * 1.15 {
+            match probed_from {
+                // A higher scale that doesn't hold up is tried less often.
+                Some(factor) => {
+                    self.probe_frames = (self.probe_frames * 2).min(MAX_PROBE_FRAMES);
+                    factor
+                }
+                None => self.factor * ((self.budget / average).sqrt() as f32) * 0.95,
+            }
+        } else if average < self.budget * 0.75 {
+            self.factor * ((self.budget * 0.9 / average).sqrt() as f32).min(1.25)
+        } else if self.frames >= self.probe_frames && self.factor < 1.0 {
+            // Frames between vsyncs never drop below the budget, the only
+            // way to find out whether there's room is to try.
+            self.probed_from = Some(self.factor);
+            self.factor * 1.1
+        } else {
+            return false;
+        };
+
+        let factor = factor.max(min_factor).min(1.0);
+        if (factor - self.factor).abs() < 0.01 {
+            return false;
+        }
+
+        self.factor = factor;
+        self.average = None;
+        self.frames = 0;
+        true
+    }
+
+    fn reset(&mut self) {
+        self.factor = 1.0;
+        self.average = None;
+        self.frames = 0;
+        self.probe_frames = PROBE_FRAMES;
+        self.probed_from = None;
+    }
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames slow enough to get the scale cut.
+    const SLOW: f64 = 40.0;
+    /// Frames within budget, but not fast enough to raise the scale.
+    const ON_BUDGET: f64 = 16.0;
+
+    fn adaptive() -> Resolution {
+        let mut resolution = Resolution::new();
+        resolution.set_adaptive(true);
+        resolution
+    }
+
+    /// Feeds the same frame time a number of times, returning after which
+    /// frames the effective scale changed, counting from 1.
+    fn feed(resolution: &mut Resolution, frame_time: f64, frames: u32) -> Vec<u32> {
+        (1..=frames).filter(|_| resolution.update(frame_time)).collect()
+    }
+
+    #[test]
+    fn ignores_frame_times_when_not_adaptive() {
+        let mut resolution = Resolution::new();
+
+        assert_eq!(feed(&mut resolution, SLOW, 100), vec![]);
+        assert_eq!(resolution.effective_scale(), 1.0);
+    }
+
+    #[test]
+    fn waits_for_frames_to_settle_before_changing() {
+        let mut resolution = adaptive();
+
+        assert_eq!(feed(&mut resolution, SLOW, 40), vec![SETTLE_FRAMES]);
+    }
+
+    #[test]
+    fn cuts_by_the_square_root_of_the_overshoot() {
+        let mut resolution = adaptive();
+        feed(&mut resolution, SLOW, SETTLE_FRAMES);
+
+        let expected = (DEFAULT_BUDGET / SLOW).sqrt() as f32 * 0.95;
+        assert!((resolution.effective_scale() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn never_goes_below_the_minimum_scale() {
+        let mut resolution = adaptive();
+        feed(&mut resolution, MAX_FRAME_TIME, 1000);
+
+        assert_eq!(resolution.effective_scale(), MIN_SCALE);
+    }
+
+    #[test]
+    fn ignores_pauses() {
+        let mut resolution = adaptive();
+
+        assert_eq!(feed(&mut resolution, MAX_FRAME_TIME + 1.0, 100), vec![]);
+        // Pauses don't count towards settling either.
+        assert_eq!(feed(&mut resolution, SLOW, SETTLE_FRAMES), vec![SETTLE_FRAMES]);
+    }
+
+    #[test]
+    fn probes_less_often_after_a_failed_probe() {
+        let mut resolution = adaptive();
+        feed(&mut resolution, SLOW, SETTLE_FRAMES);
+        let cut = resolution.effective_scale();
+
+        assert_eq!(feed(&mut resolution, ON_BUDGET, PROBE_FRAMES), vec![PROBE_FRAMES]);
+        assert!(resolution.effective_scale() > cut);
+
+        // Too slow at the higher scale, back to where it was.
+        assert_eq!(feed(&mut resolution, SLOW, SETTLE_FRAMES), vec![SETTLE_FRAMES]);
+        assert_eq!(resolution.effective_scale(), cut);
+
+        assert_eq!(feed(&mut resolution, ON_BUDGET, PROBE_FRAMES * 2), vec![PROBE_FRAMES * 2]);
+    }
+
+    #[test]
+    fn probes_as_often_again_after_a_successful_probe() {
+        let mut resolution = adaptive();
+        feed(&mut resolution, SLOW, SETTLE_FRAMES);
+        feed(&mut resolution, ON_BUDGET, PROBE_FRAMES);
+        feed(&mut resolution, SLOW, SETTLE_FRAMES);
+        let probe = resolution.effective_scale() * 1.1;
+
+        feed(&mut resolution, ON_BUDGET, PROBE_FRAMES * 2);
+        assert_eq!(resolution.effective_scale(), probe);
+
+        assert_eq!(feed(&mut resolution, ON_BUDGET, PROBE_FRAMES), vec![PROBE_FRAMES]);
+    }
+
+    #[test]
+    fn raises_the_scale_when_well_under_budget() {
+        let mut resolution = adaptive();
+        feed(&mut resolution, SLOW, SETTLE_FRAMES);
+        let cut = resolution.effective_scale();
+
+        assert_eq!(feed(&mut resolution, 5.0, SETTLE_FRAMES), vec![SETTLE_FRAMES]);
+        assert!(resolution.effective_scale() > cut);
+        assert!(resolution.effective_scale() <= 1.0);
+    }
+}
@@ -0,0 +1,420 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+#[cfg(not(feature = "web-sys-backend"))]
+use stdweb::unstable::TryInto;
+#[cfg(not(feature = "web-sys-backend"))]
+use stdweb::web::TypedArray;
+
+use crate::gl_state::Gl;
+use crate::owned::Owned;
+use crate::shader;
+use crate::webgl_rendering_context::{
+    GLenum, WebGL2RenderingContext, WebGLBuffer, WebGLFramebuffer, WebGLProgram, WebGLRenderbuffer,
+    WebGLRenderingContext, WebGLTexture, WebGLUniformLocation,
+};
+
+/// Everything needed to create a GPU resource again. The GPU's copy is gone
+/// once the context is lost, so this is kept on the CPU side next to it.
+#[derive(Debug, Clone)]
+pub enum Description {
+    /// A buffer holding `data`.
+    Buffer { target: GLenum, data: Vec<u8>, usage: GLenum },
+    /// A 2D RGBA texture without mipmaps, `pixels` is left uninitialized when `None`.
+    Texture { width: i32, height: i32, filter: GLenum, wrap: GLenum, pixels: Option<Vec<u8>> },
+    /// A linearly filtered RGBA cubemap without mipmaps, `faces` in the
+    /// order of `TEXTURE_CUBE_MAP_POSITIVE_X` and the targets after it.
+    Cubemap { size: i32, faces: Vec<Vec<u8>> },
+    /// A cubic 3D RGBA texture without mipmaps, slice after slice. Only
+    /// WebGL2 contexts have them.
+    Volume { size: i32, filter: GLenum, wrap: GLenum, voxels: Vec<u8> },
+    Renderbuffer { format: GLenum, width: i32, height: i32 },
+    /// A framebuffer rendering into a texture and a depth renderbuffer,
+    /// which are recreated before it since they were created before it.
+    Framebuffer { color: Option<Resource>, depth: Option<Resource> },
+    /// A program linked from preprocessed sources, with attributes bound to
+    /// fixed locations.
+    Program { vertex: String, fragment: String, attributes: Vec<(u32, String)> },
+    /// Location of a uniform, only valid for the program object it was
+    /// looked up in.
+    UniformLocation { program: Resource, name: String },
+}
+
+/// A WebGL object of any of the kinds a `Description` creates.
+#[derive(Debug)]
+pub enum Object {
+    Buffer(Owned<WebGLBuffer>),
+    Texture(Owned<WebGLTexture>),
+    Renderbuffer(Owned<WebGLRenderbuffer>),
+    Framebuffer(Owned<WebGLFramebuffer>),
+    Program(Owned<WebGLProgram>),
+    UniformLocation(WebGLUniformLocation),
+}
+
+#[derive(Debug)]
+struct Entry {
+    description: Description,
+    /// `None` while the context is lost.
+    object: Option<Object>,
+    /// How many times the object was restored.
+    generation: u32,
+}
+
+/// A shared handle to a GPU resource tracked by a `Registry`.
+///
+/// Owners keep the handle rather than the WebGL object, so after the context
+/// is restored they use the new object without knowing it was replaced. The
+/// object is deleted along with the last handle.
+#[derive(Debug, Clone)]
+pub struct Resource(Rc<RefCell<Entry>>);
+
+macro_rules! object_accessor {
+    ($name:ident, $variant:ident, $type_:ty) => {
+        /// The WebGL object, `None` while the context is lost or if the
+        /// resource is of another kind.
+        pub fn $name(&self) -> Option<$type_> {
+            match &self.0.borrow().object {
+                Some(Object::$variant(object)) => Some((**object).clone()),
+                _ => None,
+            }
+        }
+    };
+}
+
+impl Resource {
+    object_accessor!(buffer, Buffer, WebGLBuffer);
+    object_accessor!(texture, Texture, WebGLTexture);
+    object_accessor!(renderbuffer, Renderbuffer, WebGLRenderbuffer);
+    object_accessor!(framebuffer, Framebuffer, WebGLFramebuffer);
+    object_accessor!(program, Program, WebGLProgram);
+
+    /// The uniform's location, `None` while the context is lost or if the
+    /// resource is of another kind. Locations are never deleted, so unlike
+    /// the other objects they aren't `Owned`.
+    pub fn uniform_location(&self) -> Option<WebGLUniformLocation> {
+        match &self.0.borrow().object {
+            Some(Object::UniformLocation(location)) => Some(location.clone()),
+            _ => None,
+        }
+    }
+
+    /// Changes every time the object is restored, so anything derived from
+    /// the old object, like uniform values set on a program, can tell it's
+    /// stale.
+    pub fn generation(&self) -> u32 {
+        self.0.borrow().generation
+    }
+
+    pub fn description(&self) -> Description {
+        self.0.borrow().description.clone()
+    }
+
+    /// Replaces the contents of a buffer, or the size and pixels of a texture
+    /// or renderbuffer, keeping the object.
+    ///
+    /// While the context is lost only the description changes, the resource
+    /// is restored with the new contents.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `description` - New description, of the same kind as the current one.
+    pub fn update(&self, gl: &Gl, description: Description) -> Result<(), String> {
+        let mut entry = self.0.borrow_mut();
+
+        match (&entry.object, &description) {
+            (None, _) => {}
+            (Some(Object::Buffer(buffer)), Description::Buffer { target, data, usage }) => {
+                upload_buffer(gl, buffer, *target, data, *usage);
+            }
+            (Some(Object::Texture(texture)), Description::Texture { width, height, pixels, .. }) => {
+                bind_for_upload(gl, WebGLRenderingContext::TEXTURE_2D, texture);
+                upload_texture(gl, WebGLRenderingContext::TEXTURE_2D, *width, *height, pixels.as_ref());
+            }
+            (Some(Object::Renderbuffer(renderbuffer)), Description::Renderbuffer { format, width, height }) => {
+                allocate_renderbuffer(gl, renderbuffer, *format, *width, *height);
+            }
+            _ => return Err(String::from("Only buffers, textures and renderbuffers can be updated in place")),
+        }
+
+        entry.description = description;
+        Ok(())
+    }
+}
+
+/// Keeps track of every GPU resource the app creates along with its
+/// description, so all of them can be created again when the browser
+/// restores a lost context.
+///
+/// Resources are restored in the order they were created, so anything a
+/// resource refers to, like a framebuffer's attachments or a uniform's
+/// program, exists by the time it's restored. Resources whose handles were
+/// all dropped are forgotten.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/isContextLost
+#[derive(Default)]
+pub struct Registry {
+    entries: Vec<Weak<RefCell<Entry>>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    /// Creates a resource from its description and starts tracking it.
+    pub fn create(&mut self, gl: &Gl, description: Description) -> Result<Resource, String> {
+        let object = build(gl, &description)?;
+        Ok(self.insert(description, object))
+    }
+
+    /// Starts tracking an object that was created elsewhere, e.g. a program
+    /// whose compile errors need reporting against the original sources.
+    pub fn insert(&mut self, description: Description, object: Object) -> Resource {
+        self.entries.retain(|entry| entry.strong_count() > 0);
+
+        let entry = Rc::new(RefCell::new(Entry { description, object: Some(object), generation: 0 }));
+        self.entries.push(Rc::downgrade(&entry));
+        Resource(entry)
+    }
+
+    /// Number of resources currently tracked.
+    pub fn len(&self) -> usize {
+        self.live().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live().next().is_none()
+    }
+
+    /// Forgets every WebGL object, they all died with the context.
+    pub fn lose(&mut self) {
+        for entry in self.live() {
+            entry.borrow_mut().object = None;
+        }
+    }
+
+    /// Recreates every resource after the context was restored.
+    ///
+    /// Returns the errors of resources that couldn't be created again, those
+    /// stay empty.
+    pub fn restore(&mut self, gl: &Gl) -> Vec<String> {
+        self.entries.retain(|entry| entry.strong_count() > 0);
+        let mut errors = Vec::new();
+
+        for entry in self.live() {
+            let description = entry.borrow().description.clone();
+            match build(gl, &description) {
+                Ok(object) => {
+                    let mut entry = entry.borrow_mut();
+                    entry.object = Some(object);
+                    entry.generation += 1;
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+
+        errors
+    }
+
+    fn live<'a>(&'a self) -> impl Iterator<Item = Rc<RefCell<Entry>>> + 'a {
+        self.entries.iter().filter_map(Weak::upgrade)
+    }
+}
+
+/// Texture unit textures are bound to while they're made or updated. Draws
+/// never sample from it, so an upload in the middle of a frame doesn't
+/// replace a texture bound for sampling. WebGL guarantees 8 units.
+const UPLOAD_UNIT: GLenum = WebGLRenderingContext::TEXTURE0 + 7;
+
+fn bind_for_upload(gl: &Gl, target: GLenum, texture: &WebGLTexture) {
+    gl.active_texture(UPLOAD_UNIT);
+    gl.bind_texture(target, Some(texture));
+}
+
+fn build(gl: &Gl, description: &Description) -> Result<Object, String> {
+    match description {
+        Description::Buffer { target, data, usage } => {
+            let buffer = Owned::new(gl, gl.create_buffer().ok_or("Unable to create a buffer")?);
+            upload_buffer(gl, &buffer, *target, data, *usage);
+            Ok(Object::Buffer(buffer))
+        }
+        Description::Texture { width, height, filter, wrap, pixels } => {
+            let texture = Owned::new(gl, gl.create_texture().ok_or("Unable to create a texture")?);
+            bind_for_upload(gl, WebGLRenderingContext::TEXTURE_2D, &texture);
+            set_sampling(gl, WebGLRenderingContext::TEXTURE_2D, *filter, *wrap);
+            upload_texture(gl, WebGLRenderingContext::TEXTURE_2D, *width, *height, pixels.as_ref());
+            Ok(Object::Texture(texture))
+        }
+        Description::Cubemap { size, faces } => {
+            if faces.len() != 6 {
+                return Err(format!("A cubemap has six faces, not {}", faces.len()));
+            }
+
+            let texture = Owned::new(gl, gl.create_texture().ok_or("Unable to create a texture")?);
+            bind_for_upload(gl, WebGLRenderingContext::TEXTURE_CUBE_MAP, &texture);
+            set_sampling(
+                gl,
+                WebGLRenderingContext::TEXTURE_CUBE_MAP,
+                WebGLRenderingContext::LINEAR,
+                WebGLRenderingContext::CLAMP_TO_EDGE
+            );
+            for (index, face) in faces.iter().enumerate() {
+                let target = WebGLRenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X + index as GLenum;
+                upload_texture(gl, target, *size, *size, Some(face));
+            }
+            Ok(Object::Texture(texture))
+        }
+        Description::Volume { size, filter, wrap, voxels } => {
+            // Binding to `TEXTURE_3D` is already an error without WebGL2.
+            if !is_webgl2(gl) {
+                return Err(String::from("3D textures need a WebGL2 context"));
+            }
+
+            let texture = Owned::new(gl, gl.create_texture().ok_or("Unable to create a texture")?);
+            bind_for_upload(gl, WebGL2RenderingContext::TEXTURE_3D, &texture);
+            set_sampling(gl, WebGL2RenderingContext::TEXTURE_3D, *filter, *wrap);
+            gl.tex_parameteri(WebGL2RenderingContext::TEXTURE_3D, WebGL2RenderingContext::TEXTURE_WRAP_R, *wrap as i32);
+            upload_volume(gl, *size, voxels);
+            Ok(Object::Texture(texture))
+        }
+        Description::Renderbuffer { format, width, height } => {
+            let renderbuffer = Owned::new(gl, gl.create_renderbuffer().ok_or("Unable to create a renderbuffer")?);
+            allocate_renderbuffer(gl, &renderbuffer, *format, *width, *height);
+            Ok(Object::Renderbuffer(renderbuffer))
+        }
+        Description::Framebuffer { color, depth } => {
+            let framebuffer = Owned::new(gl, gl.create_framebuffer().ok_or("Unable to create a framebuffer")?);
+            gl.bind_framebuffer(WebGLRenderingContext::FRAMEBUFFER, Some(&*framebuffer));
+
+            if let Some(color) = color {
+                gl.framebuffer_texture2_d(
+                    WebGLRenderingContext::FRAMEBUFFER,
+                    WebGLRenderingContext::COLOR_ATTACHMENT0,
+                    WebGLRenderingContext::TEXTURE_2D,
+                    color.texture().as_ref(),
+                    0
+                );
+            }
+            if let Some(depth) = depth {
+                gl.framebuffer_renderbuffer(
+                    WebGLRenderingContext::FRAMEBUFFER,
+                    WebGLRenderingContext::DEPTH_ATTACHMENT,
+                    WebGLRenderingContext::RENDERBUFFER,
+                    depth.renderbuffer().as_ref()
+                );
+            }
+
+            let status = gl.check_framebuffer_status(WebGLRenderingContext::FRAMEBUFFER);
+            gl.bind_framebuffer(WebGLRenderingContext::FRAMEBUFFER, None);
+
+            if status == WebGLRenderingContext::FRAMEBUFFER_COMPLETE {
+                Ok(Object::Framebuffer(framebuffer))
+            } else {
+                Err(format!("Unable to render into this framebuffer, status 0x{:x}", status))
+            }
+        }
+        Description::Program { vertex, fragment, attributes } => {
+            shader::build(gl, vertex, fragment, attributes).map(Object::Program)
+        }
+        Description::UniformLocation { program, name } => {
+            let program = program.program().ok_or("The uniform's program doesn't exist")?;
+            gl.get_uniform_location(&program, name)
+                .map(Object::UniformLocation)
+                .ok_or_else(|| format!("The program has no active uniform `{}`", name))
+        }
+    }
+}
+
+#[cfg(not(feature = "web-sys-backend"))]
+fn upload_buffer(gl: &Gl, buffer: &WebGLBuffer, target: GLenum, data: &[u8], usage: GLenum) {
+    let data = TypedArray::<u8>::from(data);
+    gl.bind_buffer(target, Some(buffer));
+    gl.buffer_data_1(target, Some(&data.buffer()), usage);
+}
+
+#[cfg(feature = "web-sys-backend")]
+fn upload_buffer(gl: &Gl, buffer: &WebGLBuffer, target: GLenum, data: &[u8], usage: GLenum) {
+    gl.bind_buffer(target, Some(buffer));
+    gl.buffer_data_bytes(target, data, usage);
+}
+
+/// Sets how the texture bound to `target` is filtered and wrapped.
+fn set_sampling(gl: &Gl, target: GLenum, filter: GLenum, wrap: GLenum) {
+    gl.tex_parameteri(target, WebGLRenderingContext::TEXTURE_MIN_FILTER, filter as i32);
+    gl.tex_parameteri(target, WebGLRenderingContext::TEXTURE_MAG_FILTER, filter as i32);
+    gl.tex_parameteri(target, WebGLRenderingContext::TEXTURE_WRAP_S, wrap as i32);
+    gl.tex_parameteri(target, WebGLRenderingContext::TEXTURE_WRAP_T, wrap as i32);
+}
+
+/// Specifies the storage of the texture bound to `target`, `TEXTURE_2D` or
+/// one of the faces of `TEXTURE_CUBE_MAP`.
+#[cfg(not(feature = "web-sys-backend"))]
+fn upload_texture(gl: &WebGLRenderingContext, target: GLenum, width: i32, height: i32, pixels: Option<&Vec<u8>>) {
+    match pixels {
+        // `texImage2D` wants a typed array, not the `ArrayBuffer` the
+        // bindings pass.
+        Some(pixels) => {
+            let pixels = TypedArray::<u8>::from(pixels.as_slice());
+            js! { @(no_return)
+                @{gl}.texImage2D(@{target}, 0, @{gl}.RGBA, @{width}, @{height}, 0, @{gl}.RGBA, @{gl}.UNSIGNED_BYTE, @{pixels});
+            }
+        }
+        None => gl.tex_image2_d(
+            target,
+            0,
+            WebGLRenderingContext::RGBA as i32,
+            width,
+            height,
+            0,
+            WebGLRenderingContext::RGBA,
+            WebGLRenderingContext::UNSIGNED_BYTE,
+            None
+        ),
+    }
+}
+
+/// Specifies the storage of the texture bound to `target`, `TEXTURE_2D` or
+/// one of the faces of `TEXTURE_CUBE_MAP`.
+#[cfg(feature = "web-sys-backend")]
+fn upload_texture(gl: &WebGLRenderingContext, target: GLenum, width: i32, height: i32, pixels: Option<&Vec<u8>>) {
+    gl.tex_image2_d_bytes(target, width, height, pixels.map(Vec::as_slice));
+}
+
+/// Whether the context is a WebGL2 one. The bindings only know WebGL1's
+/// interface, which WebGL2 contexts also have.
+#[cfg(not(feature = "web-sys-backend"))]
+pub fn is_webgl2(gl: &WebGLRenderingContext) -> bool {
+    js! {
+        return typeof WebGL2RenderingContext !== "undefined" && @{gl} instanceof WebGL2RenderingContext;
+    }
+    .try_into()
+    .unwrap_or(false)
+}
+
+#[cfg(feature = "web-sys-backend")]
+pub fn is_webgl2(gl: &WebGLRenderingContext) -> bool {
+    gl.is_webgl2()
+}
+
+/// Specifies the storage of the cubic texture bound to `TEXTURE_3D`.
+#[cfg(not(feature = "web-sys-backend"))]
+fn upload_volume(gl: &WebGLRenderingContext, size: i32, voxels: &[u8]) {
+    let voxels = TypedArray::<u8>::from(voxels);
+    js! { @(no_return)
+        var gl = @{gl};
+        gl.texImage3D(gl.TEXTURE_3D, 0, gl.RGBA, @{size}, @{size}, @{size}, 0, gl.RGBA, gl.UNSIGNED_BYTE, @{voxels});
+    }
+}
+
+/// Specifies the storage of the cubic texture bound to `TEXTURE_3D`.
+#[cfg(feature = "web-sys-backend")]
+fn upload_volume(gl: &WebGLRenderingContext, size: i32, voxels: &[u8]) {
+    gl.tex_image3_d_bytes(size, size, size, voxels);
+}
+
+fn allocate_renderbuffer(gl: &Gl, renderbuffer: &WebGLRenderbuffer, format: GLenum, width: i32, height: i32) {
+    gl.bind_renderbuffer(WebGLRenderingContext::RENDERBUFFER, Some(renderbuffer));
+    gl.renderbuffer_storage(WebGLRenderingContext::RENDERBUFFER, format, width, height);
+    gl.bind_renderbuffer(WebGLRenderingContext::RENDERBUFFER, None);
+}
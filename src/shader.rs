@@ -1,8 +1,53 @@
 use crate::stdweb::unstable::TryInto;
+use crate::glsl::{interface, Diagnostic, Preprocessor, Severity, Stage};
+use crate::glsl::parser::Version;
+use crate::glsl::translate::translate;
+use crate::owned::Owned;
+use crate::resources::{Description, Object, Registry, Resource};
 use crate::webgl_rendering_context::{WebGLRenderingContext, WebGLShader, GLenum, WebGLProgram};
+use crate::webgl_rendering_context::{EXTFragDepth, EXTShaderTextureLod, Extension, OESStandardDerivatives, WEBGLDrawBuffers};
+
+/// The GLSL ES version a context compiles, `Es300` for WebGL2 contexts.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/getParameter
+pub fn shading_language(gl: &WebGLRenderingContext) -> Version {
+    let version: String = gl
+        .get_parameter(WebGLRenderingContext::SHADING_LANGUAGE_VERSION)
+        .try_into()
+        .unwrap_or_default();
+
+    if version.contains("GLSL ES 3") {
+        Version::Es300
+    } else {
+        Version::Es100
+    }
+}
+
+/// Enables a WebGL extension by name, returning whether the browser has it.
+fn enable_extension(gl: &WebGLRenderingContext, name: &str) -> bool {
+    if name == OESStandardDerivatives::NAME {
+        gl.get_extension::<OESStandardDerivatives>().is_some()
+    } else if name == EXTShaderTextureLod::NAME {
+        gl.get_extension::<EXTShaderTextureLod>().is_some()
+    } else if name == EXTFragDepth::NAME {
+        gl.get_extension::<EXTFragDepth>().is_some()
+    } else if name == WEBGLDrawBuffers::NAME {
+        gl.get_extension::<WEBGLDrawBuffers>().is_some()
+    } else {
+        false
+    }
+}
 
 /// Creates a shader of the given type, loads the source and compiles it.
 ///
+/// The source may be written against either GLSL ES 1.00 or 3.00, it's
+/// translated to whatever the context compiles first, and the extensions the
+/// translation needs are enabled. The shader is deleted when dropped, which
+/// only flags it if a program still has it attached.
+///
+/// A lost context fails the compile instead of panicking, the shader is built
+/// again once the context is restored.
+///
 /// ## Arguments
 ///
 /// * `gl` - Webgl render context, acquired from a canvas.
@@ -10,40 +55,77 @@ use crate::webgl_rendering_context::{WebGLRenderingContext, WebGLShader, GLenum,
 /// * `src` - Shader source code.
 ///
 /// https://developer.mozilla.org/en-US/docs/Web/API/WebGL_API/Tutorial/Adding_2D_content_to_a_WebGL_context
-pub fn load(gl: &WebGLRenderingContext, kind: GLenum, src: &str) -> Result<WebGLShader, String> {
-    let shader = gl.create_shader(kind).unwrap();
-    gl.shader_source(&shader, src);
+pub fn load(gl: &WebGLRenderingContext, kind: GLenum, src: &str) -> Result<Owned<WebGLShader>, String> {
+    let stage = if kind == WebGLRenderingContext::VERTEX_SHADER { Stage::Vertex } else { Stage::Fragment };
+    let translation = translate(src, stage, shading_language(gl));
+
+    if let Some(log) = translation.error_log() {
+        return Err(log);
+    }
+
+    if let Some(missing) = translation.extensions.iter().find(|name| !enable_extension(gl, name)) {
+        return Err(format!("This shader needs the {} extension, which this browser doesn't support", missing));
+    }
+
+    let shader = Owned::new(gl, gl.create_shader(kind).ok_or("Unable to create a shader")?);
+    gl.shader_source(&shader, &translation.source);
     gl.compile_shader(&shader);
 
     let compile_status: bool = gl
         .get_shader_parameter(&shader, WebGLRenderingContext::COMPILE_STATUS)
         .try_into()
-        .unwrap();
+        .unwrap_or(false);
 
     if compile_status {
         Ok(shader)
     } else {
-        let info = gl.get_shader_info_log(&shader).unwrap();
-        gl.delete_shader(Some(&shader));
+        let info = gl.get_shader_info_log(&shader).unwrap_or_default();
         Err(format!("An error occurred compiling the shaders: {}", info))
     }
 }
 
+/// A linked program, along with any warnings about the interface between
+/// its two stages.
+pub struct LinkedProgram {
+    pub program: Resource,
+    pub warnings: Vec<Diagnostic>,
+}
+
 /// Initialize a shader program, so that webgl understands how to draw
 /// our data.
 ///
+/// Both sources are preprocessed first and every error is reported against
+/// the original file and line. The varyings of both stages are compared before
+/// linking, so mismatches are reported with the location of both declarations
+/// instead of the driver's terse link log.
+///
 /// ## Arguments
 ///
 /// * `gl` - Webgl render context, acquired from a canvas.
+/// * `registry` - Tracks the program so it can be restored with the context.
+/// * `preprocessor` - Resolves includes and injects defines.
 /// * `vsrc` - Vertex shader source code.
 /// * `fsrc` - Fragment shader source code.
 ///
 /// https://developer.mozilla.org/en-US/docs/Web/API/WebGL_API/Tutorial/Adding_2D_content_to_a_WebGL_context
-pub fn init(gl: &WebGLRenderingContext, vsrc: &str, fsrc: &str) -> Result<WebGLProgram, String> {
-    let vertex_shader = load(gl, WebGLRenderingContext::VERTEX_SHADER, vsrc).unwrap();
-    let fragment_shader = load(gl, WebGLRenderingContext::FRAGMENT_SHADER, fsrc).unwrap();
+pub fn init(gl: &WebGLRenderingContext, registry: &mut Registry, preprocessor: &Preprocessor, vsrc: &str, fsrc: &str) -> Result<LinkedProgram, Vec<Diagnostic>> {
+    let vertex = preprocessor.process(Stage::Vertex.name(), vsrc).map_err(|error| vec![Diagnostic::error(None, error)])?;
+    let fragment = preprocessor.process(Stage::Fragment.name(), fsrc).map_err(|error| vec![Diagnostic::error(None, error)])?;
+
+    let vertex_shader = load(gl, WebGLRenderingContext::VERTEX_SHADER, &vertex.source)
+        .map_err(|error| Diagnostic::from_info_log(&vertex.source_map.remap_log(&error)))?;
+    let fragment_shader = load(gl, WebGLRenderingContext::FRAGMENT_SHADER, &fragment.source)
+        .map_err(|error| Diagnostic::from_info_log(&fragment.source_map.remap_log(&error)))?;
+
+    let mut diagnostics = interface::validate(&vertex, &fragment);
+    if diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error) {
+        return Err(diagnostics);
+    }
 
-    let program = gl.create_program().unwrap();
+    // The shaders are dropped on the way out, successful or not, leaving the
+    // program as their only owner.
+    let program = gl.create_program().ok_or_else(|| vec![Diagnostic::error(None, String::from("Unable to create a program"))])?;
+    let program = Owned::new(gl, program);
     gl.attach_shader(&program, &vertex_shader);
     gl.attach_shader(&program, &fragment_shader);
     gl.link_program(&program);
@@ -51,12 +133,56 @@ pub fn init(gl: &WebGLRenderingContext, vsrc: &str, fsrc: &str) -> Result<WebGLP
     let link_status: bool = gl
         .get_program_parameter(&program, WebGLRenderingContext::LINK_STATUS)
         .try_into()
-        .unwrap();
+        .unwrap_or(false);
+
+    if link_status {
+        diagnostics.extend(interface::unwritten_varyings(&vertex, &fragment));
+        let description = Description::Program {
+            vertex: vertex.source.clone(),
+            fragment: fragment.source.clone(),
+            attributes: Vec::new(),
+        };
+        let program = registry.insert(description, Object::Program(program));
+        Ok(LinkedProgram { program, warnings: diagnostics })
+    } else {
+        let info = gl.get_program_info_log(&program).unwrap_or_default();
+        diagnostics.extend(Diagnostic::from_info_log(&format!("Unable to initialize the shader program: {}", info)));
+        Err(diagnostics)
+    }
+}
+
+/// Compiles and links sources that need no preprocessing, binding attributes
+/// to the given locations before linking.
+///
+/// ## Arguments
+///
+/// * `gl` - Webgl render context, acquired from a canvas.
+/// * `vsrc` - Vertex shader source code.
+/// * `fsrc` - Fragment shader source code.
+/// * `attributes` - Locations to bind attributes to.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/bindAttribLocation
+pub fn build(gl: &WebGLRenderingContext, vsrc: &str, fsrc: &str, attributes: &[(u32, String)]) -> Result<Owned<WebGLProgram>, String> {
+    let vertex_shader = load(gl, WebGLRenderingContext::VERTEX_SHADER, vsrc)?;
+    let fragment_shader = load(gl, WebGLRenderingContext::FRAGMENT_SHADER, fsrc)?;
+
+    let program = Owned::new(gl, gl.create_program().ok_or("Unable to create a program")?);
+    gl.attach_shader(&program, &vertex_shader);
+    gl.attach_shader(&program, &fragment_shader);
+    for (location, name) in attributes {
+        gl.bind_attrib_location(&program, *location, name);
+    }
+    gl.link_program(&program);
+
+    let link_status: bool = gl
+        .get_program_parameter(&program, WebGLRenderingContext::LINK_STATUS)
+        .try_into()
+        .unwrap_or(false);
 
     if link_status {
         Ok(program)
     } else {
-        let info = gl.get_program_info_log(&program).unwrap();
+        let info = gl.get_program_info_log(&program).unwrap_or_default();
         Err(format!("Unable to initialize the shader program: {}", info))
     }
-}
\ No newline at end of file
+}
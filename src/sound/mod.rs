@@ -0,0 +1,282 @@
+pub mod wav;
+
+use stdweb::unstable::TryInto;
+use stdweb::web::TypedArray;
+use stdweb::{js, Value};
+
+use crate::gl_state::Gl;
+use crate::render_target::{Pass, RenderTarget};
+use crate::resources::Registry;
+use crate::uniform::Uniform;
+use crate::webgl_rendering_context::WebGLRenderingContext;
+
+/// Comes before the user's source, which only defines `mainSound`.
+const PREFIX: &str = "precision highp float;\n";
+
+/// Comes after the user's source. Each pixel is a stereo sample, in rows
+/// from the bottom left, with both channels as 16 bits in two bytes each.
+const MAIN: &str = r#"
+uniform float uBlockOffset;
+uniform float uSampleRate;
+uniform float uBlockWidth;
+
+void main() {
+    float index = floor(gl_FragCoord.y) * uBlockWidth + floor(gl_FragCoord.x);
+    vec2 sound = clamp(mainSound(uBlockOffset + index / uSampleRate), -1.0, 1.0);
+    vec2 value = floor((sound * 0.5 + 0.5) * 65535.0 + 0.5);
+    vec2 high = floor(value / 256.0);
+    vec2 low = value - high * 256.0;
+    gl_FragColor = vec4(low.x, high.x, low.y, high.y) / 255.0;
+}
+"#;
+
+/// Width and height of a block, which holds 262144 samples, about 6 seconds.
+const BLOCK_SIZE: i32 = 512;
+
+/// Seconds of sound a shader renders.
+pub const DURATION: f64 = 60.0;
+
+/// Sample rate of sounds only rendered to be exported.
+pub const EXPORT_SAMPLE_RATE: u32 = 44100;
+
+/// A sound shader, rendered into a texture one block of samples at a time
+/// and read back, like Shadertoy's sound tab. The shader defines
+/// `vec2 mainSound(float time)`, returning the left and right sample at a
+/// time in seconds.
+///
+/// Rendering everything up front would stall the page for a while, so the
+/// app renders a block per frame, which easily stays ahead of playback.
+pub struct SoundShader {
+    pass: Pass,
+    block_offset: Option<Uniform<f32>>,
+    sample_rate_uniform: Option<Uniform<f32>>,
+    block_width: Option<Uniform<f32>>,
+    target: RenderTarget,
+    sample_rate: u32,
+    /// Left and right samples rendered so far, interleaved.
+    samples: Vec<i16>,
+    player: Option<Player>,
+}
+
+impl SoundShader {
+    /// Compiles a sound shader.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `registry` - Tracks the shader's resources so they can be restored with the context.
+    /// * `source` - Preprocessed source defining `mainSound`.
+    /// * `sample_rate` - Samples per second to render.
+    pub fn new(gl: &Gl, registry: &mut Registry, source: &str, sample_rate: u32) -> Result<Self, String> {
+        let pass = Pass::new(gl, registry, &format!("{}{}{}", PREFIX, source, MAIN), &[])?;
+        let program = pass.program().clone();
+
+        Ok(Self {
+            block_offset: Uniform::new(gl, registry, &program, "uBlockOffset")?,
+            sample_rate_uniform: Uniform::new(gl, registry, &program, "uSampleRate")?,
+            block_width: Uniform::new(gl, registry, &program, "uBlockWidth")?,
+            target: RenderTarget::new(gl, registry, BLOCK_SIZE, BLOCK_SIZE)?,
+            pass,
+            sample_rate,
+            samples: Vec::new(),
+            player: None,
+        })
+    }
+
+    /// Plays blocks as they're rendered, starting with the next one.
+    pub fn set_player(&mut self, player: Option<Player>) {
+        self.player = player;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.player.is_some()
+    }
+
+    /// Seconds rendered so far.
+    pub fn rendered(&self) -> f64 {
+        self.samples.len() as f64 / 2.0 / f64::from(self.sample_rate)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.samples.len() / 2 >= self.frames()
+    }
+
+    /// Renders and reads back the next block, if there's any left, and
+    /// schedules it if playing.
+    pub fn render_block(&mut self, gl: &Gl) {
+        let done = self.samples.len() / 2;
+        if done >= self.frames() {
+            return;
+        }
+
+        let offset = done as f64 / f64::from(self.sample_rate);
+        let sample_rate = self.sample_rate as f32;
+        let Self { pass, block_offset, sample_rate_uniform, block_width, target, .. } = self;
+
+        target.bind(gl);
+        pass.draw(gl, &[], |gl| {
+            if let Some(uniform) = block_offset.as_mut() {
+                uniform.set(gl, offset as f32);
+            }
+            if let Some(uniform) = sample_rate_uniform.as_mut() {
+                uniform.set(gl, sample_rate);
+            }
+            if let Some(uniform) = block_width.as_mut() {
+                uniform.set(gl, BLOCK_SIZE as f32);
+            }
+        });
+
+        let pixels = read_pixels(gl, BLOCK_SIZE, BLOCK_SIZE);
+        let block: Vec<i16> = pixels
+            .chunks(4)
+            .take(self.frames() - done)
+            .flat_map(|pixel| vec![decode(pixel[0], pixel[1]), decode(pixel[2], pixel[3])])
+            .collect();
+
+        if let Some(player) = self.player.as_ref() {
+            player.schedule(&block, offset);
+        }
+        self.samples.extend(block);
+    }
+
+    /// Renders every block left, at once.
+    pub fn render_all(&mut self, gl: &Gl) {
+        while !self.is_finished() {
+            self.render_block(gl);
+        }
+    }
+
+    /// What's rendered so far as a stereo WAV file.
+    pub fn wav(&self) -> Vec<u8> {
+        wav::encode(&self.samples, 2, self.sample_rate)
+    }
+
+    /// Stereo samples the whole sound has.
+    fn frames(&self) -> usize {
+        (DURATION * f64::from(self.sample_rate)) as usize
+    }
+}
+
+/// A 16 bit sample from the two bytes the shader wrote, low byte first.
+fn decode(low: u8, high: u8) -> i16 {
+    ((u16::from(high) << 8 | u16::from(low)) as i32 - 32768) as i16
+}
+
+/// Reads the `RGBA` pixels of the bound framebuffer's bottom left corner.
+#[cfg(not(feature = "web-sys-backend"))]
+fn read_pixels(gl: &WebGLRenderingContext, width: i32, height: i32) -> Vec<u8> {
+    // `readPixels` wants a typed array, not the `ArrayBuffer` the bindings
+    // pass.
+    let pixels: TypedArray<u8> = js! {
+        var gl = @{gl};
+        var pixels = new Uint8Array(@{width} * @{height} * 4);
+        gl.readPixels(0, 0, @{width}, @{height}, gl.RGBA, gl.UNSIGNED_BYTE, pixels);
+        return pixels;
+    }
+    .try_into()
+    .unwrap();
+
+    pixels.to_vec()
+}
+
+/// Reads the `RGBA` pixels of the bound framebuffer's bottom left corner.
+#[cfg(feature = "web-sys-backend")]
+fn read_pixels(gl: &WebGLRenderingContext, width: i32, height: i32) -> Vec<u8> {
+    let mut pixels = vec![0; (width * height * 4) as usize];
+    gl.read_pixels_bytes(width, height, &mut pixels);
+    pixels
+}
+
+/// Plays blocks of stereo samples back to back through Web Audio, timed by
+/// the audio clock rather than by when they arrive.
+///
+/// Dropping the player stops it.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/AudioBufferSourceNode
+pub struct Player {
+    /// The context and when the first block started, once there was one.
+    graph: Value,
+    sample_rate: u32,
+}
+
+impl Player {
+    pub fn new() -> Self {
+        let graph = js! {
+            var Context = window.AudioContext || window.webkitAudioContext;
+            var context = new Context();
+            // Created in response to a click, but browsers may still start
+            // it suspended.
+            context.resume();
+            return { context: context, start: null };
+        };
+        let sample_rate: f64 = js! { return @{&graph}.context.sampleRate; }.try_into().unwrap_or(44100.0);
+
+        Self { graph, sample_rate: sample_rate as u32 }
+    }
+
+    /// The rate of the output device, which the sound should be rendered at
+    /// to play without resampling.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Plays a block after the ones before it.
+    ///
+    /// ## Arguments
+    ///
+    /// * `samples` - Left and right samples, interleaved.
+    /// * `offset` - Seconds between the start of the first block and this one.
+    fn schedule(&self, samples: &[i16], offset: f64) {
+        let (left, right): (Vec<f32>, Vec<f32>) = samples
+            .chunks(2)
+            .map(|frame| (f32::from(frame[0]) / 32768.0, f32::from(frame[1]) / 32768.0))
+            .unzip();
+        let left = TypedArray::<f32>::from(left.as_slice());
+        let right = TypedArray::<f32>::from(right.as_slice());
+
+        js! { @(no_return)
+            var player = @{&self.graph};
+            var context = player.context;
+            var left = @{left};
+            var buffer = context.createBuffer(2, left.length, @{self.sample_rate});
+            buffer.getChannelData(0).set(left);
+            buffer.getChannelData(1).set(@{right});
+
+            var node = context.createBufferSource();
+            node.buffer = buffer;
+            node.connect(context.destination);
+            // Leave a little time for the first block to reach the device.
+            if (player.start === null) {
+                player.start = context.currentTime + 0.05;
+            }
+            node.start(player.start + @{offset});
+        };
+    }
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        js! { @(no_return)
+            @{&self.graph}.context.close();
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_the_shaders_encoding() {
+        assert_eq!(decode(0, 0), i16::MIN);
+        assert_eq!(decode(0x00, 0x80), 0);
+        assert_eq!(decode(0xff, 0xff), i16::MAX);
+        assert_eq!(decode(0x01, 0x80), 1);
+    }
+}
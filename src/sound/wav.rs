@@ -0,0 +1,91 @@
+//! Encoding of 16 bit PCM WAV files.
+
+/// Size of the RIFF header and the `fmt ` and `data` chunk headers.
+const HEADER_SIZE: usize = 44;
+
+const BITS_PER_SAMPLE: u16 = 16;
+
+/// Encodes samples as a WAV file.
+///
+/// ## Arguments
+///
+/// * `samples` - Samples of every channel, interleaved.
+/// * `channels` - Number of channels.
+/// * `sample_rate` - Samples per second of each channel.
+///
+/// http://soundfile.sapp.org/doc/WaveFormat/
+pub fn encode(samples: &[i16], channels: u16, sample_rate: u32) -> Vec<u8> {
+    let block_align = channels * BITS_PER_SAMPLE / 8;
+    let data_size = (samples.len() * 2) as u32;
+
+    let mut wav = Vec::with_capacity(HEADER_SIZE + samples.len() * 2);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(HEADER_SIZE as u32 - 8 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // Uncompressed PCM.
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(wav: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([wav[offset], wav[offset + 1]])
+    }
+
+    fn u32_at(wav: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([wav[offset], wav[offset + 1], wav[offset + 2], wav[offset + 3]])
+    }
+
+    #[test]
+    fn writes_the_header() {
+        let wav = encode(&[0; 8], 2, 48000);
+
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32_at(&wav, 4), 36 + 16);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&wav, 16), 16);
+        assert_eq!(u16_at(&wav, 20), 1);
+        assert_eq!(u16_at(&wav, 22), 2);
+        assert_eq!(u32_at(&wav, 24), 48000);
+        assert_eq!(u32_at(&wav, 28), 48000 * 4);
+        assert_eq!(u16_at(&wav, 32), 4);
+        assert_eq!(u16_at(&wav, 34), 16);
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(u32_at(&wav, 40), 16);
+    }
+
+    #[test]
+    fn writes_samples_little_endian_in_order() {
+        let wav = encode(&[1, -1, i16::MAX, i16::MIN], 2, 44100);
+
+        assert_eq!(wav.len(), HEADER_SIZE + 8);
+        assert_eq!(&wav[HEADER_SIZE..], &[1, 0, 0xff, 0xff, 0xff, 0x7f, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn encodes_silence_as_a_header() {
+        let wav = encode(&[], 1, 8000);
+
+        assert_eq!(wav.len(), HEADER_SIZE);
+        assert_eq!(u32_at(&wav, 4), 36);
+        assert_eq!(u32_at(&wav, 40), 0);
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::VecDeque;
+
+use crate::resources::is_webgl2;
+use crate::stdweb::unstable::TryInto;
+use crate::webgl_rendering_context::{
+    EXTDisjointTimerQuery, EXTDisjointTimerQueryWebGL2, GLenum, WebGL2RenderingContext, WebGLRenderingContext, WebGLTimerQueryEXT,
+};
+#[cfg(not(feature = "web-sys-backend"))]
+use crate::stdweb::Value;
+#[cfg(feature = "web-sys-backend")]
+use crate::webgl_rendering_context::Value;
+
+/// How many frames of history to keep for the graph and statistics.
+pub const HISTORY: usize = 120;
+
+/// Frames still waiting on their queries before the oldest are given up on,
+/// so a driver that never answers doesn't leak query objects.
+const MAX_PENDING: usize = 8;
+
+/// Frames measured between updates of the shown timings, the page isn't
+/// worth re-rendering every frame for them.
+const REFRESH_INTERVAL: usize = 15;
+
+/// GPU time spent on one frame, in milliseconds, in total and per pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameTiming {
+    pub passes: Vec<(String, f64)>,
+    pub total: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+struct PendingFrame {
+    passes: Vec<(String, WebGLTimerQueryEXT)>,
+}
+
+/// Where time elapsed queries come from. WebGL1 contexts have them on
+/// `EXT_disjoint_timer_query`, WebGL2 ones have queries of their own and
+/// `EXT_disjoint_timer_query_webgl2` only adds the target to time with.
+enum Queries {
+    WebGL1(EXTDisjointTimerQuery),
+    WebGL2(WebGLRenderingContext),
+}
+
+impl Queries {
+    fn create(&self) -> Option<WebGLTimerQueryEXT> {
+        match self {
+            Queries::WebGL1(extension) => extension.create_query_ext(),
+            Queries::WebGL2(gl) => create_query(gl),
+        }
+    }
+
+    fn begin(&self, query: &WebGLTimerQueryEXT) {
+        match self {
+            Queries::WebGL1(extension) => extension.begin_query_ext(EXTDisjointTimerQuery::TIME_ELAPSED_EXT, query),
+            Queries::WebGL2(gl) => begin_query(gl, EXTDisjointTimerQueryWebGL2::TIME_ELAPSED_EXT, query),
+        }
+    }
+
+    fn end(&self) {
+        match self {
+            Queries::WebGL1(extension) => extension.end_query_ext(EXTDisjointTimerQuery::TIME_ELAPSED_EXT),
+            Queries::WebGL2(gl) => end_query(gl, EXTDisjointTimerQueryWebGL2::TIME_ELAPSED_EXT),
+        }
+    }
+
+    fn available(&self, query: &WebGLTimerQueryEXT) -> bool {
+        let available = match self {
+            Queries::WebGL1(extension) => extension.get_query_object_ext(query, EXTDisjointTimerQuery::QUERY_RESULT_AVAILABLE_EXT),
+            Queries::WebGL2(gl) => get_query_parameter(gl, query, WebGL2RenderingContext::QUERY_RESULT_AVAILABLE),
+        };
+        available.try_into().unwrap_or(false)
+    }
+
+    /// The time a query measured, in nanoseconds.
+    fn result(&self, query: &WebGLTimerQueryEXT) -> f64 {
+        let result = match self {
+            Queries::WebGL1(extension) => extension.get_query_object_ext(query, EXTDisjointTimerQuery::QUERY_RESULT_EXT),
+            Queries::WebGL2(gl) => get_query_parameter(gl, query, WebGL2RenderingContext::QUERY_RESULT),
+        };
+        result.try_into().unwrap_or(0.0)
+    }
+
+    fn delete(&self, query: &WebGLTimerQueryEXT) {
+        match self {
+            Queries::WebGL1(extension) => extension.delete_query_ext(Some(query)),
+            Queries::WebGL2(gl) => delete_query(gl, Some(query)),
+        }
+    }
+}
+
+// The stdweb bindings only know WebGL1's interface, WebGL2's queries are
+// called on the context directly.
+
+#[cfg(not(feature = "web-sys-backend"))]
+fn create_query(gl: &WebGLRenderingContext) -> Option<WebGLTimerQueryEXT> {
+    js! { return @{gl}.createQuery(); }.try_into().ok()
+}
+
+#[cfg(not(feature = "web-sys-backend"))]
+fn begin_query(gl: &WebGLRenderingContext, target: GLenum, query: &WebGLTimerQueryEXT) {
+    js! { @(no_return) @{gl}.beginQuery(@{target}, @{query}); }
+}
+
+#[cfg(not(feature = "web-sys-backend"))]
+fn end_query(gl: &WebGLRenderingContext, target: GLenum) {
+    js! { @(no_return) @{gl}.endQuery(@{target}); }
+}
+
+#[cfg(not(feature = "web-sys-backend"))]
+fn get_query_parameter(gl: &WebGLRenderingContext, query: &WebGLTimerQueryEXT, pname: GLenum) -> Value {
+    js!( return @{gl}.getQueryParameter(@{query}, @{pname}); )
+}
+
+#[cfg(not(feature = "web-sys-backend"))]
+fn delete_query(gl: &WebGLRenderingContext, query: Option<&WebGLTimerQueryEXT>) {
+    js! { @(no_return) @{gl}.deleteQuery(@{query}); }
+}
+
+#[cfg(feature = "web-sys-backend")]
+fn create_query(gl: &WebGLRenderingContext) -> Option<WebGLTimerQueryEXT> {
+    gl.create_query()
+}
+
+#[cfg(feature = "web-sys-backend")]
+fn begin_query(gl: &WebGLRenderingContext, target: GLenum, query: &WebGLTimerQueryEXT) {
+    gl.begin_query(target, query);
+}
+
+#[cfg(feature = "web-sys-backend")]
+fn end_query(gl: &WebGLRenderingContext, target: GLenum) {
+    gl.end_query(target);
+}
+
+#[cfg(feature = "web-sys-backend")]
+fn get_query_parameter(gl: &WebGLRenderingContext, query: &WebGLTimerQueryEXT, pname: GLenum) -> Value {
+    gl.get_query_parameter(query, pname)
+}
+
+#[cfg(feature = "web-sys-backend")]
+fn delete_query(gl: &WebGLRenderingContext, query: Option<&WebGLTimerQueryEXT>) {
+    gl.delete_query(query);
+}
+
+/// Measures how long the GPU spends on each render pass with
+/// `EXT_disjoint_timer_query`, or `EXT_disjoint_timer_query_webgl2` on
+/// WebGL2 contexts.
+///
+/// Only one time elapsed query can run at a time, so passes are timed one
+/// after the other and summed for the frame. Results arrive a few frames
+/// late, `poll` collects whatever is ready. Frames that overlap a disjoint
+/// event, e.g. the GPU changing clocks, are thrown away since their timings
+/// can't be trusted.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/EXT_disjoint_timer_query
+pub struct GpuTimer {
+    queries: Queries,
+    current: Option<PendingFrame>,
+    active: bool,
+    pending: VecDeque<PendingFrame>,
+    history: VecDeque<FrameTiming>,
+    /// Frames measured since the timings were last shown.
+    unshown: usize,
+    shown: bool,
+}
+
+impl GpuTimer {
+    /// Returns `None` when the browser doesn't expose the extension the
+    /// context needs, most do not by default since it can be used for timing
+    /// attacks.
+    pub fn new(gl: &WebGLRenderingContext) -> Option<Self> {
+        let queries = if is_webgl2(gl) {
+            gl.get_extension::<EXTDisjointTimerQueryWebGL2>()?;
+            Queries::WebGL2(gl.clone())
+        } else {
+            Queries::WebGL1(gl.get_extension::<EXTDisjointTimerQuery>()?)
+        };
+
+        Some(Self {
+            queries,
+            current: None,
+            active: false,
+            pending: VecDeque::new(),
+            history: VecDeque::with_capacity(HISTORY),
+            unshown: 0,
+            shown: false,
+        })
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.current = Some(PendingFrame { passes: Vec::new() });
+    }
+
+    /// Starts timing a pass, ending the previous one if it's still running.
+    pub fn begin_pass(&mut self, name: &str) {
+        self.end_pass();
+
+        let frame = match self.current.as_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        if let Some(query) = self.queries.create() {
+            self.queries.begin(&query);
+            frame.passes.push((name.to_string(), query));
+            self.active = true;
+        }
+    }
+
+    pub fn end_pass(&mut self) {
+        if self.active {
+            self.queries.end();
+            self.active = false;
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        self.end_pass();
+
+        if let Some(frame) = self.current.take() {
+            self.pending.push_back(frame);
+        }
+
+        while self.pending.len() > MAX_PENDING {
+            let frame = self.pending.pop_front().unwrap();
+            self.delete(frame);
+        }
+    }
+
+    /// Collects the results of finished frames, oldest first.
+    pub fn poll(&mut self, gl: &WebGLRenderingContext) {
+        // Both extensions' `GPU_DISJOINT_EXT` is the same parameter.
+        let disjoint: bool = gl
+            .get_parameter(EXTDisjointTimerQuery::GPU_DISJOINT_EXT)
+            .try_into()
+            .unwrap_or(false);
+
+        if disjoint {
+            while let Some(frame) = self.pending.pop_front() {
+                self.delete(frame);
+            }
+            return;
+        }
+
+        while let Some(frame) = self.pending.front() {
+            let available = frame.passes.iter().all(|(_, query)| self.queries.available(query));
+
+            if !available {
+                break;
+            }
+
+            let frame = self.pending.pop_front().unwrap();
+            let passes: Vec<(String, f64)> = frame.passes
+                .iter()
+                .map(|(name, query)| (name.clone(), self.queries.result(query) / 1_000_000.0))
+                .collect();
+            self.delete(frame);
+
+            if self.history.len() == HISTORY {
+                self.history.pop_front();
+            }
+            let total = passes.iter().map(|(_, milliseconds)| milliseconds).sum();
+            self.history.push_back(FrameTiming { passes, total });
+            self.unshown += 1;
+        }
+    }
+
+    /// Whether the timings are due to be shown again: once the first frame
+    /// is measured and then every `REFRESH_INTERVAL` frames.
+    pub fn take_refresh(&mut self) -> bool {
+        if self.unshown >= REFRESH_INTERVAL || (self.unshown > 0 && !self.shown) {
+            self.unshown = 0;
+            self.shown = true;
+            return true;
+        }
+        false
+    }
+
+    /// Frame timings, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &FrameTiming> {
+        self.history.iter()
+    }
+
+    /// The most recent frame with results.
+    pub fn latest(&self) -> Option<&FrameTiming> {
+        self.history.back()
+    }
+
+    /// Minimum, average and maximum frame time over the history.
+    pub fn stats(&self) -> Option<Stats> {
+        if self.history.is_empty() {
+            return None;
+        }
+
+        let totals = self.history.iter().map(|frame| frame.total);
+        Some(Stats {
+            min: totals.clone().fold(f64::INFINITY, f64::min),
+            avg: totals.clone().sum::<f64>() / self.history.len() as f64,
+            max: totals.fold(0.0, f64::max),
+        })
+    }
+
+    /// Average time of each pass over the history, in the order passes ran.
+    pub fn pass_averages(&self) -> Vec<(String, f64)> {
+        let mut averages: Vec<(String, f64, usize)> = Vec::new();
+
+        for (name, milliseconds) in self.history.iter().flat_map(|frame| frame.passes.iter()) {
+            match averages.iter_mut().find(|(existing, _, _)| existing == name) {
+                Some((_, total, count)) => {
+                    *total += milliseconds;
+                    *count += 1;
+                }
+                None => averages.push((name.clone(), *milliseconds, 1)),
+            }
+        }
+
+        averages
+            .into_iter()
+            .map(|(name, total, count)| (name, total / count as f64))
+            .collect()
+    }
+
+    fn delete(&self, frame: PendingFrame) {
+        for (_, query) in &frame.passes {
+            self.queries.delete(query);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use nalgebra_glm as glm;
+use stdweb::unstable::TryInto;
+
+use crate::gl_state::Gl;
+use crate::resources::{Description, Registry, Resource};
+use crate::webgl_rendering_context::{GLenum, WebGL2RenderingContext, WebGLRenderingContext, WebGLUniformLocation};
+
+/// A Rust type that can be uploaded to a uniform.
+pub trait UniformType: Clone + PartialEq {
+    /// The uniform types, as reported by `getActiveUniform`, this can be
+    /// uploaded to.
+    const TYPES: &'static [GLenum];
+
+    fn upload(&self, gl: &Gl, location: &WebGLUniformLocation);
+}
+
+/// The texture unit a sampler reads from. Which of the unit's textures it
+/// reads depends on the sampler's type, see `Uniform::type_`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureUnit(pub i32);
+
+impl UniformType for f32 {
+    const TYPES: &'static [GLenum] = &[WebGLRenderingContext::FLOAT];
+
+    fn upload(&self, gl: &Gl, location: &WebGLUniformLocation) {
+        gl.uniform1f(Some(location), *self);
+    }
+}
+
+impl UniformType for i32 {
+    const TYPES: &'static [GLenum] = &[WebGLRenderingContext::INT];
+
+    fn upload(&self, gl: &Gl, location: &WebGLUniformLocation) {
+        gl.uniform1i(Some(location), *self);
+    }
+}
+
+impl UniformType for bool {
+    const TYPES: &'static [GLenum] = &[WebGLRenderingContext::BOOL];
+
+    fn upload(&self, gl: &Gl, location: &WebGLUniformLocation) {
+        gl.uniform1i(Some(location), *self as i32);
+    }
+}
+
+impl UniformType for TextureUnit {
+    const TYPES: &'static [GLenum] = &[
+        WebGLRenderingContext::SAMPLER_2D,
+        WebGLRenderingContext::SAMPLER_CUBE,
+        WebGL2RenderingContext::SAMPLER_3D,
+    ];
+
+    fn upload(&self, gl: &Gl, location: &WebGLUniformLocation) {
+        gl.uniform1i(Some(location), self.0);
+    }
+}
+
+impl UniformType for glm::Vec2 {
+    const TYPES: &'static [GLenum] = &[WebGLRenderingContext::FLOAT_VEC2];
+
+    fn upload(&self, gl: &Gl, location: &WebGLUniformLocation) {
+        gl.uniform2f(Some(location), self.x, self.y);
+    }
+}
+
+impl UniformType for glm::Vec3 {
+    const TYPES: &'static [GLenum] = &[WebGLRenderingContext::FLOAT_VEC3];
+
+    fn upload(&self, gl: &Gl, location: &WebGLUniformLocation) {
+        gl.uniform3f(Some(location), self.x, self.y, self.z);
+    }
+}
+
+impl UniformType for glm::Vec4 {
+    const TYPES: &'static [GLenum] = &[WebGLRenderingContext::FLOAT_VEC4];
+
+    fn upload(&self, gl: &Gl, location: &WebGLUniformLocation) {
+        gl.uniform4f(Some(location), self.x, self.y, self.z, self.w);
+    }
+}
+
+impl UniformType for glm::Mat3 {
+    const TYPES: &'static [GLenum] = &[WebGLRenderingContext::FLOAT_MAT3];
+
+    fn upload(&self, gl: &Gl, location: &WebGLUniformLocation) {
+        gl.uniform_matrix3fv(Some(location), self.as_slice());
+    }
+}
+
+impl UniformType for glm::Mat4 {
+    const TYPES: &'static [GLenum] = &[WebGLRenderingContext::FLOAT_MAT4];
+
+    fn upload(&self, gl: &Gl, location: &WebGLUniformLocation) {
+        gl.uniform_matrix4fv(Some(location), self.as_slice());
+    }
+}
+
+/// A uniform of a linked program that only accepts values of the type it
+/// was declared with.
+///
+/// The last value uploaded is remembered and setting it again is skipped,
+/// every upload is a call into JavaScript. The memory is dropped with the
+/// program's values when the context is restored.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/uniform
+pub struct Uniform<T: UniformType> {
+    name: String,
+    /// The type it was declared with, one of `T::TYPES`.
+    type_: GLenum,
+    location: Resource,
+    /// The value last uploaded and the location's generation at the time.
+    value: Option<(u32, T)>,
+}
+
+impl<T: UniformType> Uniform<T> {
+    /// Looks a uniform up and checks that `T` matches its declaration.
+    ///
+    /// Returns `None` when the program has no such active uniform, e.g. when
+    /// the compiler removed it for being unused.
+    ///
+    /// ## Arguments
+    ///
+    /// * `gl` - Webgl render context, acquired from a canvas.
+    /// * `registry` - Tracks the location so it can be restored with the context.
+    /// * `program` - Linked program the uniform belongs to.
+    /// * `name` - Name of the uniform.
+    ///
+    /// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext/getActiveUniform
+    pub fn new(gl: &Gl, registry: &mut Registry, program: &Resource, name: &str) -> Result<Option<Self>, String> {
+        let linked = program.program().ok_or("The program doesn't exist")?;
+        let count: u32 = gl
+            .get_program_parameter(&linked, WebGLRenderingContext::ACTIVE_UNIFORMS)
+            .try_into()
+            .unwrap_or(0);
+
+        // Arrays are reported by the name of their first element.
+        let type_ = (0..count)
+            .filter_map(|index| gl.get_active_uniform(&linked, index))
+            .find(|info| info.name() == name || info.name() == format!("{}[0]", name))
+            .map(|info| info.type_());
+
+        let type_ = match type_ {
+            Some(type_) => type_,
+            None => return Ok(None),
+        };
+
+        if !T::TYPES.contains(&type_) {
+            return Err(format!(
+                "Uniform `{}` is declared as {} but set as {}",
+                name,
+                type_name(type_),
+                T::TYPES.iter().map(|&type_| type_name(type_)).collect::<Vec<&str>>().join(" or ")
+            ));
+        }
+
+        let location = registry.create(gl, Description::UniformLocation {
+            program: program.clone(),
+            name: name.to_string(),
+        })?;
+
+        Ok(Some(Self { name: name.to_string(), type_, location, value: None }))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn type_(&self) -> GLenum {
+        self.type_
+    }
+
+    /// The value last uploaded, `None` before the first.
+    pub fn value(&self) -> Option<&T> {
+        self.value.as_ref().map(|(_, value)| value)
+    }
+
+    /// Uploads a value, unless it's the one already there.
+    pub fn set(&mut self, gl: &Gl, value: T) {
+        let generation = self.location.generation();
+        if let Some((uploaded, previous)) = &self.value {
+            if *uploaded == generation && *previous == value {
+                return;
+            }
+        }
+
+        if let Some(location) = self.location.uniform_location() {
+            value.upload(gl, &location);
+            self.value = Some((generation, value));
+        }
+    }
+}
+
+/// The GLSL name of a uniform type reported by `getActiveUniform`.
+pub fn type_name(type_: GLenum) -> &'static str {
+    match type_ {
+        WebGLRenderingContext::FLOAT => "float",
+        WebGLRenderingContext::FLOAT_VEC2 => "vec2",
+        WebGLRenderingContext::FLOAT_VEC3 => "vec3",
+        WebGLRenderingContext::FLOAT_VEC4 => "vec4",
+        WebGLRenderingContext::INT => "int",
+        WebGLRenderingContext::INT_VEC2 => "ivec2",
+        WebGLRenderingContext::INT_VEC3 => "ivec3",
+        WebGLRenderingContext::INT_VEC4 => "ivec4",
+        WebGLRenderingContext::BOOL => "bool",
+        WebGLRenderingContext::BOOL_VEC2 => "bvec2",
+        WebGLRenderingContext::BOOL_VEC3 => "bvec3",
+        WebGLRenderingContext::BOOL_VEC4 => "bvec4",
+        WebGLRenderingContext::FLOAT_MAT2 => "mat2",
+        WebGLRenderingContext::FLOAT_MAT3 => "mat3",
+        WebGLRenderingContext::FLOAT_MAT4 => "mat4",
+        WebGLRenderingContext::SAMPLER_2D => "sampler2D",
+        WebGLRenderingContext::SAMPLER_CUBE => "samplerCube",
+        WebGL2RenderingContext::SAMPLER_3D => "sampler3D",
+        _ => "an unknown type",
+    }
+}
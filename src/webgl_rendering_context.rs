@@ -1121,7 +1121,7 @@ impl GLContext {
     }
 
     pub fn get_extension<E: Extension>(&self) -> Option<E> {
-        (js! { return @{self}.getExtension({E::NAME}); } ).try_into().ok()
+        (js! { return @{self}.getExtension(@{E::NAME}); } ).try_into().ok()
     }
 
     pub fn get_frag_data_location(&self, program: &WebGLProgram, name: &str) -> GLint {
@@ -2746,7 +2746,7 @@ impl WebGL2RenderingContext {
     }
 
     pub fn get_extension<E: Extension>(&self) -> Option<E> {
-        (js! { return @{self}.getExtension({E::NAME}); } ).try_into().ok()
+        (js! { return @{self}.getExtension(@{E::NAME}); } ).try_into().ok()
     }
 
     pub fn get_frag_data_location(&self, program: &WebGLProgram, name: &str) -> GLint {
@@ -4003,7 +4003,7 @@ impl WebGLRenderingContext {
     }
 
     pub fn get_extension<E: Extension>(&self) -> Option<E> {
-        (js! { return @{self}.getExtension({E::NAME}); } ).try_into().ok()
+        (js! { return @{self}.getExtension(@{E::NAME}); } ).try_into().ok()
     }
 
     pub fn get_framebuffer_attachment_parameter(&self, target: GLenum, attachment: GLenum, pname: GLenum) -> Value {
@@ -4403,4 +4403,86 @@ pub struct WebGLVertexArrayObject(Reference);
 impl WebGLVertexArrayObject {
     
 }
-    
\ No newline at end of file
+
+macro_rules! define_extension {
+    ($name:ident, $js_name:expr) => {
+        #[derive(Debug, Clone, ReferenceType)]
+        pub struct $name(Reference);
+
+        impl InstanceOf for $name {
+            #[inline]
+            fn instance_of( reference: &Reference ) -> bool {
+                js!(
+                    return @{{reference}} instanceof Object;
+                ).try_into().unwrap()
+            }
+        }
+
+        impl Extension for $name {
+            const NAME: &'static str = $js_name;
+        }
+    }
+}
+
+define_extension!(OESElementIndexUint, "OES_element_index_uint");
+define_extension!(OESStandardDerivatives, "OES_standard_derivatives");
+define_extension!(EXTShaderTextureLod, "EXT_shader_texture_lod");
+define_extension!(EXTFragDepth, "EXT_frag_depth");
+define_extension!(WEBGLDrawBuffers, "WEBGL_draw_buffers");
+define_extension!(EXTDisjointTimerQuery, "EXT_disjoint_timer_query");
+define_extension!(EXTDisjointTimerQueryWebGL2, "EXT_disjoint_timer_query_webgl2");
+
+impl EXTDisjointTimerQuery {
+    pub const QUERY_COUNTER_BITS_EXT: GLenum = 0x8864;
+    pub const CURRENT_QUERY_EXT: GLenum = 0x8865;
+    pub const QUERY_RESULT_EXT: GLenum = 0x8866;
+    pub const QUERY_RESULT_AVAILABLE_EXT: GLenum = 0x8867;
+    pub const TIME_ELAPSED_EXT: GLenum = 0x88BF;
+    pub const TIMESTAMP_EXT: GLenum = 0x8E28;
+    pub const GPU_DISJOINT_EXT: GLenum = 0x8FBB;
+
+    pub fn create_query_ext(&self) -> Option<WebGLTimerQueryEXT> {
+        (js! { return @{self}.createQueryEXT(); } ).try_into().ok()
+    }
+
+    pub fn delete_query_ext(&self, query: Option<&WebGLTimerQueryEXT>) {
+        js!( @{self}.deleteQueryEXT(@{query}); );
+    }
+
+    pub fn begin_query_ext(&self, target: GLenum, query: &WebGLTimerQueryEXT) {
+        js!( @{self}.beginQueryEXT(@{target}, @{query}); );
+    }
+
+    pub fn end_query_ext(&self, target: GLenum) {
+        js!( @{self}.endQueryEXT(@{target}); );
+    }
+
+    pub fn get_query_object_ext(&self, query: &WebGLTimerQueryEXT, pname: GLenum) -> Value {
+        (js! { return @{self}.getQueryObjectEXT(@{query}, @{pname}); } ).try_into().unwrap()
+    }
+}
+
+/// Only adds the `TIME_ELAPSED_EXT` target and `GPU_DISJOINT_EXT` to
+/// WebGL2's own queries, with the values `EXTDisjointTimerQuery` has.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/EXT_disjoint_timer_query_webgl2
+impl EXTDisjointTimerQueryWebGL2 {
+    pub const QUERY_COUNTER_BITS_EXT: GLenum = 0x8864;
+    pub const TIME_ELAPSED_EXT: GLenum = 0x88BF;
+    pub const TIMESTAMP_EXT: GLenum = 0x8E28;
+    pub const GPU_DISJOINT_EXT: GLenum = 0x8FBB;
+}
+
+/// The query object of `EXT_disjoint_timer_query`. Browsers disagree on its
+/// class name, so any object is accepted.
+#[derive(Debug, Clone, ReferenceType)]
+pub struct WebGLTimerQueryEXT(Reference);
+
+impl InstanceOf for WebGLTimerQueryEXT {
+    #[inline]
+    fn instance_of( reference: &Reference ) -> bool {
+        js!(
+            return @{{reference}} instanceof Object;
+        ).try_into().unwrap()
+    }
+}
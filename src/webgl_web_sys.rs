@@ -0,0 +1,481 @@
+//! The subset of `webgl_rendering_context` the renderer uses, on top of
+//! `web-sys` instead of stdweb.
+//!
+//! Names and signatures follow the stdweb bindings so code written against
+//! them compiles with either, `main.rs` picks one with the `web-sys-backend`
+//! feature. Methods the two agree on come from `web_sys::WebGlRenderingContext`
+//! through `Deref`, only the ones that differ are defined here. Values of
+//! unknown type are wrapped in `Value`, which converts with stdweb's
+//! `TryInto` like the values the stdweb bindings return.
+use std::cell::Cell;
+use std::ops::Deref;
+
+use stdweb::unstable::TryFrom;
+use wasm_bindgen::{JsCast, JsValue};
+
+pub type GLenum = u32;
+pub type GLbitfield = u32;
+pub type GLboolean = bool;
+pub type GLbyte = i8;
+pub type GLshort = i16;
+pub type GLint = i32;
+pub type GLsizei = i32;
+pub type GLintptr = i64;
+pub type GLsizeiptr = i64;
+pub type GLubyte = u8;
+pub type GLushort = u16;
+pub type GLuint = u32;
+pub type GLfloat = f32;
+pub type GLclampf = f32;
+
+pub type WebGLBuffer = Tracked<web_sys::WebGlBuffer>;
+pub type WebGLFramebuffer = Tracked<web_sys::WebGlFramebuffer>;
+pub type WebGLProgram = Tracked<web_sys::WebGlProgram>;
+pub type WebGLRenderbuffer = Tracked<web_sys::WebGlRenderbuffer>;
+pub type WebGLShader = web_sys::WebGlShader;
+pub type WebGLTexture = Tracked<web_sys::WebGlTexture>;
+pub type WebGLUniformLocation = Tracked<web_sys::WebGlUniformLocation>;
+pub type WebGLActiveInfo = web_sys::WebGlActiveInfo;
+pub type WebGLTimerQueryEXT = web_sys::WebGlQuery;
+/// Only for its constants, the context is a `WebGLRenderingContext` either
+/// way.
+pub type WebGL2RenderingContext = web_sys::WebGl2RenderingContext;
+
+thread_local! {
+    /// The id the next tracked object gets.
+    static NEXT_ID: Cell<u32> = Cell::new(1);
+}
+
+/// A WebGL object with an id handed out when it was created, the objects
+/// the command buffer takes.
+///
+/// Telling two `JsValue`s apart is a call into JavaScript, comparing ids
+/// isn't. Clones keep the id, they refer to the same object.
+#[derive(Debug, Clone)]
+pub struct Tracked<T> {
+    id: u32,
+    object: T,
+}
+
+impl<T> Tracked<T> {
+    fn new(object: T) -> Self {
+        let id = NEXT_ID.with(|next| next.replace(next.get().wrapping_add(1)));
+        Self { id, object }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.object
+    }
+}
+
+impl<T: AsRef<JsValue>> AsRef<JsValue> for Tracked<T> {
+    fn as_ref(&self) -> &JsValue {
+        self.object.as_ref()
+    }
+}
+
+/// A value returned by a query whose type depends on what was asked for.
+#[derive(Debug, Clone)]
+pub struct Value(JsValue);
+
+impl TryFrom<Value> for bool {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, ()> {
+        value.0.as_bool().ok_or(())
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, ()> {
+        value.0.as_f64().ok_or(())
+    }
+}
+
+impl TryFrom<Value> for u32 {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, ()> {
+        value.0.as_f64().map(|number| number as u32).ok_or(())
+    }
+}
+
+impl TryFrom<Value> for i32 {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, ()> {
+        value.0.as_f64().map(|number| number as i32).ok_or(())
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, ()> {
+        value.0.as_string().ok_or(())
+    }
+}
+
+/// A WebGL extension, as returned by `get_extension`.
+pub trait Extension: Sized {
+    const NAME: &'static str;
+
+    fn from_object(object: js_sys::Object) -> Self;
+}
+
+macro_rules! extension {
+    ($type_:ty, $name:expr) => {
+        impl Extension for $type_ {
+            const NAME: &'static str = $name;
+
+            fn from_object(object: js_sys::Object) -> Self {
+                object.unchecked_into()
+            }
+        }
+    };
+}
+
+pub type OESElementIndexUint = web_sys::OesElementIndexUint;
+pub type OESStandardDerivatives = web_sys::OesStandardDerivatives;
+pub type EXTShaderTextureLod = web_sys::ExtShaderTextureLod;
+pub type EXTFragDepth = web_sys::ExtFragDepth;
+pub type WEBGLDrawBuffers = web_sys::WebglDrawBuffers;
+
+extension!(OESElementIndexUint, "OES_element_index_uint");
+extension!(OESStandardDerivatives, "OES_standard_derivatives");
+extension!(EXTShaderTextureLod, "EXT_shader_texture_lod");
+extension!(EXTFragDepth, "EXT_frag_depth");
+extension!(WEBGLDrawBuffers, "WEBGL_draw_buffers");
+
+/// https://developer.mozilla.org/en-US/docs/Web/API/EXT_disjoint_timer_query
+#[derive(Debug, Clone)]
+pub struct EXTDisjointTimerQuery(web_sys::ExtDisjointTimerQuery);
+
+impl EXTDisjointTimerQuery {
+    pub const QUERY_RESULT_EXT: GLenum = web_sys::ExtDisjointTimerQuery::QUERY_RESULT_EXT;
+    pub const QUERY_RESULT_AVAILABLE_EXT: GLenum = web_sys::ExtDisjointTimerQuery::QUERY_RESULT_AVAILABLE_EXT;
+    pub const TIME_ELAPSED_EXT: GLenum = web_sys::ExtDisjointTimerQuery::TIME_ELAPSED_EXT;
+    pub const GPU_DISJOINT_EXT: GLenum = web_sys::ExtDisjointTimerQuery::GPU_DISJOINT_EXT;
+
+    pub fn get_query_object_ext(&self, query: &WebGLTimerQueryEXT, pname: GLenum) -> Value {
+        Value(self.0.get_query_object_ext(query, pname))
+    }
+}
+
+impl Extension for EXTDisjointTimerQuery {
+    const NAME: &'static str = "EXT_disjoint_timer_query";
+
+    fn from_object(object: js_sys::Object) -> Self {
+        EXTDisjointTimerQuery(object.unchecked_into())
+    }
+}
+
+impl Deref for EXTDisjointTimerQuery {
+    type Target = web_sys::ExtDisjointTimerQuery;
+
+    fn deref(&self) -> &web_sys::ExtDisjointTimerQuery {
+        &self.0
+    }
+}
+
+/// Only adds the `TIME_ELAPSED_EXT` target and `GPU_DISJOINT_EXT` to
+/// WebGL2's own queries, with the values `EXTDisjointTimerQuery` has.
+///
+/// https://developer.mozilla.org/en-US/docs/Web/API/EXT_disjoint_timer_query_webgl2
+#[derive(Debug, Clone)]
+pub struct EXTDisjointTimerQueryWebGL2;
+
+impl EXTDisjointTimerQueryWebGL2 {
+    pub const TIME_ELAPSED_EXT: GLenum = web_sys::ExtDisjointTimerQuery::TIME_ELAPSED_EXT;
+    pub const GPU_DISJOINT_EXT: GLenum = web_sys::ExtDisjointTimerQuery::GPU_DISJOINT_EXT;
+}
+
+impl Extension for EXTDisjointTimerQueryWebGL2 {
+    const NAME: &'static str = "EXT_disjoint_timer_query_webgl2";
+
+    fn from_object(_: js_sys::Object) -> Self {
+        EXTDisjointTimerQueryWebGL2
+    }
+}
+
+/// https://developer.mozilla.org/en-US/docs/Web/API/WebGLRenderingContext
+#[derive(Debug, Clone)]
+pub struct WebGLRenderingContext(web_sys::WebGlRenderingContext);
+
+/// Declares constants of `WebGLRenderingContext` as those of web-sys.
+macro_rules! constants {
+    ($($name:ident),* $(,)?) => {
+        impl WebGLRenderingContext {
+            $(pub const $name: GLenum = web_sys::WebGlRenderingContext::$name;)*
+        }
+    };
+}
+
+constants!(
+    ACTIVE_ATTRIBUTES,
+    ACTIVE_UNIFORMS,
+    ARRAY_BUFFER,
+    BLEND,
+    BOOL,
+    BOOL_VEC2,
+    BOOL_VEC3,
+    BOOL_VEC4,
+    BYTE,
+    CLAMP_TO_EDGE,
+    COLOR_ATTACHMENT0,
+    COLOR_BUFFER_BIT,
+    COMPILE_STATUS,
+    CULL_FACE,
+    DEPTH_ATTACHMENT,
+    DEPTH_BUFFER_BIT,
+    DEPTH_COMPONENT16,
+    DEPTH_TEST,
+    ELEMENT_ARRAY_BUFFER,
+    FLOAT,
+    FLOAT_MAT2,
+    FLOAT_MAT3,
+    FLOAT_MAT4,
+    FLOAT_VEC2,
+    FLOAT_VEC3,
+    FLOAT_VEC4,
+    FRAGMENT_SHADER,
+    FRAMEBUFFER,
+    FRAMEBUFFER_COMPLETE,
+    INT,
+    INT_VEC2,
+    INT_VEC3,
+    INT_VEC4,
+    LEQUAL,
+    LINEAR,
+    LINK_STATUS,
+    NEAREST,
+    ONE,
+    ONE_MINUS_SRC_ALPHA,
+    RENDERBUFFER,
+    REPEAT,
+    RGBA,
+    SAMPLER_2D,
+    SAMPLER_CUBE,
+    SHADING_LANGUAGE_VERSION,
+    SHORT,
+    SRC_ALPHA,
+    STATIC_DRAW,
+    TEXTURE0,
+    TEXTURE1,
+    TEXTURE_2D,
+    TEXTURE_CUBE_MAP,
+    TEXTURE_CUBE_MAP_POSITIVE_X,
+    TEXTURE_MAG_FILTER,
+    TEXTURE_MIN_FILTER,
+    TEXTURE_WRAP_S,
+    TEXTURE_WRAP_T,
+    TRIANGLES,
+    UNSIGNED_BYTE,
+    UNSIGNED_INT,
+    UNSIGNED_SHORT,
+    VERTEX_SHADER,
+);
+
+impl WebGLRenderingContext {
+    /// Gets the WebGL2 context of the canvas with the given id, or its WebGL1
+    /// context where there's none.
+    ///
+    /// The rest of the app still holds stdweb elements, which web-sys can't
+    /// take, so the canvas is looked up again.
+    pub fn from_canvas_id(id: &str) -> Option<Self> {
+        let canvas = web_sys::window()?.document()?.get_element_by_id(id)?;
+        let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into().ok()?;
+        let context = match canvas.get_context("webgl2").ok()? {
+            Some(context) => context,
+            None => canvas.get_context("webgl").ok()??,
+        };
+        // A WebGL2 context isn't an instance of `WebGlRenderingContext`, it
+        // takes the same calls.
+        Some(WebGLRenderingContext(context.unchecked_into()))
+    }
+
+    pub fn canvas(&self) -> web_sys::HtmlCanvasElement {
+        self.0.canvas().unwrap().unchecked_into()
+    }
+
+    pub fn create_buffer(&self) -> Option<WebGLBuffer> {
+        self.0.create_buffer().map(Tracked::new)
+    }
+
+    pub fn create_framebuffer(&self) -> Option<WebGLFramebuffer> {
+        self.0.create_framebuffer().map(Tracked::new)
+    }
+
+    pub fn create_program(&self) -> Option<WebGLProgram> {
+        self.0.create_program().map(Tracked::new)
+    }
+
+    pub fn create_renderbuffer(&self) -> Option<WebGLRenderbuffer> {
+        self.0.create_renderbuffer().map(Tracked::new)
+    }
+
+    pub fn create_texture(&self) -> Option<WebGLTexture> {
+        self.0.create_texture().map(Tracked::new)
+    }
+
+    pub fn delete_buffer(&self, buffer: Option<&WebGLBuffer>) {
+        self.0.delete_buffer(buffer.map(Deref::deref));
+    }
+
+    pub fn delete_framebuffer(&self, framebuffer: Option<&WebGLFramebuffer>) {
+        self.0.delete_framebuffer(framebuffer.map(Deref::deref));
+    }
+
+    pub fn delete_program(&self, program: Option<&WebGLProgram>) {
+        self.0.delete_program(program.map(Deref::deref));
+    }
+
+    pub fn delete_renderbuffer(&self, renderbuffer: Option<&WebGLRenderbuffer>) {
+        self.0.delete_renderbuffer(renderbuffer.map(Deref::deref));
+    }
+
+    pub fn delete_texture(&self, texture: Option<&WebGLTexture>) {
+        self.0.delete_texture(texture.map(Deref::deref));
+    }
+
+    pub fn get_extension<E: Extension>(&self) -> Option<E> {
+        self.0.get_extension(E::NAME).ok()?.map(E::from_object)
+    }
+
+    pub fn get_parameter(&self, pname: GLenum) -> Value {
+        Value(self.0.get_parameter(pname).unwrap_or(JsValue::NULL))
+    }
+
+    pub fn get_program_parameter(&self, program: &WebGLProgram, pname: GLenum) -> Value {
+        Value(self.0.get_program_parameter(program, pname))
+    }
+
+    pub fn get_shader_parameter(&self, shader: &WebGLShader, pname: GLenum) -> Value {
+        Value(self.0.get_shader_parameter(shader, pname))
+    }
+
+    pub fn framebuffer_texture2_d(
+        &self,
+        target: GLenum,
+        attachment: GLenum,
+        textarget: GLenum,
+        texture: Option<&WebGLTexture>,
+        level: GLint,
+    ) {
+        self.0.framebuffer_texture_2d(target, attachment, textarget, texture.map(Deref::deref), level);
+    }
+
+    pub fn framebuffer_renderbuffer(
+        &self,
+        target: GLenum,
+        attachment: GLenum,
+        renderbuffertarget: GLenum,
+        renderbuffer: Option<&WebGLRenderbuffer>,
+    ) {
+        self.0.framebuffer_renderbuffer(target, attachment, renderbuffertarget, renderbuffer.map(Deref::deref));
+    }
+
+    pub fn get_uniform_location(&self, program: &WebGLProgram, name: &str) -> Option<WebGLUniformLocation> {
+        self.0.get_uniform_location(program, name).map(Tracked::new)
+    }
+
+    /// Uploads `data` to the buffer bound to `target`.
+    pub fn buffer_data_bytes(&self, target: GLenum, data: &[u8], usage: GLenum) {
+        self.0.buffer_data_with_u8_array(target, data, usage);
+    }
+
+    /// Specifies the storage of the `RGBA` texture bound to `target`, with
+    /// its pixels if there are any.
+    pub fn tex_image2_d_bytes(&self, target: GLenum, width: GLsizei, height: GLsizei, pixels: Option<&[u8]>) {
+        // Only fails for arguments that don't match the pixels' length.
+        self.0
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                target,
+                0,
+                Self::RGBA as i32,
+                width,
+                height,
+                0,
+                Self::RGBA,
+                Self::UNSIGNED_BYTE,
+                pixels,
+            )
+            .unwrap();
+    }
+
+    pub fn is_webgl2(&self) -> bool {
+        self.0.has_type::<web_sys::WebGl2RenderingContext>()
+    }
+
+    /// Specifies the storage of the `RGBA` texture bound to `TEXTURE_3D`,
+    /// only call it on WebGL2 contexts.
+    pub fn tex_image3_d_bytes(&self, width: GLsizei, height: GLsizei, depth: GLsizei, voxels: &[u8]) {
+        // Only fails for arguments that don't match the voxels' length.
+        self.0
+            .unchecked_ref::<web_sys::WebGl2RenderingContext>()
+            .tex_image_3d_with_opt_u8_array(
+                WebGL2RenderingContext::TEXTURE_3D,
+                0,
+                Self::RGBA as i32,
+                width,
+                height,
+                depth,
+                0,
+                Self::RGBA,
+                Self::UNSIGNED_BYTE,
+                Some(voxels),
+            )
+            .unwrap();
+    }
+
+    /// Creates a WebGL2 query, only call the query methods on WebGL2 contexts.
+    pub fn create_query(&self) -> Option<WebGLTimerQueryEXT> {
+        self.webgl2().create_query()
+    }
+
+    pub fn delete_query(&self, query: Option<&WebGLTimerQueryEXT>) {
+        self.webgl2().delete_query(query)
+    }
+
+    pub fn begin_query(&self, target: GLenum, query: &WebGLTimerQueryEXT) {
+        self.webgl2().begin_query(target, query)
+    }
+
+    pub fn end_query(&self, target: GLenum) {
+        self.webgl2().end_query(target)
+    }
+
+    pub fn get_query_parameter(&self, query: &WebGLTimerQueryEXT, pname: GLenum) -> Value {
+        Value(self.webgl2().get_query_parameter(query, pname))
+    }
+
+    fn webgl2(&self) -> &web_sys::WebGl2RenderingContext {
+        self.0.unchecked_ref::<web_sys::WebGl2RenderingContext>()
+    }
+
+    /// Reads the `RGBA` pixels of the bound framebuffer's bottom left corner.
+    pub fn read_pixels_bytes(&self, width: GLsizei, height: GLsizei, pixels: &mut [u8]) {
+        // Only fails for a buffer too small for the pixels.
+        self.0
+            .read_pixels_with_opt_u8_array(0, 0, width, height, Self::RGBA, Self::UNSIGNED_BYTE, Some(pixels))
+            .unwrap();
+    }
+}
+
+impl Deref for WebGLRenderingContext {
+    type Target = web_sys::WebGlRenderingContext;
+
+    fn deref(&self) -> &web_sys::WebGlRenderingContext {
+        &self.0
+    }
+}
@@ -0,0 +1,245 @@
+// GLSL ES mode, completer and signature help for the Ace editors.
+//
+// The vocabulary (types, qualifiers, built-in functions and the uniforms the
+// app injects) is handed over from Rust by `netshaderGlsl.install`.
+(function () {
+    var vocabulary = {
+        types: [],
+        qualifiers: [],
+        keywords: [],
+        variables: [],
+        uniforms: [],
+        functions: []
+    };
+
+    ace.define("ace/mode/glsl_es_highlight_rules", ["require", "exports", "module", "ace/lib/oop", "ace/mode/text_highlight_rules"], function (require, exports) {
+        var oop = require("ace/lib/oop");
+        var TextHighlightRules = require("ace/mode/text_highlight_rules").TextHighlightRules;
+
+        var GlslEsHighlightRules = function () {
+            var keywordMapper = this.createKeywordMapper({
+                "storage.type": vocabulary.types.join("|"),
+                "storage.modifier": vocabulary.qualifiers.join("|"),
+                "keyword.control": vocabulary.keywords.join("|"),
+                "support.function": vocabulary.functions.map(function (f) { return f.name; }).join("|"),
+                "variable.language": vocabulary.variables.map(function (v) { return v.name; })
+                    .concat(vocabulary.uniforms.map(function (u) { return u.name; })).join("|")
+            }, "identifier");
+
+            this.$rules = {
+                "start": [
+                    { token: "comment", regex: "\\/\\/.*$" },
+                    { token: "comment", regex: "\\/\\*", next: "comment" },
+                    { token: "keyword.preprocessor", regex: "^\\s*#\\s*\\w+.*$" },
+                    { token: "constant.numeric", regex: "0[xX][0-9a-fA-F]+[uU]?\\b" },
+                    { token: "constant.numeric", regex: "(?:\\d+\\.\\d*|\\.\\d+|\\d+)(?:[eE][+-]?\\d+)?[uUfF]?\\b" },
+                    { token: keywordMapper, regex: "[a-zA-Z_][a-zA-Z0-9_]*\\b" },
+                    { token: "keyword.operator", regex: "\\+\\+|--|<<=?|>>=?|[!=<>]=|&&|\\|\\||\\^\\^|[-+*/%&|^]=?|[=!<>~?:]" },
+                    { token: "punctuation.operator", regex: "[;,.]" },
+                    { token: "paren.lparen", regex: "[\\[({]" },
+                    { token: "paren.rparen", regex: "[\\])}]" },
+                    { token: "text", regex: "\\s+" }
+                ],
+                "comment": [
+                    { token: "comment", regex: "\\*\\/", next: "start" },
+                    { defaultToken: "comment" }
+                ]
+            };
+        };
+
+        oop.inherits(GlslEsHighlightRules, TextHighlightRules);
+        exports.GlslEsHighlightRules = GlslEsHighlightRules;
+    });
+
+    ace.define("ace/mode/glsl_es", ["require", "exports", "module", "ace/lib/oop", "ace/mode/text", "ace/mode/glsl_es_highlight_rules", "ace/mode/behaviour/cstyle", "ace/mode/folding/cstyle"], function (require, exports) {
+        var oop = require("ace/lib/oop");
+        var TextMode = require("ace/mode/text").Mode;
+        var GlslEsHighlightRules = require("ace/mode/glsl_es_highlight_rules").GlslEsHighlightRules;
+        var CstyleBehaviour = require("ace/mode/behaviour/cstyle").CstyleBehaviour;
+        var CStyleFoldMode = require("ace/mode/folding/cstyle").FoldMode;
+
+        var Mode = function () {
+            this.HighlightRules = GlslEsHighlightRules;
+            this.$behaviour = new CstyleBehaviour();
+            this.foldingRules = new CStyleFoldMode();
+        };
+        oop.inherits(Mode, TextMode);
+
+        (function () {
+            this.lineCommentStart = "//";
+            this.blockComment = { start: "/*", end: "*/" };
+
+            this.getNextLineIndent = function (state, line, tab) {
+                var indent = this.$getIndent(line);
+                if (/[{(\[]\s*$/.test(line)) {
+                    indent += tab;
+                }
+                return indent;
+            };
+
+            this.checkOutdent = function (state, line, input) {
+                return /^\s+$/.test(line) && /^\s*\}/.test(input);
+            };
+
+            this.autoOutdent = function (state, doc, row) {
+                var line = doc.getLine(row);
+                var match = line.match(/^(\s*)\}/);
+                if (!match) {
+                    return;
+                }
+
+                var openBracePos = doc.findMatchingBracket({ row: row, column: match[1].length });
+                if (!openBracePos) {
+                    return;
+                }
+
+                var indent = this.$getIndent(doc.getLine(openBracePos.row));
+                doc.replace(new (require("ace/range").Range)(row, 0, row, match[1].length), indent);
+            };
+
+            this.$id = "ace/mode/glsl_es";
+        }).call(Mode.prototype);
+
+        exports.Mode = Mode;
+    });
+
+    var PRECISIONS = "(?:(?:lowp|mediump|highp)\\s+)?";
+
+    // Uniforms, varyings and functions declared in the editor's own buffer.
+    function declarations(source) {
+        var found = { uniforms: [], functions: [] };
+        var match;
+
+        var uniformPattern = new RegExp("\\b(uniform|varying|attribute|in|out)\\s+" + PRECISIONS + "(\\w+)\\s+(\\w+)", "g");
+        while ((match = uniformPattern.exec(source)) !== null) {
+            found.uniforms.push({ qualifier: match[1], type: match[2], name: match[3] });
+        }
+
+        var functionPattern = new RegExp("^\\s*" + PRECISIONS + "(\\w+)\\s+(\\w+)\\s*\\(([^)]*)\\)\\s*\\{", "gm");
+        while ((match = functionPattern.exec(source)) !== null) {
+            if (vocabulary.keywords.indexOf(match[1]) !== -1) {
+                continue;
+            }
+            found.functions.push({
+                name: match[2],
+                signatures: [match[1] + " " + match[2] + "(" + match[3].trim().replace(/\s+/g, " ") + ")"]
+            });
+        }
+
+        return found;
+    }
+
+    function escapeHtml(text) {
+        return text.replace(/&/g, "&amp;").replace(/</g, "&lt;").replace(/>/g, "&gt;");
+    }
+
+    function signaturesHtml(signatures) {
+        return signatures.map(function (s) { return "<code>" + escapeHtml(s) + "</code>"; }).join("<br/>");
+    }
+
+    var completer = {
+        identifierRegexps: [/[a-zA-Z_0-9]/],
+        getCompletions: function (editor, session, pos, prefix, callback) {
+            var completions = [];
+            var add = function (words, meta, score) {
+                words.forEach(function (word) {
+                    completions.push({ caption: word, value: word, meta: meta, score: score });
+                });
+            };
+
+            add(vocabulary.types, "type", 80);
+            add(vocabulary.qualifiers, "qualifier", 70);
+            add(vocabulary.keywords, "keyword", 70);
+
+            vocabulary.variables.forEach(function (v) {
+                completions.push({ caption: v.name, value: v.name, meta: v.type, score: 85 });
+            });
+
+            vocabulary.uniforms.forEach(function (u) {
+                completions.push({
+                    caption: u.name,
+                    value: u.name,
+                    meta: "uniform " + u.type,
+                    score: 100,
+                    docHTML: "<code>uniform " + u.type + " " + u.name + "</code><br/>" + escapeHtml(u.doc)
+                });
+            });
+
+            vocabulary.functions.forEach(function (f) {
+                completions.push({ caption: f.name, value: f.name, meta: "built-in", score: 90, docHTML: signaturesHtml(f.signatures) });
+            });
+
+            var local = declarations(session.getValue());
+            local.uniforms.forEach(function (u) {
+                completions.push({ caption: u.name, value: u.name, meta: u.qualifier + " " + u.type, score: 110 });
+            });
+            local.functions.forEach(function (f) {
+                completions.push({ caption: f.name, value: f.name, meta: "function", score: 110, docHTML: signaturesHtml(f.signatures) });
+            });
+
+            callback(null, completions);
+        }
+    };
+
+    // Name of the function whose argument list the cursor is in, if any.
+    function enclosingCall(session, pos) {
+        var text = session.getLine(pos.row).slice(0, pos.column);
+        var depth = 0;
+        for (var i = text.length - 1; i >= 0; i--) {
+            var c = text[i];
+            if (c === ")") {
+                depth++;
+            } else if (c === "(") {
+                if (depth === 0) {
+                    var match = text.slice(0, i).match(/(\w+)\s*$/);
+                    return match ? match[1] : null;
+                }
+                depth--;
+            }
+        }
+        return null;
+    }
+
+    function installSignatureHelp(editor) {
+        var tooltip = document.createElement("div");
+        tooltip.className = "ace_tooltip";
+        tooltip.style.display = "none";
+        tooltip.style.position = "fixed";
+        tooltip.style.pointerEvents = "none";
+        document.body.appendChild(tooltip);
+
+        var update = function () {
+            var pos = editor.getCursorPosition();
+            var name = enclosingCall(editor.session, pos);
+            var functions = vocabulary.functions.concat(declarations(editor.getValue()).functions);
+            var found = name && functions.filter(function (f) { return f.name === name; })[0];
+
+            if (!found || !editor.isFocused()) {
+                tooltip.style.display = "none";
+                return;
+            }
+
+            var coords = editor.renderer.textToScreenCoordinates(pos.row, pos.column);
+            tooltip.innerHTML = signaturesHtml(found.signatures);
+            tooltip.style.left = coords.pageX + "px";
+            tooltip.style.top = (coords.pageY + editor.renderer.lineHeight) + "px";
+            tooltip.style.display = "block";
+        };
+
+        editor.selection.on("changeCursor", update);
+        editor.on("blur", function () { tooltip.style.display = "none"; });
+    }
+
+    window.netshaderGlsl = {
+        install: function (editor, words) {
+            vocabulary = words;
+            editor.getSession().setMode("ace/mode/glsl_es");
+            editor.setOptions({
+                enableBasicAutocompletion: true,
+                enableLiveAutocompletion: true
+            });
+            editor.completers = [completer];
+            installSignatureHelp(editor);
+        }
+    };
+})();